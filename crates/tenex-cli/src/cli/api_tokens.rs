@@ -0,0 +1,289 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::config::CliConfig;
+
+/// Prefix of generated token secrets, so leaked tokens are recognizable
+const TOKEN_SECRET_PREFIX: &str = "tenex-";
+
+/// An HTTP API token persisted in the daemon config.
+/// Only the SHA-256 hash of the secret is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    /// Short public identifier used to list and revoke the token
+    pub id: String,
+    /// Human-readable label
+    pub name: String,
+    /// Hex SHA-256 of the token secret
+    pub token_hash: String,
+    /// Project d-tags this token may access
+    pub projects: Vec<String>,
+    /// Agent slugs this token may address (empty = any agent in the allowed projects)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub agents: Vec<String>,
+    /// Unix timestamp (seconds) when the token was created
+    pub created_at: u64,
+}
+
+impl ApiToken {
+    /// Whether the token grants access to the project with this d-tag
+    pub fn allows_project(&self, project_dtag: &str) -> bool {
+        self.projects.iter().any(|p| p == project_dtag)
+    }
+
+    /// Whether the token may address the agent with this slug
+    pub fn allows_agent(&self, agent_slug: &str) -> bool {
+        self.agents.is_empty() || self.agents.iter().any(|a| a == agent_slug)
+    }
+
+    /// JSON summary for listings (never includes the hash)
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "projects": self.projects,
+            "agents": self.agents,
+            "created_at": self.created_at,
+        })
+    }
+}
+
+/// Hex SHA-256 of a token secret
+fn hash_token(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Generate a new random token secret
+fn generate_secret() -> String {
+    format!(
+        "{}{}{}",
+        TOKEN_SECRET_PREFIX,
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// API tokens loaded from `config.json` in the data directory.
+/// Shared between the daemon (token management) and the HTTP server (auth).
+#[derive(Debug)]
+pub struct ApiTokenRegistry {
+    config_path: PathBuf,
+    tokens: Vec<ApiToken>,
+}
+
+impl ApiTokenRegistry {
+    /// Load tokens from data_dir/config.json (empty if missing or unreadable)
+    pub fn load(data_dir: &Path) -> Self {
+        let config_path = data_dir.join("config.json");
+        let tokens = if config_path.exists() {
+            match CliConfig::load(&config_path) {
+                Ok(config) => config.api_tokens,
+                Err(e) => {
+                    eprintln!("Warning: Failed to load API tokens: {}", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            config_path,
+            tokens,
+        }
+    }
+
+    /// Authentication is enforced once at least one token exists
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    pub fn tokens(&self) -> &[ApiToken] {
+        &self.tokens
+    }
+
+    /// Find the token matching a presented secret
+    pub fn authenticate(&self, secret: &str) -> Option<&ApiToken> {
+        let hash = hash_token(secret.trim());
+        self.tokens.iter().find(|token| token.token_hash == hash)
+    }
+
+    /// Create and persist a new token. Returns the token and its secret;
+    /// the secret is not stored and cannot be shown again.
+    pub fn create(
+        &mut self,
+        name: &str,
+        projects: Vec<String>,
+        agents: Vec<String>,
+    ) -> Result<(ApiToken, String)> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Token name cannot be empty");
+        }
+        if projects.is_empty() {
+            anyhow::bail!("At least one project is required");
+        }
+        if self.tokens.iter().any(|token| token.name == name) {
+            anyhow::bail!("A token named '{}' already exists", name);
+        }
+
+        let secret = generate_secret();
+        let token = ApiToken {
+            id: format!("tok_{}", &uuid::Uuid::new_v4().simple().to_string()[..12]),
+            name: name.to_string(),
+            token_hash: hash_token(&secret),
+            projects,
+            agents,
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        self.tokens.push(token.clone());
+        if let Err(e) = self.persist() {
+            self.tokens.pop();
+            return Err(e);
+        }
+
+        Ok((token, secret))
+    }
+
+    /// Revoke a token by id or name. Returns the removed token, if any.
+    pub fn revoke(&mut self, id_or_name: &str) -> Result<Option<ApiToken>> {
+        let Some(index) = self
+            .tokens
+            .iter()
+            .position(|token| token.id == id_or_name || token.name == id_or_name)
+        else {
+            return Ok(None);
+        };
+
+        let token = self.tokens.remove(index);
+        if let Err(e) = self.persist() {
+            self.tokens.insert(index, token);
+            return Err(e);
+        }
+
+        Ok(Some(token))
+    }
+
+    /// Write the token list back to config.json, preserving the other settings
+    fn persist(&self) -> Result<()> {
+        let mut config = if self.config_path.exists() {
+            CliConfig::load(&self.config_path)?
+        } else {
+            CliConfig::default()
+        };
+        config.api_tokens = self.tokens.clone();
+        config
+            .save(&self.config_path)
+            .context("Failed to save API tokens")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "tenex-api-tokens-{}-{}",
+            name,
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_create_authenticate_revoke() {
+        let dir = temp_data_dir("lifecycle");
+        let mut registry = ApiTokenRegistry::load(&dir);
+        assert!(!registry.is_enabled());
+
+        let (token, secret) = registry
+            .create("voice", vec!["my-project".to_string()], vec![])
+            .unwrap();
+        assert!(secret.starts_with(TOKEN_SECRET_PREFIX));
+        assert_ne!(token.token_hash, secret);
+        assert_eq!(registry.authenticate(&secret).unwrap().id, token.id);
+        assert!(registry.authenticate("tenex-wrong").is_none());
+
+        // Tokens survive a reload from disk
+        let reloaded = ApiTokenRegistry::load(&dir);
+        assert!(reloaded.authenticate(&secret).is_some());
+
+        assert!(registry.revoke("voice").unwrap().is_some());
+        assert!(registry.revoke(&token.id).unwrap().is_none());
+        assert!(ApiTokenRegistry::load(&dir).authenticate(&secret).is_none());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_persist_preserves_credentials() {
+        let dir = temp_data_dir("credentials");
+        std::fs::write(
+            dir.join("config.json"),
+            r#"{"credentials":{"key":"nsec1abc123"}}"#,
+        )
+        .unwrap();
+
+        let mut registry = ApiTokenRegistry::load(&dir);
+        registry
+            .create("ci", vec!["my-project".to_string()], vec![])
+            .unwrap();
+
+        let config = CliConfig::load(&dir.join("config.json")).unwrap();
+        assert_eq!(config.credentials.unwrap().key, "nsec1abc123");
+        assert_eq!(config.api_tokens.len(), 1);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_create_validation() {
+        let dir = temp_data_dir("validation");
+        let mut registry = ApiTokenRegistry::load(&dir);
+        assert!(registry.create("", vec!["p".to_string()], vec![]).is_err());
+        assert!(registry.create("no-projects", vec![], vec![]).is_err());
+        registry
+            .create("dup", vec!["p".to_string()], vec![])
+            .unwrap();
+        assert!(registry
+            .create("dup", vec!["p".to_string()], vec![])
+            .is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_token_scope() {
+        let token = ApiToken {
+            id: "tok_1".to_string(),
+            name: "scoped".to_string(),
+            token_hash: hash_token("secret"),
+            projects: vec!["alpha".to_string()],
+            agents: vec!["planner".to_string()],
+            created_at: 0,
+        };
+        assert!(token.allows_project("alpha"));
+        assert!(!token.allows_project("beta"));
+        assert!(token.allows_agent("planner"));
+        assert!(!token.allows_agent("coder"));
+
+        let unscoped = ApiToken {
+            agents: vec![],
+            ..token
+        };
+        assert!(unscoped.allows_agent("coder"));
+    }
+}
@@ -0,0 +1,314 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, Result};
+use tenex_core::models::{AskEvent, AskQuestion};
+
+use super::protocol::{AskAnswer, AskAnswerValue};
+
+/// Serialize an ask event's questions for CLI output
+pub(super) fn ask_to_json(ask: &AskEvent) -> serde_json::Value {
+    let questions: Vec<serde_json::Value> = ask
+        .questions
+        .iter()
+        .map(|q| match q {
+            AskQuestion::SingleSelect {
+                title,
+                question,
+                suggestions,
+            } => serde_json::json!({
+                "type": "single_select",
+                "title": title,
+                "question": question,
+                "suggestions": suggestions,
+            }),
+            AskQuestion::MultiSelect {
+                title,
+                question,
+                options,
+            } => serde_json::json!({
+                "type": "multi_select",
+                "title": title,
+                "question": question,
+                "options": options,
+            }),
+        })
+        .collect();
+
+    serde_json::json!({
+        "title": ask.title,
+        "context": ask.context,
+        "questions": questions,
+    })
+}
+
+/// Check that `answers` cover every question of the ask with values it
+/// accepts, and format them as the markdown reply the TUI and FFI publish.
+pub(super) fn format_ask_answers(ask: &AskEvent, answers: &[AskAnswer]) -> Result<String, String> {
+    for answer in answers {
+        let known = ask
+            .questions
+            .iter()
+            .any(|q| question_title(q) == answer.question);
+        if !known {
+            return Err(format!("Unknown question '{}'", answer.question));
+        }
+        if answers
+            .iter()
+            .filter(|a| a.question == answer.question)
+            .count()
+            > 1
+        {
+            return Err(format!("Question '{}' answered twice", answer.question));
+        }
+    }
+
+    let mut response = String::new();
+    for question in &ask.questions {
+        let title = question_title(question);
+        let Some(answer) = answers.iter().find(|a| a.question == title) else {
+            return Err(format!("Question '{}' is not answered", title));
+        };
+
+        response.push_str(&format!("## {}\n\n", title));
+        match (&answer.value, question) {
+            (AskAnswerValue::CustomText { value }, _) => {
+                if value.trim().is_empty() {
+                    return Err(format!("Answer to '{}' is empty", title));
+                }
+                response.push_str(&format!("{}\n\n", value.trim()));
+            }
+            (
+                AskAnswerValue::SingleSelect { value },
+                AskQuestion::SingleSelect { suggestions, .. },
+            ) => {
+                if !suggestions.contains(value) {
+                    return Err(format!(
+                        "'{}' is not a suggestion for '{}' (use custom_text for other answers)",
+                        value, title
+                    ));
+                }
+                response.push_str(&format!("{}\n\n", value));
+            }
+            (AskAnswerValue::MultiSelect { values }, AskQuestion::MultiSelect { options, .. }) => {
+                if values.is_empty() {
+                    return Err(format!("Select at least one option for '{}'", title));
+                }
+                if let Some(value) = values.iter().find(|v| !options.contains(v)) {
+                    return Err(format!("'{}' is not an option for '{}'", value, title));
+                }
+                for value in values {
+                    response.push_str(&format!("- {}\n", value));
+                }
+                response.push('\n');
+            }
+            (AskAnswerValue::SingleSelect { .. }, AskQuestion::MultiSelect { .. }) => {
+                return Err(format!("'{}' is a multi_select question", title));
+            }
+            (AskAnswerValue::MultiSelect { .. }, AskQuestion::SingleSelect { .. }) => {
+                return Err(format!("'{}' is a single_select question", title));
+            }
+        }
+    }
+
+    Ok(response.trim().to_string())
+}
+
+fn question_title(question: &AskQuestion) -> &str {
+    match question {
+        AskQuestion::SingleSelect { title, .. } | AskQuestion::MultiSelect { title, .. } => title,
+    }
+}
+
+/// Prompt for an answer to each question of a `show_ask` result.
+/// Choices are picked by number; anything else is taken as custom text.
+pub(super) fn prompt_ask_answers(
+    ask: &serde_json::Value,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<Vec<AskAnswer>> {
+    if let Some(title) = ask["title"].as_str() {
+        writeln!(output, "{}", title)?;
+    }
+    if let Some(context) = ask["context"].as_str().filter(|c| !c.is_empty()) {
+        writeln!(output, "{}", context)?;
+    }
+
+    let mut answers = Vec::new();
+    for question in ask["questions"].as_array().into_iter().flatten() {
+        let title = question["title"].as_str().unwrap_or_default().to_string();
+        let multi = question["type"] == "multi_select";
+        let choices: Vec<String> = question[if multi { "options" } else { "suggestions" }]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c.as_str().map(String::from))
+            .collect();
+
+        writeln!(output, "\n## {}", title)?;
+        writeln!(
+            output,
+            "{}",
+            question["question"].as_str().unwrap_or_default()
+        )?;
+        for (i, choice) in choices.iter().enumerate() {
+            writeln!(output, "  {}. {}", i + 1, choice)?;
+        }
+
+        let value = loop {
+            let hint = match (choices.is_empty(), multi) {
+                (true, _) => "Answer",
+                (false, false) => "Number or custom answer",
+                (false, true) => "Numbers (comma-separated) or custom answer",
+            };
+            write!(output, "{}: ", hint)?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                bail!("No answer given for '{}'", title);
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            break parse_choice(line, &choices, multi);
+        };
+        answers.push(AskAnswer {
+            question: title,
+            value,
+        });
+    }
+
+    Ok(answers)
+}
+
+/// Numbers pick from `choices`; any other input is custom text
+fn parse_choice(line: &str, choices: &[String], multi: bool) -> AskAnswerValue {
+    let picked: Option<Vec<String>> = line
+        .split(if multi { ',' } else { '\n' })
+        .map(|part| {
+            part.trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| choices.get(i).cloned())
+        })
+        .collect();
+
+    match picked {
+        Some(values) if multi => AskAnswerValue::MultiSelect { values },
+        Some(mut values) => AskAnswerValue::SingleSelect {
+            value: values.remove(0),
+        },
+        None => AskAnswerValue::CustomText {
+            value: line.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ask() -> AskEvent {
+        AskEvent {
+            title: Some("Setup".to_string()),
+            context: "Before I start".to_string(),
+            questions: vec![
+                AskQuestion::SingleSelect {
+                    title: "Database".to_string(),
+                    question: "Which database?".to_string(),
+                    suggestions: vec!["Postgres".to_string(), "SQLite".to_string()],
+                },
+                AskQuestion::MultiSelect {
+                    title: "Targets".to_string(),
+                    question: "Which platforms?".to_string(),
+                    options: vec!["linux".to_string(), "macos".to_string()],
+                },
+            ],
+        }
+    }
+
+    fn answer(question: &str, value: AskAnswerValue) -> AskAnswer {
+        AskAnswer {
+            question: question.to_string(),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_format_ask_answers() {
+        let answers = vec![
+            answer(
+                "Targets",
+                AskAnswerValue::MultiSelect {
+                    values: vec!["linux".to_string(), "macos".to_string()],
+                },
+            ),
+            answer(
+                "Database",
+                AskAnswerValue::CustomText {
+                    value: "MySQL".to_string(),
+                },
+            ),
+        ];
+        assert_eq!(
+            format_ask_answers(&ask(), &answers).unwrap(),
+            "## Database\n\nMySQL\n\n## Targets\n\n- linux\n- macos"
+        );
+    }
+
+    #[test]
+    fn test_format_ask_answers_validates() {
+        let single = |value: &str| {
+            answer(
+                "Database",
+                AskAnswerValue::SingleSelect {
+                    value: value.to_string(),
+                },
+            )
+        };
+        let targets = answer(
+            "Targets",
+            AskAnswerValue::MultiSelect {
+                values: vec!["linux".to_string()],
+            },
+        );
+
+        assert!(format_ask_answers(&ask(), &[single("Postgres"), targets.clone()]).is_ok());
+        assert!(format_ask_answers(&ask(), &[single("MySQL"), targets.clone()]).is_err());
+        assert!(format_ask_answers(&ask(), &[single("Postgres")]).is_err());
+        assert!(format_ask_answers(
+            &ask(),
+            &[single("Postgres"), single("SQLite"), targets.clone()]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_prompt_ask_answers() {
+        let mut input = "\n2\n1, 2\n".as_bytes();
+        let mut output = Vec::new();
+        let answers = prompt_ask_answers(&ask_to_json(&ask()), &mut input, &mut output).unwrap();
+        assert_eq!(
+            answers,
+            vec![
+                answer(
+                    "Database",
+                    AskAnswerValue::SingleSelect {
+                        value: "SQLite".to_string()
+                    }
+                ),
+                answer(
+                    "Targets",
+                    AskAnswerValue::MultiSelect {
+                        values: vec!["linux".to_string(), "macos".to_string()]
+                    }
+                ),
+            ]
+        );
+
+        let mut input = "3\n".as_bytes();
+        assert!(prompt_ask_answers(&ask_to_json(&ask()), &mut input, &mut output).is_err());
+    }
+}
@@ -8,9 +8,14 @@ use std::{collections::HashSet, io};
 
 use anyhow::{Context, Result};
 
+use super::asks::prompt_ask_answers;
 use super::config::CliConfig;
 use super::daemon::socket_path as get_socket_path;
-use super::protocol::{CliCommand, Response};
+use super::documents::{render_lesson, render_report};
+use super::follow::{follow_thread, wait_until_idle};
+use super::mcp;
+use super::protocol::{CliCommand, DocumentFormat, Notification, Response, SubscribeFilter};
+use super::usage::{format_usage_csv, format_usage_table, UsageFormat, UsageRow};
 
 const MAX_WAIT_SECONDS: u64 = 10;
 const POLL_INTERVAL_MS: u64 = 100;
@@ -18,6 +23,8 @@ const BOOT_WAIT_TIMEOUT_SECS: u64 = 60;
 const BOOT_POLL_INTERVAL_MS: u64 = 500;
 const REPLY_POLL_INTERVAL_MS: u64 = 500;
 const BUNKER_WATCH_POLL_INTERVAL_MS: u64 = 500;
+/// Default limit for --until-idle when --wait is not given
+const UNTIL_IDLE_TIMEOUT_SECS: u64 = 3600;
 
 #[derive(Debug, Clone, serde::Deserialize)]
 struct PendingBunkerRequest {
@@ -88,7 +95,7 @@ fn spawn_daemon(data_dir: &Path, config: Option<&CliConfig>) -> Result<()> {
 }
 
 /// Send a command to the daemon and return the response
-fn send_command_raw(
+pub(super) fn send_command_raw(
     command: &CliCommand,
     data_dir: &Path,
     config: Option<&CliConfig>,
@@ -114,6 +121,84 @@ fn send_command_raw(
     Ok(serde_json::from_str(&response_line)?)
 }
 
+/// Notifications from a `subscribe` connection, one per NDJSON line
+pub(super) struct NotificationStream {
+    reader: BufReader<UnixStream>,
+    /// Partial line kept across read timeouts
+    line: Vec<u8>,
+}
+
+impl NotificationStream {
+    /// Wait up to `timeout` for the next notification. Returns `Ok(None)` on
+    /// timeout and an error once the daemon closes the connection.
+    pub(super) fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<Notification>> {
+        self.reader.get_ref().set_read_timeout(Some(timeout))?;
+        match self.read_notification() {
+            Some(Ok(notification)) => Ok(Some(notification)),
+            Some(Err(e)) => match e.downcast_ref::<io::Error>().map(|e| e.kind()) {
+                Some(io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Ok(None),
+                _ => Err(e),
+            },
+            None => anyhow::bail!("Daemon closed the subscription"),
+        }
+    }
+
+    fn read_notification(&mut self) -> Option<Result<Notification>> {
+        match self.reader.read_until(b'\n', &mut self.line) {
+            Ok(0) => None,
+            Ok(_) => {
+                let result = serde_json::from_slice(&self.line).map_err(Into::into);
+                self.line.clear();
+                Some(result)
+            }
+            // Bytes read before a timeout stay in `line` for the next call
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+impl Iterator for NotificationStream {
+    type Item = Result<Notification>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.reader.get_ref().set_read_timeout(None) {
+            return Some(Err(e.into()));
+        }
+        self.read_notification()
+    }
+}
+
+/// Open a `subscribe` connection to the daemon
+pub(super) fn subscribe(
+    filter: SubscribeFilter,
+    data_dir: &Path,
+    config: Option<&CliConfig>,
+) -> Result<NotificationStream> {
+    let command = CliCommand::Subscribe { filter };
+    let request = command
+        .to_request(1)
+        .context("Command cannot be sent to daemon")?;
+
+    let stream = connect_to_daemon(data_dir, config)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    writeln!(writer, "{}", serde_json::to_string(&request)?)?;
+    writer.flush()?;
+
+    let mut response_line = String::new();
+    reader.read_line(&mut response_line)?;
+    let response: Response = serde_json::from_str(&response_line)?;
+    if let Some(error) = response.error {
+        anyhow::bail!("Error [{}]: {}", error.code, error.message);
+    }
+
+    Ok(NotificationStream {
+        reader,
+        line: Vec::new(),
+    })
+}
+
 /// Get project info if online, None otherwise
 fn get_booted_project(
     project_slug: &str,
@@ -142,7 +227,7 @@ fn get_booted_project(
 }
 
 /// Get messages from a thread, returns Vec of message objects
-fn get_thread_messages(
+pub(super) fn get_thread_messages(
     thread_id: &str,
     data_dir: &Path,
     config: Option<&CliConfig>,
@@ -209,6 +294,13 @@ fn wait_for_reply(
     Ok(false)
 }
 
+/// --until-idle gave up before the thread went idle: the summary is already
+/// printed, so exit non-zero for scripts to detect
+fn exit_idle_timeout(timeout_secs: u64) -> ! {
+    eprintln!("Error: agents still working after {} seconds", timeout_secs);
+    std::process::exit(1);
+}
+
 /// Send a command to the daemon and print the response
 pub fn send_command(
     command: CliCommand,
@@ -220,6 +312,27 @@ pub fn send_command(
         return watch_bunker_requests(data_dir, config.as_ref());
     }
 
+    if let CliCommand::Follow { ref thread_id } = command {
+        return follow_thread(thread_id, data_dir, config.as_ref());
+    }
+
+    if let CliCommand::Mcp = command {
+        return mcp::serve(data_dir, config.as_ref());
+    }
+
+    // Stream notifications until the daemon closes the connection
+    if let CliCommand::Subscribe { filter } = command {
+        for notification in subscribe(filter, data_dir, config.as_ref())? {
+            let notification = notification?;
+            if pretty {
+                println!("{}", serde_json::to_string_pretty(&notification)?);
+            } else {
+                println!("{}", serde_json::to_string(&notification)?);
+            }
+        }
+        return Ok(());
+    }
+
     // Handle boot-project --wait specially
     if let CliCommand::BootProject {
         ref project_slug,
@@ -252,12 +365,20 @@ pub fn send_command(
         std::process::exit(1);
     }
 
-    // Handle create-thread with --wait
+    // Handle create-thread with --wait or --until-idle
     if let CliCommand::CreateThread {
-        wait_secs: Some(wait_secs),
+        wait_secs,
+        until_idle,
         ..
     } = command
     {
+        if wait_secs.is_none() && !until_idle {
+            return print_response(
+                send_command_raw(&command, data_dir, config.as_ref())?,
+                pretty,
+            );
+        }
+
         let response = send_command_raw(&command, data_dir, config.as_ref())?;
 
         if let Some(error) = response.error {
@@ -275,14 +396,28 @@ pub fn send_command(
 
             // Extract thread_id for waiting
             if let Some(thread_id) = result.get("thread_id").and_then(|t| t.as_str()) {
-                wait_for_reply(
-                    thread_id,
-                    thread_id,
-                    wait_secs,
-                    data_dir,
-                    config.as_ref(),
-                    pretty,
-                )?;
+                if until_idle {
+                    let timeout_secs = wait_secs.unwrap_or(UNTIL_IDLE_TIMEOUT_SECS);
+                    if !wait_until_idle(
+                        thread_id,
+                        thread_id,
+                        timeout_secs,
+                        data_dir,
+                        config.as_ref(),
+                        pretty,
+                    )? {
+                        exit_idle_timeout(timeout_secs);
+                    }
+                } else if let Some(wait_secs) = wait_secs {
+                    wait_for_reply(
+                        thread_id,
+                        thread_id,
+                        wait_secs,
+                        data_dir,
+                        config.as_ref(),
+                        pretty,
+                    )?;
+                }
             } else {
                 eprintln!("Warning: Could not get thread_id, cannot wait for reply");
             }
@@ -291,13 +426,20 @@ pub fn send_command(
         return Ok(());
     }
 
-    // Handle send-message with --wait
+    // Handle send-message with --wait or --until-idle
     if let CliCommand::SendMessage {
         ref thread_id,
-        wait_secs: Some(wait_secs),
+        wait_secs,
+        until_idle,
         ..
     } = command
     {
+        if wait_secs.is_none() && !until_idle {
+            return print_response(
+                send_command_raw(&command, data_dir, config.as_ref())?,
+                pretty,
+            );
+        }
         let thread_id_for_wait = thread_id.clone();
         let response = send_command_raw(&command, data_dir, config.as_ref())?;
 
@@ -319,22 +461,120 @@ pub fn send_command(
                 .get("message_id")
                 .and_then(|m| m.as_str())
                 .unwrap_or("");
-            wait_for_reply(
-                &thread_id_for_wait,
-                our_message_id,
-                wait_secs,
-                data_dir,
-                config.as_ref(),
-                pretty,
-            )?;
+            if until_idle {
+                let timeout_secs = wait_secs.unwrap_or(UNTIL_IDLE_TIMEOUT_SECS);
+                if !wait_until_idle(
+                    &thread_id_for_wait,
+                    our_message_id,
+                    timeout_secs,
+                    data_dir,
+                    config.as_ref(),
+                    pretty,
+                )? {
+                    exit_idle_timeout(timeout_secs);
+                }
+            } else if let Some(wait_secs) = wait_secs {
+                wait_for_reply(
+                    &thread_id_for_wait,
+                    our_message_id,
+                    wait_secs,
+                    data_dir,
+                    config.as_ref(),
+                    pretty,
+                )?;
+            }
         }
 
         return Ok(());
     }
 
-    let response = send_command_raw(&command, data_dir, config.as_ref())?;
+    // Answer an ask interactively: fetch its questions, then prompt for each
+    if let CliCommand::AnswerAsk {
+        ref ask_id,
+        answers: None,
+    } = command
+    {
+        let response = send_command_raw(
+            &CliCommand::ShowAsk {
+                ask_id: ask_id.clone(),
+            },
+            data_dir,
+            config.as_ref(),
+        )?;
+        if let Some(error) = response.error {
+            eprintln!("Error [{}]: {}", error.code, error.message);
+            std::process::exit(1);
+        }
+        let ask = response.result.unwrap_or_default();
+        if ask["answered"].as_bool() == Some(true) {
+            eprintln!("Ask '{}' has already been answered", ask_id);
+            std::process::exit(1);
+        }
+
+        let answers = prompt_ask_answers(&ask, &mut io::stdin().lock(), &mut io::stderr())?;
+        let command = CliCommand::AnswerAsk {
+            ask_id: ask_id.clone(),
+            answers: Some(answers),
+        };
+        return print_response(
+            send_command_raw(&command, data_dir, config.as_ref())?,
+            pretty,
+        );
+    }
+
+    // Usage as a table or CSV instead of JSON
+    if let CliCommand::Usage { format, .. } = command {
+        if format != UsageFormat::Json {
+            let response = send_command_raw(&command, data_dir, config.as_ref())?;
+            if let Some(error) = response.error {
+                eprintln!("Error [{}]: {}", error.code, error.message);
+                std::process::exit(1);
+            }
+            let result = response.result.unwrap_or_default();
+            let rows: Vec<UsageRow> = serde_json::from_value(result["rows"].clone())?;
+            let total: UsageRow = serde_json::from_value(result["total"].clone())?;
+            if format == UsageFormat::Csv {
+                print!("{}", format_usage_csv(&rows, &total));
+            } else {
+                print!("{}", format_usage_table(&rows, &total));
+            }
+            return Ok(());
+        }
+    }
+
+    // Print a report or lesson as markdown or rendered text instead of JSON
+    let document = match command {
+        CliCommand::ShowReport { format, .. } if format != DocumentFormat::Json => {
+            Some((format, render_report as fn(&serde_json::Value) -> String))
+        }
+        CliCommand::ShowLesson { format, .. } if format != DocumentFormat::Json => {
+            Some((format, render_lesson as fn(&serde_json::Value) -> String))
+        }
+        _ => None,
+    };
+    if let Some((format, render)) = document {
+        let response = send_command_raw(&command, data_dir, config.as_ref())?;
+        if let Some(error) = response.error {
+            eprintln!("Error [{}]: {}", error.code, error.message);
+            std::process::exit(1);
+        }
+        let result = response.result.unwrap_or_default();
+        if format == DocumentFormat::Raw {
+            println!("{}", result["content"].as_str().unwrap_or(""));
+        } else {
+            println!("{}", render(&result));
+        }
+        return Ok(());
+    }
+
+    print_response(
+        send_command_raw(&command, data_dir, config.as_ref())?,
+        pretty,
+    )
+}
 
-    // Print result
+/// Print a daemon response, exiting with an error code on failure
+fn print_response(response: Response, pretty: bool) -> Result<()> {
     if let Some(error) = response.error {
         eprintln!("Error [{}]: {}", error.code, error.message);
         std::process::exit(1);
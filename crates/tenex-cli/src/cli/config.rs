@@ -2,14 +2,210 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use tenex_core::constants::RELAY_URL;
+use tenex_core::nostr::LogLevel;
 
-/// CLI configuration that can be loaded from a JSON file
+use super::api_tokens::ApiToken;
+
+/// Default HTTP server bind address
+pub const DEFAULT_HTTP_BIND: &str = "127.0.0.1:8080";
+
+/// Default IRC gateway bind address
+pub const DEFAULT_IRC_BIND: &str = "127.0.0.1:6667";
+
+/// Upper bound for a webhook's `maxAttempts`
+pub const MAX_WEBHOOK_ATTEMPTS: u32 = 10;
+
+/// CLI configuration that can be loaded from a JSON file.
+/// The daemon re-reads it on SIGHUP; everything except credentials is
+/// applied without a restart.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct CliConfig {
     /// Credentials for nostr authentication
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credentials: Option<Credentials>,
+
+    /// Bearer tokens accepted by the HTTP server (managed via `tenex-cli token`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_tokens: Vec<ApiToken>,
+
+    /// Relay URLs (default: wss://relay.tenex.chat)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+
+    /// HTTP server settings (`--http` / `--http-bind` take precedence)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpConfig>,
+
+    /// IRC gateway settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub irc: Option<IrcConfig>,
+
+    /// Backend pubkeys trusted or blocked in addition to the stored preferences
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backends: Option<BackendsConfig>,
+
+    /// Projects to subscribe to or boot once they are known
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<ProjectAutoStart>,
+
+    /// NIP-46 bunker settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bunker: Option<BunkerConfig>,
+
+    /// Daemon log level: error, info or debug (default: info)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+
+    /// How much of the daemon's own data is kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<RetentionConfig>,
+
+    /// Outbound webhooks for agent events
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpConfig {
+    /// Serve the HTTP API without `--http`
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Bind address (default: 127.0.0.1:8080)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IrcConfig {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Bind address (default: 127.0.0.1:6667)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+
+    /// Password clients must send with PASS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendsConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approved: Vec<String>,
+
+    /// Blocked wins over approved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectAutoStart {
+    /// Project slug (d-tag)
+    pub slug: String,
+
+    /// Also boot the project (kind:24000), not just subscribe to it
+    #[serde(default)]
+    pub boot: bool,
+
+    /// Re-boot the project whenever its backend status goes stale
+    #[serde(default)]
+    pub keep_alive: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BunkerConfig {
+    /// Run the bunker (overrides the stored preference)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Auto-approve rules in addition to the stored ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_approve: Vec<BunkerAutoApproveRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BunkerAutoApproveRule {
+    pub requester_pubkey: String,
+
+    /// Event kind to match (omit for any kind)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_kind: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionConfig {
+    /// Rotate the daemon log once it grows past this size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_log_bytes: Option<u64>,
+
+    /// Rotated logs to keep (tenex.log.1, tenex.log.2, ...; default: 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_log_files: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    /// Name shown in the delivery log
+    pub name: String,
+
+    /// http:// or https:// URL the payloads are POSTed to
+    pub url: String,
+
+    /// Key for the HMAC-SHA256 `X-Tenex-Signature` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+
+    /// Events to send (default: all)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<WebhookEvent>,
+
+    /// Project slugs to send events for (default: all). Events that do not
+    /// belong to a project only go to hooks without this filter.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<String>,
+
+    /// Delivery attempts before giving up (default: 5, at most 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// An agent asked the user a question
+    Ask,
+    /// A conversation has no more active operations
+    ConversationFinished,
+    /// A report was published or updated
+    Report,
+    /// An unknown backend is waiting for approval
+    BackendApproval,
+    /// The bunker received a signing request
+    BunkerRequest,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::ConversationFinished => "conversation_finished",
+            Self::Report => "report",
+            Self::BackendApproval => "backend_approval",
+            Self::BunkerRequest => "bunker_request",
+        }
+    }
 }
 
 /// Nostr credentials configuration
@@ -34,6 +230,13 @@ impl CliConfig {
         Ok(config)
     }
 
+    /// Write config to a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize config")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))
+    }
+
     /// Serialize config to JSON for passing to daemon
     pub fn to_json(&self) -> Result<String> {
         serde_json::to_string(self).context("Failed to serialize config")
@@ -43,6 +246,65 @@ impl CliConfig {
     pub fn from_json(json: &str) -> Result<Self> {
         serde_json::from_str(json).context("Failed to deserialize config")
     }
+
+    /// Configured relays, or the default relay
+    pub fn relay_urls(&self) -> Vec<String> {
+        if self.relays.is_empty() {
+            vec![RELAY_URL.to_string()]
+        } else {
+            self.relays.clone()
+        }
+    }
+
+    /// Check settings that would otherwise only fail once applied
+    pub fn validate(&self) -> Result<()> {
+        for relay in &self.relays {
+            if !relay.starts_with("wss://") && !relay.starts_with("ws://") {
+                anyhow::bail!("Invalid relay URL '{}' (expected ws:// or wss://)", relay);
+            }
+        }
+        if let Some(bind) = self.http.as_ref().and_then(|http| http.bind.as_ref()) {
+            bind.parse::<std::net::SocketAddr>()
+                .with_context(|| format!("Invalid HTTP bind address '{}'", bind))?;
+        }
+        if let Some(bind) = self.irc.as_ref().and_then(|irc| irc.bind.as_ref()) {
+            bind.parse::<std::net::SocketAddr>()
+                .with_context(|| format!("Invalid IRC bind address '{}'", bind))?;
+        }
+        for project in &self.projects {
+            if project.slug.trim().is_empty() {
+                anyhow::bail!("Project slug cannot be empty");
+            }
+        }
+        for (i, hook) in self.webhooks.iter().enumerate() {
+            if hook.name.trim().is_empty() {
+                anyhow::bail!("Webhook name cannot be empty");
+            }
+            if self.webhooks[..i]
+                .iter()
+                .any(|other| other.name == hook.name)
+            {
+                anyhow::bail!("Duplicate webhook name '{}'", hook.name);
+            }
+            if !hook.url.starts_with("http://") && !hook.url.starts_with("https://") {
+                anyhow::bail!(
+                    "Invalid webhook URL '{}' (expected http:// or https://)",
+                    hook.url
+                );
+            }
+            if let Some(attempts) = hook.max_attempts {
+                if !(1..=MAX_WEBHOOK_ATTEMPTS).contains(&attempts) {
+                    anyhow::bail!(
+                        "Invalid maxAttempts {} for webhook '{}' (expected 1-{})",
+                        attempts,
+                        hook.name,
+                        MAX_WEBHOOK_ATTEMPTS
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -68,5 +330,116 @@ mod tests {
         let json = r#"{}"#;
         let config: CliConfig = serde_json::from_str(json).unwrap();
         assert!(config.credentials.is_none());
+        assert!(config.api_tokens.is_empty());
+    }
+
+    #[test]
+    fn test_parse_daemon_settings() {
+        let json = r#"{
+            "relays": ["wss://relay.example.com"],
+            "http": { "enabled": true, "bind": "0.0.0.0:9090" },
+            "irc": { "enabled": true, "password": "hunter2" },
+            "backends": { "approved": ["aa"], "blocked": ["bb"] },
+            "projects": [{ "slug": "ops", "boot": true, "keepAlive": true }, { "slug": "docs" }],
+            "bunker": {
+                "enabled": true,
+                "autoApprove": [{ "requesterPubkey": "cc", "eventKind": 1 }]
+            },
+            "logLevel": "debug",
+            "retention": { "maxLogBytes": 1048576 },
+            "webhooks": [{
+                "name": "ci",
+                "url": "https://ci.example.com/hook",
+                "events": ["ask", "conversation_finished"],
+                "projects": ["ops"]
+            }]
+        }"#;
+        let config: CliConfig = serde_json::from_str(json).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.relay_urls(), vec!["wss://relay.example.com"]);
+        assert_eq!(
+            config.http.as_ref().unwrap().bind.as_deref(),
+            Some("0.0.0.0:9090")
+        );
+        let irc = config.irc.as_ref().unwrap();
+        assert!(irc.enabled);
+        assert_eq!(irc.bind, None);
+        assert_eq!(irc.password.as_deref(), Some("hunter2"));
+        assert!(config.projects[0].boot);
+        assert!(!config.projects[1].boot);
+        assert!(config.projects[0].keep_alive);
+        assert!(!config.projects[1].keep_alive);
+        assert_eq!(
+            config.bunker.as_ref().unwrap().auto_approve[0].event_kind,
+            Some(1)
+        );
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(config.retention.unwrap().max_log_files, None);
+        assert_eq!(
+            config.webhooks[0].events,
+            vec![WebhookEvent::Ask, WebhookEvent::ConversationFinished]
+        );
+        assert_eq!(config.webhooks[0].secret, None);
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_settings() {
+        let config = CliConfig {
+            relays: vec!["relay.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = CliConfig {
+            http: Some(HttpConfig {
+                enabled: true,
+                bind: Some("localhost".to_string()),
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let hook = WebhookConfig {
+            name: "ci".to_string(),
+            url: "ftp://ci.example.com".to_string(),
+            secret: None,
+            events: vec![],
+            projects: vec![],
+            max_attempts: None,
+        };
+        let config = CliConfig {
+            webhooks: vec![hook.clone()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let hook = WebhookConfig {
+            url: "https://ci.example.com".to_string(),
+            max_attempts: Some(MAX_WEBHOOK_ATTEMPTS + 1),
+            ..hook
+        };
+        let config = CliConfig {
+            webhooks: vec![hook],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        assert_eq!(CliConfig::default().relay_urls(), vec![RELAY_URL]);
+    }
+
+    #[test]
+    fn test_parse_config_with_api_tokens() {
+        let json = r#"{
+            "apiTokens": [{
+                "id": "tok_1",
+                "name": "voice",
+                "tokenHash": "abc",
+                "projects": ["my-project"],
+                "createdAt": 1700000000
+            }]
+        }"#;
+        let config: CliConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.api_tokens.len(), 1);
+        assert_eq!(config.api_tokens[0].projects, vec!["my-project"]);
+        assert!(config.api_tokens[0].agents.is_empty());
     }
 }
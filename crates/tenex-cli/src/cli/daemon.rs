@@ -12,22 +12,41 @@ use tokio::sync::broadcast;
 use anyhow::Result;
 use serde::Deserialize;
 
-use crate::cli::http::run_server;
+use crate::cli::http::{is_final_reply, is_loopback_bind, run_server};
 use crate::nostr::{self, DataChange, NostrCommand};
 use crate::store::AppDataStore;
 use tenex_core::config::CoreConfig;
 use tenex_core::models::PreferencesStorage;
-use tenex_core::nostr::set_log_path;
+use tenex_core::nostr::{set_log_level, set_log_path};
 use tenex_core::runtime::{CoreHandle, CoreRuntime};
 use tenex_core::slug::{validate_slug, SlugValidation};
 
-use super::config::CliConfig;
-use super::protocol::{Request, Response};
-use super::roster::{project_has_available_agent, project_roster_agents};
+use super::api_tokens::ApiTokenRegistry;
+use super::asks::{ask_to_json, format_ask_answers};
+use super::config::{BunkerAutoApproveRule, CliConfig, ProjectAutoStart};
+use super::irc::run_irc_server;
+use super::protocol::{AskAnswer, Request, Response, SubscribeFilter};
+use super::reload::{diff_config, http_bind_address, irc_bind_address, rotate_log, ConfigChanges};
+use super::roster::{
+    apply_roster_edit, default_agent_pubkey, find_roster_agent, project_has_available_agent,
+    project_roster_agents, RosterEdit,
+};
+use super::schedules::{JobRun, ScheduleRegistry, ScheduledJob};
+use super::subscriptions::{inbox_item_to_json, SubscriptionHub};
+use super::supervisor::KeepAlive;
+use super::usage::{aggregate_usage, UsageGroup, UsageRecord};
+use super::webhooks::{delivery_log_to_json, DeliveryLog, WebhookDispatcher};
 
 const SOCKET_NAME: &str = "tenex-cli.sock";
 const PID_FILE: &str = "daemon.pid";
 const LOG_FILE: &str = "tenex.log";
+const CONFIG_FILE: &str = "config.json";
+/// How often the log is checked against the retention settings
+const RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the keep-alive supervisor checks its projects
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// How often scheduled jobs are checked (they fire at most once a minute)
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 const BUNKER_PENDING_TIMEOUT: Duration = Duration::from_secs(70);
 
 #[derive(Debug, Clone)]
@@ -42,6 +61,8 @@ struct BunkerDaemonState {
     running: bool,
     uri: Option<String>,
     pending: HashMap<String, PendingBunkerRequest>,
+    /// Auto-approve rules from config.json, applied whenever the bunker starts
+    config_rules: Vec<BunkerAutoApproveRule>,
 }
 
 impl BunkerDaemonState {
@@ -96,7 +117,7 @@ pub async fn run_daemon(
     data_dir: PathBuf,
     config: Option<CliConfig>,
     http_enabled: bool,
-    http_bind: String,
+    http_bind: Option<String>,
 ) -> Result<()> {
     eprintln!("Starting tenex-cli daemon...");
 
@@ -108,6 +129,14 @@ pub async fn run_daemon(
     set_log_path(log_path.clone());
     eprintln!("Log file: {:?}", log_path);
 
+    let config_path = data_dir.join(CONFIG_FILE);
+    let mut config = config.unwrap_or_default();
+    // Same check as a SIGHUP reload, which keeps the old settings instead
+    if let Err(e) = config.validate() {
+        anyhow::bail!("Invalid config {}: {:#}", config_path.display(), e);
+    }
+    set_log_level(config.log_level.unwrap_or_default());
+
     // Socket path
     let socket_path = data_dir.join(SOCKET_NAME);
 
@@ -177,22 +206,13 @@ pub async fn run_daemon(
         }
     });
 
-    // Set trusted backends from preferences
-    {
-        let prefs_guard = prefs.lock().unwrap();
-        let approved = prefs_guard.approved_backend_pubkeys().clone();
-        let blocked = prefs_guard.blocked_backend_pubkeys().clone();
-        shared_data_store
-            .lock()
-            .unwrap()
-            .trust
-            .set_trusted_backends(approved, blocked);
-    }
+    // Set trusted backends from preferences and config
+    apply_trusted_backends(&prefs, &shared_data_store, &config);
 
     // Try to auto-login: config credentials take priority over stored credentials
     let keys = {
         let prefs_guard = prefs.lock().unwrap();
-        try_auto_login_with_config(config.as_ref(), &prefs_guard, &core_handle)
+        try_auto_login_with_config(&config, &prefs_guard, &core_handle)
     };
     if keys.is_some() {
         eprintln!("Auto-login successful");
@@ -212,11 +232,22 @@ pub async fn run_daemon(
     let start_time = Instant::now();
     let ndb = core_runtime.ndb();
     let logged_in = keys.is_some();
-    let bunker_state = Arc::new(Mutex::new(BunkerDaemonState::default()));
-
-    // Auto-start bunker when enabled in preferences and logged in.
+    let bunker_state = Arc::new(Mutex::new(BunkerDaemonState {
+        config_rules: config
+            .bunker
+            .as_ref()
+            .map(|bunker| bunker.auto_approve.clone())
+            .unwrap_or_default(),
+        ..Default::default()
+    }));
+
+    // Auto-start bunker when enabled in config or preferences and logged in.
     if logged_in {
-        let should_start_bunker = prefs.lock().unwrap().bunker_enabled();
+        let should_start_bunker = config
+            .bunker
+            .as_ref()
+            .and_then(|bunker| bunker.enabled)
+            .unwrap_or_else(|| prefs.lock().unwrap().bunker_enabled());
         if should_start_bunker {
             if let Err(e) = start_bunker_runtime(&core_handle, &bunker_state, &prefs) {
                 eprintln!("Failed to auto-start bunker: {}", e);
@@ -224,29 +255,99 @@ pub async fn run_daemon(
         }
     }
 
+    // API tokens for the HTTP server, managed over the socket with `tenex-cli token`
+    let api_tokens = Arc::new(Mutex::new(ApiTokenRegistry::load(&data_dir)));
+
     // Spawn HTTP server if enabled (shares the same data store)
-    let http_task = if http_enabled {
+    let spawn_http = |bind: String| {
         let http_core_handle = core_handle.clone();
         let http_store_clone = shared_data_store.clone();
-        Some(tokio::spawn(async move {
+        let http_broadcast_tx = broadcast_tx_for_http.clone();
+        let http_api_tokens = api_tokens.clone();
+        let http_keys = keys.clone();
+        let http_data_dir = data_dir.clone();
+        if !api_tokens.lock().unwrap().is_enabled() {
+            if is_loopback_bind(&bind) {
+                eprintln!(
+                    "Warning: no API tokens configured - HTTP server accepts unauthenticated requests"
+                );
+            } else {
+                eprintln!(
+                    "Warning: no API tokens configured - HTTP server on non-loopback address {} \
+                     rejects all requests until a token is created",
+                    bind
+                );
+            }
+        }
+        tokio::spawn(async move {
             if let Err(e) = run_server(
-                http_bind,
+                bind,
                 http_core_handle,
                 http_store_clone,
-                broadcast_tx_for_http,
+                http_broadcast_tx,
+                http_api_tokens,
+                http_keys,
+                &http_data_dir,
             )
             .await
             {
                 eprintln!("HTTP server error: {}", e);
             }
-        }))
-    } else {
-        None
+        })
     };
+    let mut http_address = http_bind_address(&config, http_enabled, http_bind.as_deref());
+    let mut http_task = http_address.clone().map(&spawn_http);
+
+    // Configured projects are subscribed/booted once they show up in the store
+    let mut pending_projects: Vec<ProjectAutoStart> = config.projects.clone();
+    let mut last_retention_check: Option<Instant> = None;
+
+    // Projects the supervisor re-boots when their status goes stale
+    let keep_alive = Arc::new(Mutex::new(KeepAlive::default()));
+    keep_alive
+        .lock()
+        .unwrap()
+        .set_projects(keep_alive_slugs(&config));
+    let mut last_keep_alive_check = Instant::now();
+
+    // Outbound webhooks for asks, finished conversations, reports, etc.
+    let mut webhooks = WebhookDispatcher::new(config.webhooks.clone());
+    let webhook_log = webhooks.log().clone();
+
+    // Recurring prompts, stored in schedules.json
+    let schedules = Arc::new(Mutex::new(ScheduleRegistry::load(&data_dir)));
+    let mut last_schedule_check = Instant::now();
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
 
     // Subscribe to broadcast for daemon's own use (handling ProjectStatus)
     let mut daemon_rx = broadcast_tx.subscribe();
 
+    // Notifications for `subscribe` connections on the socket
+    let subscriptions = SubscriptionHub::new();
+
+    // IRC gateway: projects are channels, agents are nicks
+    let spawn_irc = |config: &CliConfig| {
+        let bind = irc_bind_address(config)?;
+        let password = config.irc.as_ref().and_then(|irc| irc.password.clone());
+        let irc_store = shared_data_store.clone();
+        let irc_core_handle = core_handle.clone();
+        let irc_subscriptions = subscriptions.clone();
+        Some(tokio::spawn(async move {
+            if let Err(e) = run_irc_server(
+                bind,
+                irc_store,
+                irc_core_handle,
+                irc_subscriptions,
+                password,
+            )
+            .await
+            {
+                eprintln!("IRC gateway error: {}", e);
+            }
+        }))
+    };
+    let mut irc_task = spawn_irc(&config);
+
     // Main event loop - unified for both HTTP and socket-only modes
     loop {
         // Drain any pending DataChange events from broadcast (non-blocking)
@@ -264,11 +365,20 @@ pub async fn run_daemon(
                             .lock()
                             .unwrap()
                             .handle_status_event_json(json);
-                    } else if let DataChange::BunkerSignRequest { request } = data_change {
+                    } else if let DataChange::BunkerSignRequest { request } = &data_change {
                         let mut state = bunker_state.lock().unwrap();
-                        state.upsert_pending(request);
+                        state.upsert_pending(request.clone());
                         state.expire_stale_pending();
                     }
+
+                    if subscriptions.has_subscribers() {
+                        let store = shared_data_store.lock().unwrap();
+                        subscriptions.on_data_change(&store, &data_change);
+                    }
+                    if webhooks.is_enabled() {
+                        let store = shared_data_store.lock().unwrap();
+                        webhooks.on_data_change(&store, &data_change);
+                    }
                 }
                 Err(broadcast::error::TryRecvError::Empty) => break,
                 Err(broadcast::error::TryRecvError::Lagged(_)) => continue, // Skip lagged messages
@@ -278,6 +388,29 @@ pub async fn run_daemon(
 
         bunker_state.lock().unwrap().expire_stale_pending();
 
+        if logged_in && !pending_projects.is_empty() {
+            start_configured_projects(&mut pending_projects, &shared_data_store, &core_handle);
+        }
+
+        if logged_in && last_keep_alive_check.elapsed() >= KEEP_ALIVE_INTERVAL {
+            last_keep_alive_check = Instant::now();
+            supervise_projects(&keep_alive, &shared_data_store, &core_handle);
+        }
+
+        if logged_in && last_schedule_check.elapsed() >= SCHEDULE_CHECK_INTERVAL {
+            last_schedule_check = Instant::now();
+            run_due_jobs(&schedules, &shared_data_store, &core_handle);
+        }
+
+        if last_retention_check.is_none_or(|at| at.elapsed() >= RETENTION_CHECK_INTERVAL) {
+            last_retention_check = Some(Instant::now());
+            if let Some(retention) = &config.retention {
+                if let Err(e) = rotate_log(&log_path, retention) {
+                    eprintln!("Failed to rotate {:?}: {}", log_path, e);
+                }
+            }
+        }
+
         tokio::select! {
             accept_result = listener.accept() => {
                 match accept_result {
@@ -291,6 +424,11 @@ pub async fn run_daemon(
                             &core_handle,
                             &prefs,
                             &bunker_state,
+                            &api_tokens,
+                            &keep_alive,
+                            &webhook_log,
+                            &schedules,
+                            &subscriptions,
                             start_time,
                             logged_in,
                         )?;
@@ -309,24 +447,91 @@ pub async fn run_daemon(
                 // Process note keys and update the SHARED data store
                 // This ensures both HTTP and Unix socket see the same data
                 let mut store = shared_data_store.lock().unwrap();
-                if let Err(e) = tenex_core::runtime::process_note_keys(
+                match tenex_core::runtime::process_note_keys(
                     ndb.as_ref(),
                     &mut store,
                     &core_handle,
                     &note_keys,
                 ) {
-                    eprintln!("Failed to process core events: {}", e);
+                    Ok(events) => {
+                        if subscriptions.has_subscribers() {
+                            subscriptions.on_core_events(&store, &events);
+                        }
+                        if webhooks.is_enabled() {
+                            webhooks.on_core_events(&store, &events);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to process core events: {}", e),
+                }
+            }
+            _ = hangup.recv() => {
+                let new_config = match load_config_file(&config_path) {
+                    Ok(new_config) => new_config,
+                    Err(e) => {
+                        eprintln!("Config reload failed, keeping current settings: {:#}", e);
+                        continue;
+                    }
+                };
+                let changes = diff_config(&config, &new_config);
+
+                set_log_level(new_config.log_level.unwrap_or_default());
+                *api_tokens.lock().unwrap() = ApiTokenRegistry::load(&data_dir);
+                if changes.relays {
+                    if let Some(ref keys) = keys {
+                        // Waits for the disconnect, so keep it off the main loop
+                        let core_handle = core_handle.clone();
+                        let keys = keys.clone();
+                        let relay_urls = new_config.relay_urls();
+                        tokio::task::spawn_blocking(move || {
+                            reconnect_relays(&core_handle, &keys, relay_urls)
+                        });
+                    }
+                }
+                if changes.backends {
+                    apply_trusted_backends(&prefs, &shared_data_store, &new_config);
+                }
+                if logged_in {
+                    apply_bunker_changes(&changes, &new_config, &core_handle, &bunker_state, &prefs);
+                }
+                pending_projects.extend(changes.projects);
+                keep_alive
+                    .lock()
+                    .unwrap()
+                    .set_projects(keep_alive_slugs(&new_config));
+                webhooks.set_hooks(new_config.webhooks.clone());
+
+                let new_http_address =
+                    http_bind_address(&new_config, http_enabled, http_bind.as_deref());
+                if new_http_address != http_address {
+                    if let Some(task) = http_task.take() {
+                        task.abort();
+                    }
+                    http_task = new_http_address.clone().map(&spawn_http);
+                    http_address = new_http_address;
                 }
+                if new_config.irc != config.irc {
+                    if let Some(task) = irc_task.take() {
+                        task.abort();
+                    }
+                    irc_task = spawn_irc(&new_config);
+                }
+
+                config = new_config;
+                last_retention_check = None;
+                eprintln!("Config reloaded from {:?}", config_path);
             }
             // Small timeout to periodically check for DataChange events
             _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {}
         }
     }
 
-    // Abort HTTP task on shutdown if it was running
+    // Abort HTTP and IRC tasks on shutdown if they were running
     if let Some(task) = http_task {
         task.abort();
     }
+    if let Some(task) = irc_task {
+        task.abort();
+    }
 
     // Cleanup
     core_runtime.shutdown();
@@ -339,18 +544,23 @@ pub async fn run_daemon(
 
 /// Try to login with config credentials first, then env vars, then stored credentials
 fn try_auto_login_with_config(
-    config: Option<&CliConfig>,
+    config: &CliConfig,
     prefs: &PreferencesStorage,
     core_handle: &CoreHandle,
 ) -> Option<nostr_sdk::Keys> {
+    let relay_urls = config.relay_urls();
+
     // Try config credentials first
-    if let Some(cfg) = config {
-        if let Some(ref creds) = cfg.credentials {
-            match try_login_with_credentials(&creds.key, creds.password.as_deref(), core_handle) {
-                Ok(keys) => return Some(keys),
-                Err(e) => {
-                    eprintln!("Failed to login with config credentials: {}", e);
-                }
+    if let Some(ref creds) = config.credentials {
+        match try_login_with_credentials(
+            &creds.key,
+            creds.password.as_deref(),
+            core_handle,
+            &relay_urls,
+        ) {
+            Ok(keys) => return Some(keys),
+            Err(e) => {
+                eprintln!("Failed to login with config credentials: {}", e);
             }
         }
     }
@@ -358,7 +568,7 @@ fn try_auto_login_with_config(
     // Try environment variables (used when daemon is spawned by client, or set manually)
     if let Ok(key) = std::env::var("TENEX_NSEC") {
         let password = std::env::var("TENEX_NSEC_PASSWORD").ok();
-        match try_login_with_credentials(&key, password.as_deref(), core_handle) {
+        match try_login_with_credentials(&key, password.as_deref(), core_handle, &relay_urls) {
             Ok(keys) => return Some(keys),
             Err(e) => {
                 eprintln!("Failed to login with TENEX_NSEC: {}", e);
@@ -367,7 +577,7 @@ fn try_auto_login_with_config(
     }
 
     // Fall back to stored credentials
-    try_auto_login(prefs, core_handle)
+    try_auto_login(prefs, core_handle, &relay_urls)
 }
 
 /// Try to parse and login with the provided key (nsec or ncryptsec)
@@ -375,6 +585,7 @@ fn try_login_with_credentials(
     key: &str,
     password: Option<&str>,
     core_handle: &CoreHandle,
+    relay_urls: &[String],
 ) -> anyhow::Result<nostr_sdk::Keys> {
     use nostr_sdk::prelude::*;
 
@@ -401,7 +612,7 @@ fn try_login_with_credentials(
         .send(NostrCommand::Connect {
             keys: keys.clone(),
             user_pubkey: pubkey,
-            relay_urls: vec![],
+            relay_urls: relay_urls.to_vec(),
             response_tx: None,
         })
         .map_err(|_| anyhow::anyhow!("Failed to send Connect command"))?;
@@ -409,7 +620,11 @@ fn try_login_with_credentials(
     Ok(keys)
 }
 
-fn try_auto_login(prefs: &PreferencesStorage, core_handle: &CoreHandle) -> Option<nostr_sdk::Keys> {
+fn try_auto_login(
+    prefs: &PreferencesStorage,
+    core_handle: &CoreHandle,
+    relay_urls: &[String],
+) -> Option<nostr_sdk::Keys> {
     if !nostr::has_stored_credentials(prefs) {
         return None;
     }
@@ -425,7 +640,7 @@ fn try_auto_login(prefs: &PreferencesStorage, core_handle: &CoreHandle) -> Optio
                         .send(NostrCommand::Connect {
                             keys: keys.clone(),
                             user_pubkey: pubkey,
-                            relay_urls: vec![],
+                            relay_urls: relay_urls.to_vec(),
                             response_tx: None,
                         })
                         .is_ok()
@@ -449,7 +664,7 @@ fn try_auto_login(prefs: &PreferencesStorage, core_handle: &CoreHandle) -> Optio
                 .send(NostrCommand::Connect {
                     keys: keys.clone(),
                     user_pubkey: pubkey,
-                    relay_urls: vec![],
+                    relay_urls: relay_urls.to_vec(),
                     response_tx: None,
                 })
                 .is_ok()
@@ -485,6 +700,14 @@ fn start_bunker_runtime(
         let mut state = bunker_state
             .lock()
             .map_err(|e| format!("Lock poisoned: {}", e))?;
+        for rule in &state.config_rules {
+            core_handle
+                .send(NostrCommand::AddBunkerAutoApproveRule {
+                    requester_pubkey: rule.requester_pubkey.clone(),
+                    event_kind: rule.event_kind,
+                })
+                .map_err(|e| format!("Failed to apply bunker auto-approve rule: {}", e))?;
+        }
         state.running = true;
         state.uri = Some(uri.clone());
         state.expire_stale_pending();
@@ -539,6 +762,315 @@ fn replay_persisted_bunker_rules(
     Ok(())
 }
 
+/// Apply bunker settings from a reloaded config: start or stop it, and
+/// add or remove auto-approve rules while it runs
+fn apply_bunker_changes(
+    changes: &ConfigChanges,
+    config: &CliConfig,
+    core_handle: &CoreHandle,
+    bunker_state: &Arc<Mutex<BunkerDaemonState>>,
+    prefs: &Arc<Mutex<PreferencesStorage>>,
+) {
+    let running = {
+        let mut state = bunker_state.lock().unwrap();
+        state.config_rules = config
+            .bunker
+            .as_ref()
+            .map(|bunker| bunker.auto_approve.clone())
+            .unwrap_or_default();
+        state.running
+    };
+
+    match changes.bunker_enabled {
+        Some(true) if !running => {
+            if let Err(e) = start_bunker_runtime(core_handle, bunker_state, prefs) {
+                eprintln!("Failed to start bunker: {}", e);
+            }
+            return;
+        }
+        Some(false) if running => {
+            if let Err(e) = stop_bunker_runtime(core_handle, bunker_state) {
+                eprintln!("Failed to stop bunker: {}", e);
+            }
+            return;
+        }
+        _ => {}
+    }
+    if !running {
+        return;
+    }
+
+    let persisted = prefs.lock().unwrap().bunker_auto_approve_rules().to_vec();
+    for rule in &changes.added_rules {
+        let _ = core_handle.send(NostrCommand::AddBunkerAutoApproveRule {
+            requester_pubkey: rule.requester_pubkey.clone(),
+            event_kind: rule.event_kind,
+        });
+    }
+    // Rules that are also stored in preferences stay
+    for rule in &changes.removed_rules {
+        let is_persisted = persisted.iter().any(|p| {
+            p.requester_pubkey == rule.requester_pubkey && p.event_kind == rule.event_kind
+        });
+        if !is_persisted {
+            let _ = core_handle.send(NostrCommand::RemoveBunkerAutoApproveRule {
+                requester_pubkey: rule.requester_pubkey.clone(),
+                event_kind: rule.event_kind,
+            });
+        }
+    }
+}
+
+/// Trust the backends approved in preferences or config; blocked wins
+fn apply_trusted_backends(
+    prefs: &Arc<Mutex<PreferencesStorage>>,
+    data_store: &Arc<Mutex<AppDataStore>>,
+    config: &CliConfig,
+) {
+    let (approved, blocked) = {
+        let prefs_guard = prefs.lock().unwrap();
+        (
+            prefs_guard.approved_backend_pubkeys().clone(),
+            prefs_guard.blocked_backend_pubkeys().clone(),
+        )
+    };
+
+    let mut store = data_store.lock().unwrap();
+    store.trust.set_trusted_backends(approved, blocked);
+    if let Some(backends) = &config.backends {
+        for pubkey in &backends.approved {
+            store.add_approved_backend(pubkey);
+        }
+        for pubkey in &backends.blocked {
+            store.add_blocked_backend(pubkey);
+        }
+    }
+}
+
+/// Reconnect to a new relay set with the current keys
+fn reconnect_relays(core_handle: &CoreHandle, keys: &nostr_sdk::Keys, relay_urls: Vec<String>) {
+    let (response_tx, response_rx) = std::sync::mpsc::channel::<Result<(), String>>();
+    if core_handle
+        .send(NostrCommand::Disconnect {
+            response_tx: Some(response_tx),
+        })
+        .is_err()
+    {
+        eprintln!("Failed to disconnect from relays");
+        return;
+    }
+    let _ = response_rx.recv_timeout(Duration::from_secs(5));
+
+    if core_handle
+        .send(NostrCommand::Connect {
+            keys: keys.clone(),
+            user_pubkey: nostr::get_current_pubkey(keys),
+            relay_urls,
+            response_tx: None,
+        })
+        .is_err()
+    {
+        eprintln!("Failed to reconnect to relays");
+    }
+}
+
+/// Subscribe to (and boot) configured projects that are now in the store.
+/// Projects that are not known yet stay pending.
+fn start_configured_projects(
+    pending: &mut Vec<ProjectAutoStart>,
+    data_store: &Arc<Mutex<AppDataStore>>,
+    core_handle: &CoreHandle,
+) {
+    let store = data_store.lock().unwrap();
+    pending.retain(|entry| {
+        let Some(project) = store.get_projects().iter().find(|p| p.id == entry.slug) else {
+            return true;
+        };
+        let project_a_tag = project.a_tag();
+        let _ = core_handle.send(NostrCommand::SubscribeToProjectMessages {
+            project_a_tag: project_a_tag.clone(),
+        });
+        let _ = core_handle.send(NostrCommand::SubscribeToProjectMetadata {
+            project_a_tag: project_a_tag.clone(),
+        });
+        if entry.boot {
+            let _ = core_handle.send(NostrCommand::BootProject {
+                project_a_tag,
+                project_pubkey: Some(project.pubkey.clone()),
+            });
+        }
+        false
+    });
+}
+
+fn keep_alive_slugs(config: &CliConfig) -> Vec<String> {
+    config
+        .projects
+        .iter()
+        .filter(|project| project.keep_alive)
+        .map(|project| project.slug.clone())
+        .collect()
+}
+
+/// Boot keep-alive projects whose backend status went stale, with backoff
+fn supervise_projects(
+    keep_alive: &Arc<Mutex<KeepAlive>>,
+    data_store: &Arc<Mutex<AppDataStore>>,
+    core_handle: &CoreHandle,
+) {
+    let now = now_unix_ms() / 1000;
+    let store = data_store.lock().unwrap();
+    let mut keep_alive = keep_alive.lock().unwrap();
+    for slug in keep_alive.slugs() {
+        let Some(project) = store.get_projects().iter().find(|p| p.id == slug) else {
+            continue;
+        };
+        let project_a_tag = project.a_tag();
+        let online = store.is_project_online(&project_a_tag);
+        if !keep_alive.observe(&slug, online, now) {
+            continue;
+        }
+
+        match core_handle.send(NostrCommand::BootProject {
+            project_a_tag,
+            project_pubkey: Some(project.pubkey.clone()),
+        }) {
+            Ok(()) => {
+                let attempt = keep_alive.record_boot(&slug, now);
+                eprintln!(
+                    "Keep-alive: booting project '{}' (attempt {})",
+                    slug, attempt
+                );
+            }
+            Err(e) => eprintln!("Keep-alive: failed to boot project '{}': {}", slug, e),
+        }
+    }
+}
+
+/// Project a-tag and agent pubkey a scheduled job sends to
+fn resolve_job_target(
+    store: &AppDataStore,
+    job: &ScheduledJob,
+) -> Result<(String, String), String> {
+    let project = store
+        .get_projects()
+        .iter()
+        .find(|p| p.id == job.project)
+        .ok_or_else(|| format!("Project '{}' not found", job.project))?;
+    let agent_pubkey = match &job.agent {
+        Some(slug) => find_roster_agent(store, project, slug)
+            .map(|agent| agent.pubkey)
+            .ok_or_else(|| format!("Agent '{}' not found in project '{}'", slug, job.project))?,
+        None => default_agent_pubkey(project)
+            .ok_or_else(|| format!("Project '{}' has no agents", job.project))?,
+    };
+    Ok((project.a_tag(), agent_pubkey))
+}
+
+/// Fire scheduled jobs whose cron expression matches the current minute
+fn run_due_jobs(
+    schedules: &Arc<Mutex<ScheduleRegistry>>,
+    data_store: &Arc<Mutex<AppDataStore>>,
+    core_handle: &CoreHandle,
+) {
+    let now = chrono::Local::now();
+    let at = now.timestamp().max(0) as u64;
+    let due = {
+        let mut schedules = schedules.lock().unwrap();
+        let due = schedules.due_jobs(&now);
+        for job in &due {
+            schedules.mark_running(&job.id);
+        }
+        due
+    };
+    for job in due {
+        // Publishing waits for the signed event, so keep it off the main loop
+        let schedules = schedules.clone();
+        let data_store = data_store.clone();
+        let core_handle = core_handle.clone();
+        tokio::task::spawn_blocking(move || {
+            let run = run_scheduled_job(&job, at, &data_store, &core_handle);
+            match (&run.error, &run.thread_id) {
+                (Some(e), _) => eprintln!("Scheduled job '{}' failed: {}", job.name, e),
+                (None, Some(thread_id)) => {
+                    eprintln!("Scheduled job '{}' sent to thread {}", job.name, thread_id)
+                }
+                (None, None) => eprintln!("Scheduled job '{}' sent", job.name),
+            }
+            if let Err(e) = schedules.lock().unwrap().record_run(&job.id, run) {
+                eprintln!("Failed to record run of '{}': {:#}", job.name, e);
+            }
+        });
+    }
+}
+
+/// Publish a job's prompt as a new thread, or into its thread when set
+fn run_scheduled_job(
+    job: &ScheduledJob,
+    at: u64,
+    data_store: &Arc<Mutex<AppDataStore>>,
+    core_handle: &CoreHandle,
+) -> JobRun {
+    let failed = |error: String| JobRun {
+        at,
+        thread_id: job.thread_id.clone(),
+        error: Some(error),
+    };
+    let target = resolve_job_target(&data_store.lock().unwrap(), job);
+    let (project_a_tag, agent_pubkey) = match target {
+        Ok(target) => target,
+        Err(e) => return failed(e),
+    };
+
+    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+    let command = match &job.thread_id {
+        Some(thread_id) => NostrCommand::PublishMessage {
+            thread_id: thread_id.clone(),
+            project_a_tag,
+            content: job.content.clone(),
+            agent_pubkey: Some(agent_pubkey),
+            reply_to: None,
+            nudge_ids: job.nudge_ids.clone(),
+            skill_ids: job.skill_ids.clone(),
+            ask_author_pubkey: None,
+            response_tx: Some(response_tx),
+        },
+        None => NostrCommand::PublishThread {
+            project_a_tag,
+            title: job.title.clone().unwrap_or_else(|| job.name.clone()),
+            content: job.content.clone(),
+            agent_pubkey: Some(agent_pubkey),
+            nudge_ids: job.nudge_ids.clone(),
+            skill_ids: job.skill_ids.clone(),
+            reference_conversation_id: None,
+            reference_report_a_tag: None,
+            fork_message_id: None,
+            response_tx: Some(response_tx),
+        },
+    };
+    if let Err(e) = core_handle.send(command) {
+        return failed(format!("Failed to publish: {}", e));
+    }
+
+    // For new threads the event ID is the thread ID
+    let event_id = response_rx.recv_timeout(Duration::from_secs(5)).ok();
+    JobRun {
+        at,
+        thread_id: job.thread_id.clone().or(event_id),
+        error: None,
+    }
+}
+
+/// Read config.json for a reload; a missing file means an empty config
+fn load_config_file(path: &Path) -> Result<CliConfig> {
+    if !path.exists() {
+        return Ok(CliConfig::default());
+    }
+    let config = CliConfig::load(path)?;
+    config.validate()?;
+    Ok(config)
+}
+
 fn persist_bunker_enabled(
     prefs: &Arc<Mutex<PreferencesStorage>>,
     enabled: bool,
@@ -595,6 +1127,11 @@ fn handle_connection(
     core_handle: &CoreHandle,
     prefs: &Arc<Mutex<PreferencesStorage>>,
     bunker_state: &Arc<Mutex<BunkerDaemonState>>,
+    api_tokens: &Arc<Mutex<ApiTokenRegistry>>,
+    keep_alive: &Arc<Mutex<KeepAlive>>,
+    webhook_log: &DeliveryLog,
+    schedules: &Arc<Mutex<ScheduleRegistry>>,
+    subscriptions: &SubscriptionHub,
     start_time: Instant,
     logged_in: bool,
 ) -> Result<bool> {
@@ -613,12 +1150,41 @@ fn handle_connection(
             }
         };
 
+        // `subscribe` hands the connection to a notification thread and stops
+        // reading requests from it
+        if request.method == "subscribe" {
+            let response = match serde_json::from_value::<SubscribeFilter>(request.params.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|filter| filter.validate().map(|_| filter))
+            {
+                Ok(filter) => {
+                    let response = Response::success(
+                        request.id,
+                        serde_json::json!({ "subscribed": true, "filter": filter }),
+                    );
+                    writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+                    writer.flush()?;
+                    subscriptions.serve(request.id, filter, writer);
+                    return Ok(false);
+                }
+                Err(msg) => Response::error(request.id, "INVALID_PARAMS", &msg),
+            };
+            writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+            writer.flush()?;
+            line.clear();
+            continue;
+        }
+
         let (response, should_shutdown) = handle_request(
             &request,
             data_store,
             core_handle,
             prefs,
             bunker_state,
+            api_tokens,
+            keep_alive,
+            webhook_log,
+            schedules,
             start_time,
             logged_in,
         );
@@ -642,6 +1208,10 @@ fn handle_request(
     core_handle: &CoreHandle,
     prefs: &Arc<Mutex<PreferencesStorage>>,
     bunker_state: &Arc<Mutex<BunkerDaemonState>>,
+    api_tokens: &Arc<Mutex<ApiTokenRegistry>>,
+    keep_alive: &Arc<Mutex<KeepAlive>>,
+    webhook_log: &DeliveryLog,
+    schedules: &Arc<Mutex<ScheduleRegistry>>,
     _start_time: Instant,
     logged_in: bool,
 ) -> (Response, bool) {
@@ -761,22 +1331,33 @@ fn handle_request(
             let messages: Vec<_> = store
                 .get_messages(thread_id)
                 .iter()
-                .map(|m| {
-                    let mut obj = serde_json::json!({
-                        "id": m.id,
-                        "content": m.content,
-                        "created_at": m.created_at,
-                        "pubkey": m.pubkey,
-                    });
-                    if let Some(name) = resolve_author_name(&store, &m.pubkey) {
-                        obj["author_name"] = serde_json::json!(name);
-                    }
-                    obj
-                })
+                .map(|m| message_to_json(&store, m))
                 .collect();
             (Response::success(id, serde_json::json!(messages)), false)
         }
 
+        "get_conversation_tree" => {
+            let Some(thread_id) = request.params["thread_id"].as_str() else {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "thread_id is required"),
+                    false,
+                );
+            };
+            let store = data_store.lock().unwrap();
+            let mut conversations = Vec::new();
+            collect_conversation_tree(&store, thread_id, None, 0, &mut conversations);
+            (
+                Response::success(
+                    id,
+                    serde_json::json!({
+                        "thread_id": thread_id,
+                        "conversations": conversations,
+                    }),
+                ),
+                false,
+            )
+        }
+
         "get_state" => {
             let store = data_store.lock().unwrap();
             let projects = store.get_projects();
@@ -1140,57 +1721,215 @@ fn handle_request(
                 serde_json::json!({
                     "status": "running",
                     "logged_in": logged_in,
+                    "keep_alive": keep_alive.lock().unwrap().to_json(now_unix_ms() / 1000),
                 }),
             ),
             false,
         ),
 
-        "bunker_start" => {
-            if !logged_in {
-                return not_logged_in_response(id);
-            }
+        "webhook_log" => {
+            let hook = request.params["hook"].as_str();
+            let limit = request.params["limit"].as_u64().unwrap_or(20) as usize;
+            (
+                Response::success(
+                    id,
+                    serde_json::json!({
+                        "deliveries": delivery_log_to_json(webhook_log, hook, limit),
+                    }),
+                ),
+                false,
+            )
+        }
 
-            match start_bunker_runtime(core_handle, bunker_state, prefs) {
-                Ok(uri) => (
-                    Response::success(
+        "schedule_add" => {
+            let param = |key: &str| {
+                request.params[key]
+                    .as_str()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+            };
+            let skill_ids = match validate_skill_ids_param(&request.params["skill_ids"]) {
+                Ok(ids) => ids,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+            let nudge_ids = match validate_nudge_ids_param(&request.params["nudge_ids"]) {
+                Ok(ids) => ids,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+            let (Some(name), Some(cron), Some(project), Some(content)) = (
+                param("name"),
+                param("cron"),
+                param("project_slug"),
+                param("content"),
+            ) else {
+                return (
+                    Response::error(
                         id,
-                        serde_json::json!({
-                            "status": "started",
-                            "running": true,
-                            "uri": uri,
-                            "enabled": prefs.lock().unwrap().bunker_enabled(),
-                        }),
+                        "INVALID_PARAMS",
+                        "name, cron, project_slug and content are required",
                     ),
                     false,
-                ),
-                Err(e) => (Response::error(id, "BUNKER_START_FAILED", &e), false),
-            }
-        }
+                );
+            };
 
-        "bunker_stop" => {
-            if !logged_in {
-                return not_logged_in_response(id);
+            let job = ScheduledJob {
+                id: String::new(),
+                name,
+                cron,
+                project,
+                agent: param("agent_slug"),
+                title: param("title"),
+                content,
+                thread_id: param("thread_id"),
+                skill_ids,
+                nudge_ids,
+                paused: false,
+                created_at: 0,
+                runs: vec![],
+            };
+            if logged_in {
+                let store = data_store.lock().unwrap();
+                if let Err(e) = resolve_job_target(&store, &job) {
+                    return (Response::error(id, "SCHEDULE_ADD_FAILED", &e), false);
+                }
             }
 
-            match stop_bunker_runtime(core_handle, bunker_state) {
-                Ok(()) => (
-                    Response::success(
-                        id,
-                        serde_json::json!({
-                            "status": "stopped",
-                            "running": false
-                        }),
-                    ),
+            let result = schedules.lock().unwrap().add(job);
+            match result {
+                Ok(job) => (
+                    Response::success(id, job.to_json(&chrono::Local::now())),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(id, "SCHEDULE_ADD_FAILED", &e.to_string()),
                     false,
                 ),
-                Err(e) => (Response::error(id, "BUNKER_STOP_FAILED", &e), false),
             }
         }
 
-        "bunker_status" => {
-            if !logged_in {
-                return not_logged_in_response(id);
-            }
+        "schedule_list" => {
+            let now = chrono::Local::now();
+            let jobs: Vec<serde_json::Value> = schedules
+                .lock()
+                .unwrap()
+                .jobs()
+                .iter()
+                .map(|job| job.to_json(&now))
+                .collect();
+            (
+                Response::success(id, serde_json::json!({ "jobs": jobs })),
+                false,
+            )
+        }
+
+        "schedule_pause" | "schedule_remove" | "schedule_history" => {
+            let job_ref = request.params["job"].as_str().unwrap_or("").trim();
+            if job_ref.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "job is required"),
+                    false,
+                );
+            }
+
+            let mut registry = schedules.lock().unwrap();
+            let result = match request.method.as_str() {
+                "schedule_pause" => {
+                    let paused = request.params["paused"].as_bool().unwrap_or(true);
+                    registry
+                        .set_paused(job_ref, paused)
+                        .map(|job| job.map(|job| job.to_json(&chrono::Local::now())))
+                }
+                "schedule_remove" => registry.remove(job_ref).map(|job| {
+                    job.map(|job| {
+                        serde_json::json!({
+                            "status": "removed",
+                            "id": job.id,
+                            "name": job.name,
+                        })
+                    })
+                }),
+                _ => {
+                    let limit = request.params["limit"].as_u64().unwrap_or(20) as usize;
+                    Ok(registry.find(job_ref).map(|job| {
+                        let runs: Vec<serde_json::Value> = job
+                            .runs
+                            .iter()
+                            .rev()
+                            .take(limit)
+                            .map(JobRun::to_json)
+                            .collect();
+                        serde_json::json!({
+                            "id": job.id,
+                            "name": job.name,
+                            "runs": runs,
+                        })
+                    }))
+                }
+            };
+            match result {
+                Ok(Some(json)) => (Response::success(id, json), false),
+                Ok(None) => (
+                    Response::error(
+                        id,
+                        "SCHEDULE_NOT_FOUND",
+                        &format!("No scheduled job with id or name '{}'", job_ref),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(id, "SCHEDULE_UPDATE_FAILED", &e.to_string()),
+                    false,
+                ),
+            }
+        }
+
+        "bunker_start" => {
+            if !logged_in {
+                return not_logged_in_response(id);
+            }
+
+            match start_bunker_runtime(core_handle, bunker_state, prefs) {
+                Ok(uri) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "started",
+                            "running": true,
+                            "uri": uri,
+                            "enabled": prefs.lock().unwrap().bunker_enabled(),
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (Response::error(id, "BUNKER_START_FAILED", &e), false),
+            }
+        }
+
+        "bunker_stop" => {
+            if !logged_in {
+                return not_logged_in_response(id);
+            }
+
+            match stop_bunker_runtime(core_handle, bunker_state) {
+                Ok(()) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "stopped",
+                            "running": false
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (Response::error(id, "BUNKER_STOP_FAILED", &e), false),
+            }
+        }
+
+        "bunker_status" => {
+            if !logged_in {
+                return not_logged_in_response(id);
+            }
 
             let mut state = bunker_state.lock().unwrap();
             state.expire_stale_pending();
@@ -1582,147 +2321,1134 @@ fn handle_request(
             (Response::success(id, serde_json::json!(agent_defs)), false)
         }
 
-        "list_mcp_tools" => {
-            let store = data_store.lock().unwrap();
-            let mcp_tools: Vec<_> = store
-                .content
-                .get_mcp_tools()
-                .iter()
-                .map(|tool| {
-                    serde_json::json!({
-                        "id": tool.id,
-                        "pubkey": tool.pubkey,
-                        "d_tag": tool.d_tag,
-                        "name": tool.name,
-                        "description": tool.description,
-                        "command": tool.command,
-                        "parameters": tool.parameters,
-                        "capabilities": tool.capabilities,
-                        "server_url": tool.server_url,
-                        "version": tool.version,
-                        "created_at": tool.created_at,
-                    })
-                })
-                .collect();
-            (Response::success(id, serde_json::json!(mcp_tools)), false)
-        }
-
-        "list_skills" => {
-            let store = data_store.lock().unwrap();
-            let skills: Vec<_> = store
-                .content
-                .get_skills()
-                .iter()
-                .map(|skill| {
-                    serde_json::json!({
-                        "id": skill.id,
-                        "pubkey": skill.pubkey,
-                        "title": skill.title,
-                        "description": skill.description,
-                        "content": skill.content,
-                        "hashtags": skill.hashtags,
-                        "file_ids": skill.file_ids,
-                        "created_at": skill.created_at,
-                    })
-                })
-                .collect();
-            (Response::success(id, serde_json::json!(skills)), false)
-        }
-
-        "list_nudges" => {
-            let store = data_store.lock().unwrap();
-            let nudges: Vec<_> = store
-                .content
-                .get_nudges()
-                .iter()
-                .map(|nudge| {
-                    serde_json::json!({
-                        "id": nudge.id,
-                        "pubkey": nudge.pubkey,
-                        "title": nudge.title,
-                        "description": nudge.description,
-                        "content": nudge.content,
-                        "hashtags": nudge.hashtags,
-                        "allowed_tools": nudge.allowed_tools,
-                        "denied_tools": nudge.denied_tools,
-                        "only_tools": nudge.only_tools,
-                        "supersedes": nudge.supersedes,
-                        "created_at": nudge.created_at,
-                    })
-                })
-                .collect();
-            (Response::success(id, serde_json::json!(nudges)), false)
-        }
-
-        "show_project" => {
-            let project_slug = request.params["project_slug"].as_str().unwrap_or("");
-            let wait_for_project = request.params["wait_for_project"]
-                .as_bool()
-                .unwrap_or(false);
-
-            // If wait_for_project is true, wait for the project roster to appear
-            if wait_for_project {
-                if let Err(err_response) = wait_for_project_roster(data_store, project_slug, id) {
-                    return err_response;
-                }
+        "create_agent_definition" => {
+            #[derive(Deserialize)]
+            struct CreateAgentDefinitionParams {
+                name: Option<String>,
+                description: Option<String>,
+                role: Option<String>,
+                instructions: Option<String>,
+                version: Option<String>,
+                /// Definition to fork (is_fork) or clone from
+                source_id: Option<String>,
+                #[serde(default)]
+                is_fork: bool,
             }
 
-            let store = data_store.lock().unwrap();
+            let params: CreateAgentDefinitionParams =
+                match serde_json::from_value(request.params.clone()) {
+                    Ok(p) => p,
+                    Err(_) => {
+                        return (
+                            Response::error(
+                                id,
+                                "INVALID_PARAMS",
+                                "Invalid create_agent_definition params",
+                            ),
+                            false,
+                        );
+                    }
+                };
 
-            // Find the project by slug
-            let project = store
-                .get_projects()
-                .iter()
-                .find(|p| p.id == project_slug)
-                .cloned();
+            let source_id = params
+                .source_id
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            if params.is_fork && source_id.is_none() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "is_fork requires source_id"),
+                    false,
+                );
+            }
 
-            let project = match project {
-                Some(p) => p,
-                None => {
-                    return (
-                        Response::error(
-                            id,
-                            "PROJECT_NOT_FOUND",
-                            &format!("Project '{}' not found", project_slug),
-                        ),
-                        false,
-                    );
+            // Forks and clones start from the source definition, like the TUI
+            // forms: a fork bumps the version, a clone starts over at 1
+            let source = match &source_id {
+                Some(source_id) => {
+                    let store = data_store.lock().unwrap();
+                    match store.content.get_agent_definition(source_id) {
+                        Some(source) => Some(source.clone()),
+                        None => {
+                            return (
+                                Response::error(
+                                    id,
+                                    "AGENT_DEFINITION_NOT_FOUND",
+                                    &format!("Agent definition '{}' not found", source_id),
+                                ),
+                                false,
+                            );
+                        }
+                    }
                 }
+                None => None,
+            };
+            let non_empty = |value: Option<String>| {
+                value
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
             };
 
-            let agents = project_roster_agents(&store, &project);
-            let booted = project_has_available_agent(&store, &project);
-            let a_tag = project.a_tag();
-            let status = store.get_project_status(&a_tag);
-
-            let response = serde_json::json!({
-                "slug": project.id,
-                "name": project.title,
-                "pubkey": project.pubkey,
-                "booted": booted,
-                "agents": agents.iter().map(agent_to_json).collect::<Vec<_>>(),
-                "branches": status.map(|s| s.branches.clone()).unwrap_or_default(),
-                "all_models": agents
-                    .iter()
-                    .filter_map(|agent| agent.model.clone())
-                    .collect::<Vec<_>>(),
-                "all_tools": agents
-                    .iter()
-                    .flat_map(|agent| agent.tools.clone())
-                    .collect::<Vec<_>>(),
-                "backend_pubkey": store.first_online_backend_for_project(&a_tag),
-                "created_at": status.map(|s| s.created_at),
+            let name = non_empty(params.name).or_else(|| {
+                source.as_ref().map(|s| {
+                    if params.is_fork {
+                        s.name.clone()
+                    } else {
+                        format!("{} (Copy)", s.name)
+                    }
+                })
+            });
+            let description = non_empty(params.description)
+                .or_else(|| source.as_ref().map(|s| s.description.clone()));
+            let (Some(name), Some(description)) = (name, description) else {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "name and description are required"),
+                    false,
+                );
+            };
+            let role = non_empty(params.role)
+                .or_else(|| source.as_ref().map(|s| s.role.clone()))
+                .unwrap_or_else(|| "assistant".to_string());
+            let instructions = params
+                .instructions
+                .filter(|i| !i.trim().is_empty())
+                .or_else(|| source.as_ref().map(|s| s.instructions.clone()))
+                .unwrap_or_default();
+            let version = non_empty(params.version).unwrap_or_else(|| match &source {
+                Some(source) if params.is_fork => next_agent_version(source.version.as_deref()),
+                _ => "1".to_string(),
             });
 
-            (Response::success(id, response), false)
+            match core_handle.send(NostrCommand::CreateAgentDefinition {
+                name: name.clone(),
+                description,
+                role,
+                instructions,
+                version: version.clone(),
+                source_id: source_id.clone(),
+                is_fork: params.is_fork,
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "created",
+                            "name": name,
+                            "version": version,
+                            "source_id": source_id,
+                            "is_fork": params.is_fork
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "SAVE_FAILED",
+                        &format!("Failed to create agent definition: {}", e),
+                    ),
+                    false,
+                ),
+            }
         }
 
-        "save_project" => {
-            #[derive(Deserialize)]
-            struct SaveProjectParams {
-                slug: Option<String>,
-                name: String,
-                #[serde(default)]
+        "delete_agent_definition" => {
+            let agent_id = request.params["id"].as_str().unwrap_or("").trim();
+            if agent_id.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "id is required"),
+                    false,
+                );
+            }
+
+            let store = data_store.lock().unwrap();
+            let Some(definition) = store.content.get_agent_definition(agent_id) else {
+                return (
+                    Response::error(
+                        id,
+                        "AGENT_DEFINITION_NOT_FOUND",
+                        &format!("Agent definition '{}' not found", agent_id),
+                    ),
+                    false,
+                );
+            };
+            // Only the author's kind:5 deletion is honored
+            let is_author = store
+                .user_pubkey
+                .as_deref()
+                .is_some_and(|pk| definition.pubkey.eq_ignore_ascii_case(pk));
+            let (agent_id, name) = (definition.id.clone(), definition.name.clone());
+            drop(store);
+
+            if !is_author {
+                return (
+                    Response::error(
+                        id,
+                        "NOT_AUTHOR",
+                        "Only the author can delete this agent definition",
+                    ),
+                    false,
+                );
+            }
+
+            match core_handle.send(NostrCommand::DeleteAgentDefinition {
+                agent_id: agent_id.clone(),
+                client: Some("tenex-cli".to_string()),
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({"status": "deleted", "id": agent_id, "name": name}),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "DELETE_FAILED",
+                        &format!("Failed to delete agent definition: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "install_agent_definition" => {
+            let param = |key: &str| {
+                request.params[key]
+                    .as_str()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+            };
+            let Some(definition_id) = param("id") else {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "id is required"),
+                    false,
+                );
+            };
+
+            let store = data_store.lock().unwrap();
+            let Some(definition) = store.content.get_agent_definition(definition_id) else {
+                return (
+                    Response::error(
+                        id,
+                        "AGENT_DEFINITION_NOT_FOUND",
+                        &format!("Agent definition '{}' not found", definition_id),
+                    ),
+                    false,
+                );
+            };
+            let name = definition.name.clone();
+
+            // Target backend: explicit, the one running the project, or the
+            // only approved backend with an agent inventory
+            let backend_pubkey = match (param("backend_pubkey"), param("project_slug")) {
+                (Some(backend), _) => Ok(backend.to_string()),
+                (None, Some(project_slug)) => {
+                    match find_project_a_tag_by_slug(&store, project_slug) {
+                        Some(a_tag) => {
+                            store
+                                .first_online_backend_for_project(&a_tag)
+                                .ok_or_else(|| {
+                                    Response::error(
+                                        id,
+                                        "PROJECT_OFFLINE",
+                                        &format!(
+                                            "No online backend is running project '{}'",
+                                            project_slug
+                                        ),
+                                    )
+                                })
+                        }
+                        None => Err(Response::error(
+                            id,
+                            "PROJECT_NOT_FOUND",
+                            &format!("Project '{}' not found", project_slug),
+                        )),
+                    }
+                }
+                (None, None) => {
+                    let mut backends = store.available_install_backends();
+                    if backends.len() == 1 {
+                        Ok(backends.remove(0))
+                    } else {
+                        Err(Response::error(
+                            id,
+                            "BACKEND_REQUIRED",
+                            &format!(
+                                "{} approved backends available; pass backend_pubkey or project_slug",
+                                backends.len()
+                            ),
+                        ))
+                    }
+                }
+            };
+            drop(store);
+            let backend_pubkey = match backend_pubkey {
+                Ok(backend_pubkey) => backend_pubkey,
+                Err(response) => return (response, false),
+            };
+
+            match core_handle.send(NostrCommand::CreateBackendAgent {
+                backend_pubkey: backend_pubkey.clone(),
+                definition_event_id: definition_id.to_string(),
+                slug_override: param("slug").map(String::from),
+                client: Some("tenex-cli".to_string()),
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "install_requested",
+                            "id": definition_id,
+                            "name": name,
+                            "backend_pubkey": backend_pubkey
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "INSTALL_FAILED",
+                        &format!("Failed to request agent install: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "usage" => {
+            let group =
+                match UsageGroup::parse(request.params["group_by"].as_str().unwrap_or("project")) {
+                    Ok(group) => group,
+                    Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+                };
+            let since = request.params["since"].as_u64().unwrap_or(0);
+            let until = request.params["until"].as_u64().unwrap_or(u64::MAX);
+
+            let store = data_store.lock().unwrap();
+            let project_a_tag = match request.params["project_slug"].as_str() {
+                Some(slug) => match find_project_a_tag_by_slug(&store, slug) {
+                    Some(a_tag) => Some(a_tag),
+                    None => {
+                        return (
+                            Response::error(
+                                id,
+                                "PROJECT_NOT_FOUND",
+                                &format!("Project '{}' not found", slug),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None => None,
+            };
+
+            let mut records = Vec::new();
+            for project in store.get_projects() {
+                let a_tag = project.a_tag();
+                if project_a_tag
+                    .as_ref()
+                    .is_some_and(|filter| *filter != a_tag)
+                {
+                    continue;
+                }
+                for thread in store.get_threads(&a_tag) {
+                    records.extend(
+                        store
+                            .get_messages(&thread.id)
+                            .iter()
+                            .filter(|m| m.created_at >= since && m.created_at < until)
+                            .filter_map(|m| UsageRecord::from_message(&a_tag, m)),
+                    );
+                }
+            }
+
+            let (rows, total) = aggregate_usage(records, group, |key| match group {
+                UsageGroup::Project => store
+                    .get_projects()
+                    .iter()
+                    .find(|p| p.a_tag() == key)
+                    .map(|p| p.title.clone()),
+                _ => resolve_author_name(&store, key),
+            });
+            (
+                Response::success(
+                    id,
+                    serde_json::json!({
+                        "group_by": group,
+                        "since": since,
+                        "until": (until != u64::MAX).then_some(until),
+                        "rows": rows,
+                        "total": total
+                    }),
+                ),
+                false,
+            )
+        }
+
+        "list_inbox" => {
+            let include_read = request.params["include_read"].as_bool().unwrap_or(false);
+            let store = data_store.lock().unwrap();
+            let project_a_tag = match request.params["project_slug"].as_str() {
+                Some(slug) => match find_project_a_tag_by_slug(&store, slug) {
+                    Some(a_tag) => Some(a_tag),
+                    None => {
+                        return (
+                            Response::error(
+                                id,
+                                "PROJECT_NOT_FOUND",
+                                &format!("Project '{}' not found", slug),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None => None,
+            };
+
+            let items: Vec<serde_json::Value> = store
+                .inbox
+                .get_items()
+                .iter()
+                .filter(|item| include_read || !item.is_read)
+                .filter(|item| {
+                    project_a_tag
+                        .as_ref()
+                        .is_none_or(|a_tag| item.project_a_tag == *a_tag)
+                })
+                .map(|item| {
+                    let mut obj = inbox_item_to_json(item);
+                    obj["project"] = serde_json::json!(item.project_a_tag);
+                    obj["author_name"] =
+                        serde_json::json!(resolve_author_name(&store, &item.author_pubkey));
+                    obj["is_read"] = serde_json::json!(item.is_read);
+                    obj
+                })
+                .collect();
+
+            // Unanswered asks are derived from the threads, so they also cover
+            // asks that never reached the inbox (e.g. in delegated conversations)
+            let mut asks: Vec<serde_json::Value> = Vec::new();
+            for project in store.get_projects() {
+                let a_tag = project.a_tag();
+                if project_a_tag
+                    .as_ref()
+                    .is_some_and(|filter| *filter != a_tag)
+                {
+                    continue;
+                }
+                for thread in store.get_threads(&a_tag) {
+                    if let Some((ask_id, ask, author_pubkey)) =
+                        store.get_unanswered_ask_for_thread(&thread.id)
+                    {
+                        let target = AskTarget {
+                            ask_id,
+                            ask,
+                            author_pubkey,
+                            thread_id: thread.id.clone(),
+                            project_a_tag: a_tag.clone(),
+                        };
+                        asks.push(target.to_json(&store));
+                    }
+                }
+            }
+            asks.sort_by_key(|ask| std::cmp::Reverse(ask["created_at"].as_u64()));
+
+            (
+                Response::success(id, serde_json::json!({ "items": items, "asks": asks })),
+                false,
+            )
+        }
+
+        "show_ask" => {
+            let ask_id = request.params["ask_id"].as_str().unwrap_or("").trim();
+            let store = data_store.lock().unwrap();
+            match find_ask(&store, ask_id) {
+                Ok(target) => (Response::success(id, target.to_json(&store)), false),
+                Err((code, msg)) => (Response::error(id, code, &msg), false),
+            }
+        }
+
+        "answer_ask" => {
+            let ask_id = request.params["ask_id"].as_str().unwrap_or("").trim();
+            let answers: Vec<AskAnswer> =
+                match serde_json::from_value(request.params["answers"].clone()) {
+                    Ok(answers) => answers,
+                    Err(e) => {
+                        return (
+                            Response::error(
+                                id,
+                                "INVALID_PARAMS",
+                                &format!("Invalid answers: {}", e),
+                            ),
+                            false,
+                        );
+                    }
+                };
+
+            let store = data_store.lock().unwrap();
+            let target = match find_ask(&store, ask_id) {
+                Ok(target) => target,
+                Err((code, msg)) => return (Response::error(id, code, &msg), false),
+            };
+            if store.is_ask_answered_by_user(ask_id) {
+                return (
+                    Response::error(
+                        id,
+                        "ALREADY_ANSWERED",
+                        &format!("Ask '{}' has already been answered", ask_id),
+                    ),
+                    false,
+                );
+            }
+            let content = match format_ask_answers(&target.ask, &answers) {
+                Ok(content) => content,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+            drop(store);
+
+            // Published like the FFI's answer_ask: a reply to the ask that
+            // p-tags its author
+            let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+            if let Err(e) = core_handle.send(NostrCommand::PublishMessage {
+                thread_id: target.thread_id.clone(),
+                project_a_tag: target.project_a_tag.clone(),
+                content,
+                agent_pubkey: None,
+                reply_to: Some(target.ask_id.clone()),
+                nudge_ids: vec![],
+                skill_ids: vec![],
+                ask_author_pubkey: Some(target.author_pubkey.clone()),
+                response_tx: Some(response_tx),
+            }) {
+                return (
+                    Response::error(id, "SEND_FAILED", &format!("Failed to send answer: {}", e)),
+                    false,
+                );
+            }
+            let Ok(event_id) = response_rx.recv_timeout(std::time::Duration::from_secs(10)) else {
+                return (
+                    Response::error(
+                        id,
+                        "PUBLISH_TIMEOUT",
+                        "Timed out waiting for the answer to be published",
+                    ),
+                    false,
+                );
+            };
+            // Only a confirmed publish clears the ask from the inbox
+            data_store.lock().unwrap().inbox.mark_read(&target.ask_id);
+
+            (
+                Response::success(
+                    id,
+                    serde_json::json!({
+                        "status": "answered",
+                        "event_id": event_id,
+                        "thread_id": target.thread_id
+                    }),
+                ),
+                false,
+            )
+        }
+
+        "list_reports" | "list_lessons" => {
+            let filter = match DocumentFilter::from_params(&request.params) {
+                Ok(filter) => filter,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+
+            let store = data_store.lock().unwrap();
+            let project = match &filter.project_slug {
+                Some(slug) => match store.get_projects().iter().find(|p| &p.id == slug) {
+                    Some(project) => Some(project.clone()),
+                    None => {
+                        return (
+                            Response::error(
+                                id,
+                                "PROJECT_NOT_FOUND",
+                                &format!("Project '{}' not found", slug),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None => None,
+            };
+
+            let documents: Vec<serde_json::Value> = if request.method == "list_reports" {
+                store
+                    .reports
+                    .get_reports()
+                    .into_iter()
+                    .filter(|r| {
+                        project
+                            .as_ref()
+                            .is_none_or(|p| r.project_a_tag == p.a_tag())
+                    })
+                    .filter(|r| filter.matches_author(&store, &r.author))
+                    .filter(|r| filter.matches_hashtags(&r.hashtags))
+                    .map(|r| report_to_json(&store, r, false))
+                    .collect()
+            } else {
+                let mut lessons: Vec<_> = store.content.lessons.values().collect();
+                lessons.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+                lessons
+                    .into_iter()
+                    .filter(|l| {
+                        // Lessons without an a-tag belong to the projects their agent is in
+                        project.as_ref().is_none_or(|p| match &l.project_a_tag {
+                            Some(a_tag) => *a_tag == p.a_tag(),
+                            None => p.agent_pubkeys.contains(&l.pubkey),
+                        })
+                    })
+                    .filter(|l| filter.matches_author(&store, &l.pubkey))
+                    .filter(|l| {
+                        filter.matches_hashtags(&l.hashtags)
+                            || l.category
+                                .as_ref()
+                                .is_some_and(|c| filter.matches_hashtags(std::slice::from_ref(c)))
+                    })
+                    .map(|l| lesson_to_json(&store, l, false))
+                    .collect()
+            };
+            (Response::success(id, serde_json::json!(documents)), false)
+        }
+
+        "show_report" => {
+            let report_ref = request.params["report"].as_str().unwrap_or("").trim();
+            if report_ref.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "report is required"),
+                    false,
+                );
+            }
+            let store = data_store.lock().unwrap();
+            match find_report(&store, report_ref) {
+                Some(report) => (
+                    Response::success(id, report_to_json(&store, report, true)),
+                    false,
+                ),
+                None => (
+                    Response::error(
+                        id,
+                        "REPORT_NOT_FOUND",
+                        &format!("Report '{}' not found", report_ref),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "show_lesson" => {
+            let lesson_id = request.params["lesson_id"].as_str().unwrap_or("").trim();
+            let store = data_store.lock().unwrap();
+            match store.content.get_lesson(lesson_id) {
+                Some(lesson) => (
+                    Response::success(id, lesson_to_json(&store, lesson, true)),
+                    false,
+                ),
+                None => (
+                    Response::error(
+                        id,
+                        "LESSON_NOT_FOUND",
+                        &format!("Lesson '{}' not found", lesson_id),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "discuss_report" => {
+            let param = |key: &str| {
+                request.params[key]
+                    .as_str()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+            };
+            let (Some(report_ref), Some(content)) = (param("report"), param("content")) else {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "report and content are required"),
+                    false,
+                );
+            };
+
+            let store = data_store.lock().unwrap();
+            let Some(report) = find_report(&store, report_ref).cloned() else {
+                return (
+                    Response::error(
+                        id,
+                        "REPORT_NOT_FOUND",
+                        &format!("Report '{}' not found", report_ref),
+                    ),
+                    false,
+                );
+            };
+            let Some(project) = store
+                .get_projects()
+                .iter()
+                .find(|p| p.a_tag() == report.project_a_tag)
+                .cloned()
+            else {
+                return (
+                    Response::error(
+                        id,
+                        "PROJECT_NOT_FOUND",
+                        &format!("Project '{}' not found", report.project_a_tag),
+                    ),
+                    false,
+                );
+            };
+
+            // Default recipient: the report's author when it is on the
+            // project, otherwise the PM
+            let agent_pubkey = match param("recipient_slug") {
+                Some(slug) => match find_roster_agent(&store, &project, slug) {
+                    Some(agent) => Some(agent.pubkey),
+                    None => {
+                        return (
+                            Response::error(
+                                id,
+                                "AGENT_NOT_FOUND",
+                                &format!(
+                                    "Agent with slug '{}' not found in project '{}'",
+                                    slug, project.id
+                                ),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None if project.agent_pubkeys.contains(&report.author) => {
+                    Some(report.author.clone())
+                }
+                None => default_agent_pubkey(&project),
+            };
+            drop(store);
+
+            let title: String = if content.chars().count() > 50 {
+                format!("{}...", content.chars().take(50).collect::<String>())
+            } else {
+                content.to_string()
+            };
+            let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+            match core_handle.send(NostrCommand::PublishThread {
+                project_a_tag: project.a_tag(),
+                title,
+                content: content.to_string(),
+                agent_pubkey: agent_pubkey.clone(),
+                nudge_ids: vec![],
+                skill_ids: vec![],
+                reference_conversation_id: None,
+                reference_report_a_tag: Some(report.a_tag()),
+                fork_message_id: None,
+                response_tx: Some(response_tx),
+            }) {
+                Ok(_) => {
+                    let thread_id = response_rx
+                        .recv_timeout(std::time::Duration::from_secs(5))
+                        .ok();
+                    (
+                        Response::success(
+                            id,
+                            serde_json::json!({
+                                "status": "created",
+                                "thread_id": thread_id,
+                                "report": report.a_tag(),
+                                "agent_pubkey": agent_pubkey
+                            }),
+                        ),
+                        false,
+                    )
+                }
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "CREATE_FAILED",
+                        &format!("Failed to create thread: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "list_mcp_tools" => {
+            let store = data_store.lock().unwrap();
+            let mcp_tools: Vec<_> = store
+                .content
+                .get_mcp_tools()
+                .iter()
+                .map(|tool| {
+                    serde_json::json!({
+                        "id": tool.id,
+                        "pubkey": tool.pubkey,
+                        "d_tag": tool.d_tag,
+                        "name": tool.name,
+                        "description": tool.description,
+                        "command": tool.command,
+                        "parameters": tool.parameters,
+                        "capabilities": tool.capabilities,
+                        "server_url": tool.server_url,
+                        "version": tool.version,
+                        "created_at": tool.created_at,
+                    })
+                })
+                .collect();
+            (Response::success(id, serde_json::json!(mcp_tools)), false)
+        }
+
+        "list_skills" => {
+            let store = data_store.lock().unwrap();
+            let skills: Vec<_> = store
+                .content
+                .get_skills()
+                .iter()
+                .map(|skill| {
+                    serde_json::json!({
+                        "id": skill.id,
+                        "pubkey": skill.pubkey,
+                        "title": skill.title,
+                        "description": skill.description,
+                        "content": skill.content,
+                        "hashtags": skill.hashtags,
+                        "file_ids": skill.file_ids,
+                        "created_at": skill.created_at,
+                    })
+                })
+                .collect();
+            (Response::success(id, serde_json::json!(skills)), false)
+        }
+
+        "list_nudges" => {
+            let store = data_store.lock().unwrap();
+            let nudges: Vec<_> = store
+                .content
+                .get_nudges()
+                .iter()
+                .map(|nudge| {
+                    serde_json::json!({
+                        "id": nudge.id,
+                        "pubkey": nudge.pubkey,
+                        "title": nudge.title,
+                        "description": nudge.description,
+                        "content": nudge.content,
+                        "hashtags": nudge.hashtags,
+                        "allowed_tools": nudge.allowed_tools,
+                        "denied_tools": nudge.denied_tools,
+                        "only_tools": nudge.only_tools,
+                        "supersedes": nudge.supersedes,
+                        "created_at": nudge.created_at,
+                    })
+                })
+                .collect();
+            (Response::success(id, serde_json::json!(nudges)), false)
+        }
+
+        "create_nudge" => {
+            #[derive(Deserialize)]
+            struct CreateNudgeParams {
+                title: String,
+                #[serde(default)]
+                description: String,
+                content: String,
+                #[serde(default)]
+                hashtags: Vec<String>,
+                #[serde(default)]
+                allow_tools: Vec<String>,
+                #[serde(default)]
+                deny_tools: Vec<String>,
+                #[serde(default)]
+                only_tools: Vec<String>,
+            }
+
+            let params: CreateNudgeParams = match serde_json::from_value(request.params.clone()) {
+                Ok(p) => p,
+                Err(_) => {
+                    return (
+                        Response::error(id, "INVALID_PARAMS", "Invalid create_nudge params"),
+                        false,
+                    );
+                }
+            };
+
+            let title = params.title.trim().to_string();
+            if title.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "Nudge title cannot be empty"),
+                    false,
+                );
+            }
+            if params.content.trim().is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "Nudge content cannot be empty"),
+                    false,
+                );
+            }
+
+            let (allow_tools, deny_tools, only_tools) = match validate_nudge_tool_modes(
+                params.allow_tools,
+                params.deny_tools,
+                params.only_tools,
+            ) {
+                Ok(tools) => tools,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+
+            match core_handle.send(NostrCommand::CreateNudge {
+                title: title.clone(),
+                description: params.description.trim().to_string(),
+                content: params.content,
+                hashtags: normalize_hashtags(params.hashtags),
+                allow_tools,
+                deny_tools,
+                only_tools,
+            }) {
+                Ok(_) => (
+                    Response::success(id, serde_json::json!({"status": "created", "title": title})),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(id, "SAVE_FAILED", &format!("Failed to create nudge: {}", e)),
+                    false,
+                ),
+            }
+        }
+
+        "update_nudge" => {
+            #[derive(Deserialize)]
+            struct UpdateNudgeParams {
+                nudge_id: String,
+                title: Option<String>,
+                description: Option<String>,
+                content: Option<String>,
+                #[serde(default)]
+                hashtags: Vec<String>,
+                #[serde(default)]
+                allow_tools: Vec<String>,
+                #[serde(default)]
+                deny_tools: Vec<String>,
+                #[serde(default)]
+                only_tools: Vec<String>,
+            }
+
+            let params: UpdateNudgeParams = match serde_json::from_value(request.params.clone()) {
+                Ok(p) => p,
+                Err(_) => {
+                    return (
+                        Response::error(id, "INVALID_PARAMS", "Invalid update_nudge params"),
+                        false,
+                    );
+                }
+            };
+
+            let store = data_store.lock().unwrap();
+            let Some(existing) = store.content.get_nudge(params.nudge_id.trim()).cloned() else {
+                return (
+                    Response::error(
+                        id,
+                        "NUDGE_NOT_FOUND",
+                        &format!("Nudge '{}' not found", params.nudge_id),
+                    ),
+                    false,
+                );
+            };
+            drop(store);
+
+            // Omitted fields keep their current value. Tool permissions are
+            // replaced as a whole when any tool flag is given.
+            let title = params
+                .title
+                .map(|t| t.trim().to_string())
+                .unwrap_or(existing.title);
+            if title.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "Nudge title cannot be empty"),
+                    false,
+                );
+            }
+            let content = params.content.unwrap_or(existing.content);
+            if content.trim().is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "Nudge content cannot be empty"),
+                    false,
+                );
+            }
+            let hashtags = if params.hashtags.is_empty() {
+                existing.hashtags
+            } else {
+                normalize_hashtags(params.hashtags)
+            };
+            let tools_given = !params.allow_tools.is_empty()
+                || !params.deny_tools.is_empty()
+                || !params.only_tools.is_empty();
+            let tools = if tools_given {
+                validate_nudge_tool_modes(params.allow_tools, params.deny_tools, params.only_tools)
+            } else {
+                validate_nudge_tool_modes(
+                    existing.allowed_tools,
+                    existing.denied_tools,
+                    existing.only_tools,
+                )
+            };
+            let (allow_tools, deny_tools, only_tools) = match tools {
+                Ok(tools) => tools,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+
+            match core_handle.send(NostrCommand::UpdateNudge {
+                original_id: existing.id.clone(),
+                title: title.clone(),
+                description: params
+                    .description
+                    .map(|d| d.trim().to_string())
+                    .unwrap_or(existing.description),
+                content,
+                hashtags,
+                allow_tools,
+                deny_tools,
+                only_tools,
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "updated",
+                            "supersedes": existing.id,
+                            "title": title
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(id, "SAVE_FAILED", &format!("Failed to update nudge: {}", e)),
+                    false,
+                ),
+            }
+        }
+
+        "delete_nudge" => {
+            let nudge_id = request.params["nudge_id"].as_str().unwrap_or("").trim();
+            if nudge_id.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "nudge_id is required"),
+                    false,
+                );
+            }
+
+            let store = data_store.lock().unwrap();
+            let Some(nudge) = store.content.get_nudge(nudge_id) else {
+                return (
+                    Response::error(
+                        id,
+                        "NUDGE_NOT_FOUND",
+                        &format!("Nudge '{}' not found", nudge_id),
+                    ),
+                    false,
+                );
+            };
+            // Only the author's kind:5 deletion is honored
+            let is_author = store
+                .user_pubkey
+                .as_deref()
+                .is_some_and(|pk| nudge.pubkey.eq_ignore_ascii_case(pk));
+            let nudge_id = nudge.id.clone();
+            drop(store);
+
+            if !is_author {
+                return (
+                    Response::error(id, "NOT_AUTHOR", "Only the author can delete this nudge"),
+                    false,
+                );
+            }
+
+            match core_handle.send(NostrCommand::DeleteNudge {
+                nudge_id: nudge_id.clone(),
+            }) {
+                Ok(_) => (
+                    Response::success(id, serde_json::json!({"status": "deleted", "id": nudge_id})),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "DELETE_FAILED",
+                        &format!("Failed to delete nudge: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "show_project" => {
+            let project_slug = request.params["project_slug"].as_str().unwrap_or("");
+            let wait_for_project = request.params["wait_for_project"]
+                .as_bool()
+                .unwrap_or(false);
+
+            // If wait_for_project is true, wait for the project roster to appear
+            if wait_for_project {
+                if let Err(err_response) = wait_for_project_roster(data_store, project_slug, id) {
+                    return err_response;
+                }
+            }
+
+            let store = data_store.lock().unwrap();
+
+            // Find the project by slug
+            let project = store
+                .get_projects()
+                .iter()
+                .find(|p| p.id == project_slug)
+                .cloned();
+
+            let project = match project {
+                Some(p) => p,
+                None => {
+                    return (
+                        Response::error(
+                            id,
+                            "PROJECT_NOT_FOUND",
+                            &format!("Project '{}' not found", project_slug),
+                        ),
+                        false,
+                    );
+                }
+            };
+
+            let agents = project_roster_agents(&store, &project);
+            let booted = project_has_available_agent(&store, &project);
+            let a_tag = project.a_tag();
+            let status = store.get_project_status(&a_tag);
+
+            let response = serde_json::json!({
+                "slug": project.id,
+                "name": project.title,
+                "pubkey": project.pubkey,
+                "booted": booted,
+                "agents": agents.iter().map(agent_to_json).collect::<Vec<_>>(),
+                "branches": status.map(|s| s.branches.clone()).unwrap_or_default(),
+                "all_models": agents
+                    .iter()
+                    .filter_map(|agent| agent.model.clone())
+                    .collect::<Vec<_>>(),
+                "all_tools": agents
+                    .iter()
+                    .flat_map(|agent| agent.tools.clone())
+                    .collect::<Vec<_>>(),
+                "backend_pubkey": store.first_online_backend_for_project(&a_tag),
+                "created_at": status.map(|s| s.created_at),
+            });
+
+            (Response::success(id, response), false)
+        }
+
+        "save_project" => {
+            #[derive(Deserialize)]
+            struct SaveProjectParams {
+                slug: Option<String>,
+                name: String,
+                #[serde(default)]
                 description: String,
                 #[serde(default)]
                 agent_pubkeys: Vec<String>,
@@ -1820,10 +3546,184 @@ fn handle_request(
                 }
                 (Response::success(id, response), false)
             } else {
-                (
-                    Response::error(id, "SAVE_FAILED", "Failed to save project"),
+                (
+                    Response::error(id, "SAVE_FAILED", "Failed to save project"),
+                    false,
+                )
+            }
+        }
+
+        "edit_project_roster" => {
+            let project_slug = request.params["project_slug"].as_str().unwrap_or("").trim();
+            let action = request.params["action"].as_str().unwrap_or("").trim();
+            let agent = request.params["agent"].as_str().unwrap_or("").trim();
+            let position = request.params["position"].as_u64().map(|p| p as usize);
+            if project_slug.is_empty() || agent.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "project_slug and agent are required"),
+                    false,
+                );
+            }
+            let edit = match RosterEdit::parse(action, position) {
+                Ok(edit) => edit,
+                Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+            };
+
+            let store = data_store.lock().unwrap();
+            let project = match find_owned_project(&store, project_slug) {
+                Ok(project) => project,
+                Err((code, msg)) => return (Response::error(id, code, &msg), false),
+            };
+            let agent_pubkey = match resolve_agent_pubkey(&store, Some(&project), agent) {
+                Ok(pubkey) => pubkey,
+                Err(msg) => return (Response::error(id, "AGENT_NOT_FOUND", &msg), false),
+            };
+            drop(store);
+
+            // Start from the published p-tag order so only this change lands
+            let agent_pubkeys =
+                match apply_roster_edit(&project.agent_pubkeys, &agent_pubkey, &edit) {
+                    Ok(agent_pubkeys) => agent_pubkeys,
+                    Err(msg) => return (Response::error(id, "INVALID_PARAMS", &msg), false),
+                };
+
+            match core_handle.send(NostrCommand::UpdateProjectAgents {
+                project_a_tag: project.a_tag(),
+                agent_pubkeys: agent_pubkeys.clone(),
+                mcp_tool_ids: project.mcp_tool_ids.clone(),
+                is_private: project.is_private,
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "updated",
+                            "slug": project.id,
+                            "pm": agent_pubkeys.first(),
+                            "agent_pubkeys": agent_pubkeys
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "SAVE_FAILED",
+                        &format!("Failed to update project agents: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "delete_project" => {
+            let project_slug = request.params["project_slug"].as_str().unwrap_or("").trim();
+            if project_slug.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "project_slug is required"),
+                    false,
+                );
+            }
+
+            let store = data_store.lock().unwrap();
+            let project = match find_owned_project(&store, project_slug) {
+                Ok(project) => project,
+                Err((code, msg)) => return (Response::error(id, code, &msg), false),
+            };
+            drop(store);
+
+            match core_handle.send(NostrCommand::DeleteProject {
+                project_a_tag: project.a_tag(),
+                client: Some("tenex-cli".to_string()),
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({"status": "deleted", "slug": project.id}),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "DELETE_FAILED",
+                        &format!("Failed to delete project: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "delete_agent" => {
+            let param = |key: &str| {
+                request.params[key]
+                    .as_str()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+            };
+            let Some(agent) = param("agent") else {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "agent is required"),
+                    false,
+                );
+            };
+
+            let store = data_store.lock().unwrap();
+            // With a project the agent is removed from it; without one the
+            // backend removes it everywhere
+            let project = match param("project_slug") {
+                Some(slug) => match store.get_projects().iter().find(|p| p.id == slug) {
+                    Some(project) => Some(project.clone()),
+                    None => {
+                        return (
+                            Response::error(
+                                id,
+                                "PROJECT_NOT_FOUND",
+                                &format!("Project '{}' not found", slug),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None => None,
+            };
+            let agent_pubkey = match resolve_agent_pubkey(&store, project.as_ref(), agent) {
+                Ok(pubkey) => pubkey,
+                Err(msg) => return (Response::error(id, "AGENT_NOT_FOUND", &msg), false),
+            };
+            drop(store);
+
+            let scope = if project.is_some() {
+                "project"
+            } else {
+                "global"
+            };
+            match core_handle.send(NostrCommand::DeleteAgent {
+                agent_pubkey: agent_pubkey.clone(),
+                project_a_tag: project.as_ref().map(|p| p.a_tag()),
+                reason: param("reason").map(String::from),
+                client: Some("tenex-cli".to_string()),
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "delete_requested",
+                            "agent_pubkey": agent_pubkey,
+                            "scope": scope,
+                            "project": project.map(|p| p.id)
+                        }),
+                    ),
                     false,
-                )
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "DELETE_FAILED",
+                        &format!("Failed to delete agent: {}", e),
+                    ),
+                    false,
+                ),
             }
         }
 
@@ -1971,6 +3871,256 @@ fn handle_request(
             }
         }
 
+        "stop_operations" => {
+            let param = |key: &str| {
+                request.params[key]
+                    .as_str()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+            };
+            let project_slug = param("project_slug");
+            let thread_id = param("thread_id");
+            let agent_slug = param("agent_slug");
+            let descendants = request.params["descendants"].as_bool().unwrap_or(false);
+            let reason = param("reason")
+                .unwrap_or("User stopped operations from tenex-cli")
+                .to_string();
+
+            if project_slug.is_none() && thread_id.is_none() {
+                return (
+                    Response::error(
+                        id,
+                        "INVALID_PARAMS",
+                        "project_slug or thread_id is required",
+                    ),
+                    false,
+                );
+            }
+            if descendants && thread_id.is_none() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "descendants requires thread_id"),
+                    false,
+                );
+            }
+
+            let store = data_store.lock().unwrap();
+            let project = match project_slug {
+                Some(slug) => store.get_projects().iter().find(|p| p.id == slug).cloned(),
+                None => thread_id
+                    .and_then(|t| store.find_project_for_thread(t))
+                    .and_then(|a_tag| {
+                        store
+                            .get_projects()
+                            .iter()
+                            .find(|p| p.a_tag() == a_tag)
+                            .cloned()
+                    }),
+            };
+            let Some(project) = project else {
+                let message = match project_slug {
+                    Some(slug) => format!("Project '{}' not found", slug),
+                    None => format!(
+                        "Could not resolve project for thread '{}'",
+                        thread_id.unwrap_or_default()
+                    ),
+                };
+                return (Response::error(id, "PROJECT_NOT_FOUND", &message), false);
+            };
+            let project_a_tag = project.a_tag();
+
+            let agent_pubkey = match agent_slug {
+                Some(slug) => match find_agent_in_project(&store, &project.id, slug) {
+                    Ok(result) => Some(result.agent_pubkey),
+                    Err(_) => {
+                        return (
+                            Response::error(
+                                id,
+                                "AGENT_NOT_FOUND",
+                                &format!(
+                                    "Agent with slug '{}' not found in project '{}'",
+                                    slug, project.id
+                                ),
+                            ),
+                            false,
+                        );
+                    }
+                },
+                None => None,
+            };
+
+            // Conversations in scope: the thread (and its delegations), or
+            // every active conversation in the project
+            let scope = match thread_id {
+                Some(thread_id) => {
+                    let mut ids = vec![thread_id.to_string()];
+                    if descendants {
+                        ids.extend(store.runtime_hierarchy.get_descendants(thread_id));
+                    }
+                    ids
+                }
+                None => store.operations.get_active_event_ids(&project_a_tag),
+            };
+
+            let mut event_ids = Vec::new();
+            let mut agent_pubkeys: Vec<String> = Vec::new();
+            for event_id in scope {
+                let working: Vec<String> = store
+                    .operations
+                    .get_working_agents(&event_id)
+                    .into_iter()
+                    .filter(|pk| agent_pubkey.as_ref().is_none_or(|a| a == pk))
+                    .collect();
+                if working.is_empty() {
+                    continue;
+                }
+                for pk in working {
+                    if !agent_pubkeys.contains(&pk) {
+                        agent_pubkeys.push(pk);
+                    }
+                }
+                event_ids.push(event_id);
+            }
+            let agents: Vec<serde_json::Value> = agent_pubkeys
+                .iter()
+                .map(|pk| {
+                    serde_json::json!({
+                        "pubkey": pk,
+                        "name": resolve_author_name(&store, pk),
+                    })
+                })
+                .collect();
+            drop(store);
+
+            // Nothing running: don't publish a stop event nobody will act on
+            if event_ids.is_empty() {
+                return (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "idle",
+                            "project": project.id,
+                            "events": [],
+                            "agents": [],
+                        }),
+                    ),
+                    false,
+                );
+            }
+
+            match core_handle.send(NostrCommand::StopOperations {
+                project_a_tag,
+                event_ids: event_ids.clone(),
+                agent_pubkeys,
+                reason,
+            }) {
+                Ok(_) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "stop_sent",
+                            "project": project.id,
+                            "events": event_ids,
+                            "agents": agents,
+                        }),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(
+                        id,
+                        "STOP_FAILED",
+                        &format!("Failed to send stop command: {}", e),
+                    ),
+                    false,
+                ),
+            }
+        }
+
+        "api_token_create" => {
+            let name = request.params["name"].as_str().unwrap_or("");
+            let string_list = |key: &str| -> Vec<String> {
+                request.params[key]
+                    .as_array()
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(|v| v.as_str())
+                            .map(|v| v.trim().to_string())
+                            .filter(|v| !v.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            };
+
+            let result = api_tokens.lock().unwrap().create(
+                name,
+                string_list("projects"),
+                string_list("agents"),
+            );
+            match result {
+                Ok((token, secret)) => {
+                    let mut json = token.to_json();
+                    json["token"] = serde_json::json!(secret);
+                    (Response::success(id, json), false)
+                }
+                Err(e) => (
+                    Response::error(id, "API_TOKEN_CREATE_FAILED", &e.to_string()),
+                    false,
+                ),
+            }
+        }
+
+        "api_token_list" => {
+            let tokens: Vec<serde_json::Value> = api_tokens
+                .lock()
+                .unwrap()
+                .tokens()
+                .iter()
+                .map(|token| token.to_json())
+                .collect();
+            (
+                Response::success(id, serde_json::json!({ "tokens": tokens })),
+                false,
+            )
+        }
+
+        "api_token_revoke" => {
+            let token = request.params["token"].as_str().unwrap_or("").trim();
+            if token.is_empty() {
+                return (
+                    Response::error(id, "INVALID_PARAMS", "token is required"),
+                    false,
+                );
+            }
+
+            let result = api_tokens.lock().unwrap().revoke(token);
+            match result {
+                Ok(Some(revoked)) => (
+                    Response::success(
+                        id,
+                        serde_json::json!({
+                            "status": "revoked",
+                            "id": revoked.id,
+                            "name": revoked.name
+                        }),
+                    ),
+                    false,
+                ),
+                Ok(None) => (
+                    Response::error(
+                        id,
+                        "API_TOKEN_NOT_FOUND",
+                        &format!("No API token with id or name '{}'", token),
+                    ),
+                    false,
+                ),
+                Err(e) => (
+                    Response::error(id, "API_TOKEN_REVOKE_FAILED", &e.to_string()),
+                    false,
+                ),
+            }
+        }
+
         _ => (
             Response::error(
                 id,
@@ -1982,6 +4132,190 @@ fn handle_request(
     }
 }
 
+/// An ask event and where answers to it are published
+struct AskTarget {
+    ask_id: String,
+    ask: tenex_core::models::AskEvent,
+    author_pubkey: String,
+    thread_id: String,
+    project_a_tag: String,
+}
+
+impl AskTarget {
+    fn to_json(&self, store: &AppDataStore) -> serde_json::Value {
+        let mut obj = ask_to_json(&self.ask);
+        obj["id"] = serde_json::json!(self.ask_id);
+        obj["author_pubkey"] = serde_json::json!(self.author_pubkey);
+        obj["author_name"] = serde_json::json!(resolve_author_name(store, &self.author_pubkey));
+        obj["thread_id"] = serde_json::json!(self.thread_id);
+        obj["project"] = serde_json::json!(self.project_a_tag);
+        obj["created_at"] = serde_json::json!(store
+            .get_messages(&self.thread_id)
+            .iter()
+            .find(|m| m.id == self.ask_id)
+            .map(|m| m.created_at));
+        obj["answered"] = serde_json::json!(store.is_ask_answered_by_user(&self.ask_id));
+        obj
+    }
+}
+
+/// Look up an ask event with its thread and project
+fn find_ask(store: &AppDataStore, ask_id: &str) -> Result<AskTarget, (&'static str, String)> {
+    let not_found = || ("ASK_NOT_FOUND", format!("Ask '{}' not found", ask_id));
+    let (ask, author_pubkey) = store.get_ask_event_by_id(ask_id).ok_or_else(not_found)?;
+    let inbox_item = store
+        .inbox
+        .get_items()
+        .iter()
+        .find(|item| item.id == ask_id);
+
+    let thread_id = store
+        .get_thread_info_for_event(ask_id)
+        .map(|(thread_id, _)| thread_id)
+        .or_else(|| inbox_item.and_then(|item| item.thread_id.clone()))
+        .ok_or_else(not_found)?;
+    let project_a_tag = store
+        .find_project_for_thread(&thread_id)
+        .or_else(|| {
+            inbox_item
+                .map(|item| item.project_a_tag.clone())
+                .filter(|a_tag| !a_tag.is_empty())
+        })
+        .ok_or_else(|| {
+            (
+                "PROJECT_NOT_FOUND",
+                format!("No project found for thread '{}'", thread_id),
+            )
+        })?;
+
+    Ok(AskTarget {
+        ask_id: ask_id.to_string(),
+        ask,
+        author_pubkey,
+        thread_id,
+        project_a_tag,
+    })
+}
+
+/// Project, author and hashtag filters shared by `list_reports` and `list_lessons`
+struct DocumentFilter {
+    project_slug: Option<String>,
+    /// Author pubkey or display name
+    author: Option<String>,
+    /// Lowercased, without '#'; a document matches if it has any of them
+    hashtags: Vec<String>,
+}
+
+impl DocumentFilter {
+    fn from_params(params: &serde_json::Value) -> Result<Self, String> {
+        let param = |key: &str| {
+            params[key]
+                .as_str()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        let hashtags = match &params["hashtags"] {
+            serde_json::Value::Null => Vec::new(),
+            serde_json::Value::Array(values) => values
+                .iter()
+                .map(|v| v.as_str().ok_or("hashtags must be an array of strings"))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .map(|t| t.trim().trim_start_matches('#').to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            _ => return Err("hashtags must be an array of strings".to_string()),
+        };
+
+        Ok(Self {
+            project_slug: param("project_slug"),
+            author: param("author"),
+            hashtags,
+        })
+    }
+
+    fn matches_author(&self, store: &AppDataStore, pubkey: &str) -> bool {
+        let Some(author) = &self.author else {
+            return true;
+        };
+        pubkey.eq_ignore_ascii_case(author)
+            || resolve_author_name(store, pubkey).is_some_and(|name| name == *author)
+    }
+
+    fn matches_hashtags(&self, hashtags: &[String]) -> bool {
+        self.hashtags.is_empty()
+            || hashtags.iter().any(|t| {
+                self.hashtags
+                    .contains(&t.trim_start_matches('#').to_lowercase())
+            })
+    }
+}
+
+/// Find a report by slug or by its `30023:<pubkey>:<slug>` coordinate
+fn find_report<'a>(
+    store: &'a AppDataStore,
+    report_ref: &str,
+) -> Option<&'a tenex_core::models::Report> {
+    if report_ref.starts_with("30023:") {
+        store.reports.get_report_by_a_tag(report_ref)
+    } else {
+        store.reports.get_report(report_ref)
+    }
+}
+
+pub(super) fn report_to_json(
+    store: &AppDataStore,
+    report: &tenex_core::models::Report,
+    include_content: bool,
+) -> serde_json::Value {
+    let mut obj = serde_json::json!({
+        "id": report.id,
+        "slug": report.slug,
+        "a_tag": report.a_tag(),
+        "project_a_tag": report.project_a_tag,
+        "author": report.author,
+        "author_name": resolve_author_name(store, &report.author),
+        "title": report.title,
+        "summary": report.summary,
+        "document": report.document,
+        "hashtags": report.hashtags,
+        "reading_time_mins": report.reading_time_mins,
+        "created_at": report.created_at,
+    });
+    if include_content {
+        obj["content"] = serde_json::json!(report.content);
+        obj["versions"] = serde_json::json!(store.reports.get_report_versions(&report.slug).len());
+    }
+    obj
+}
+
+fn lesson_to_json(
+    store: &AppDataStore,
+    lesson: &tenex_core::models::Lesson,
+    include_sections: bool,
+) -> serde_json::Value {
+    let mut obj = serde_json::json!({
+        "id": lesson.id,
+        "author": lesson.pubkey,
+        "author_name": resolve_author_name(store, &lesson.pubkey),
+        "title": lesson.title,
+        "category": lesson.category,
+        "project_a_tag": lesson.project_a_tag,
+        "hashtags": lesson.hashtags,
+        "created_at": lesson.created_at,
+    });
+    if include_sections {
+        obj["content"] = serde_json::json!(lesson.content);
+        obj["detailed"] = serde_json::json!(lesson.detailed);
+        obj["reasoning"] = serde_json::json!(lesson.reasoning);
+        obj["metacognition"] = serde_json::json!(lesson.metacognition);
+        obj["reflection"] = serde_json::json!(lesson.reflection);
+    } else {
+        obj["summary"] = serde_json::json!(lesson.content.chars().take(160).collect::<String>());
+    }
+    obj
+}
+
 /// Serialize a ProjectAgent to JSON for CLI output
 fn agent_to_json(a: &tenex_core::models::ProjectAgent) -> serde_json::Value {
     serde_json::json!({
@@ -1998,7 +4332,70 @@ fn agent_to_json(a: &tenex_core::models::ProjectAgent) -> serde_json::Value {
 /// 1. Check if pubkey belongs to a project roster -> return agent name
 /// 2. Otherwise check profile name from kind:0
 /// 3. Return None if no real name found (don't return truncated pubkey)
-fn resolve_author_name(store: &AppDataStore, pubkey: &str) -> Option<String> {
+/// Depth-first walk of the delegation tree below `conversation_id`
+fn collect_conversation_tree(
+    store: &AppDataStore,
+    conversation_id: &str,
+    parent_id: Option<&str>,
+    depth: usize,
+    out: &mut Vec<serde_json::Value>,
+) {
+    // Guard against cycles in malformed delegation tags
+    if out.iter().any(|c| c["id"] == conversation_id) {
+        return;
+    }
+
+    let active_agents = store
+        .operations
+        .agent_tracking
+        .get_active_agents_for_conversation(conversation_id);
+    out.push(serde_json::json!({
+        "id": conversation_id,
+        "parent_id": parent_id,
+        "depth": depth,
+        "title": store.get_thread_by_id(conversation_id).map(|t| t.title.clone()),
+        "active_agents": active_agents,
+        "runtime_ms": store.runtime_hierarchy.get_individual_runtime(conversation_id),
+        "message_count": store.get_messages(conversation_id).len(),
+    }));
+
+    let mut children: Vec<&String> = store
+        .runtime_hierarchy
+        .get_children(conversation_id)
+        .map(|children| children.iter().collect())
+        .unwrap_or_default();
+    children.sort_by_key(|child| {
+        store
+            .runtime_hierarchy
+            .get_conversation_created_at(child)
+            .unwrap_or_default()
+    });
+    for child in children {
+        collect_conversation_tree(store, child, Some(conversation_id), depth + 1, out);
+    }
+}
+
+pub(super) fn message_to_json(
+    store: &AppDataStore,
+    m: &tenex_core::models::Message,
+) -> serde_json::Value {
+    let mut obj = serde_json::json!({
+        "id": m.id,
+        "content": m.content,
+        "created_at": m.created_at,
+        "pubkey": m.pubkey,
+        "final_reply": store
+            .user_pubkey
+            .as_deref()
+            .is_some_and(|user_pubkey| is_final_reply(m, user_pubkey)),
+    });
+    if let Some(name) = resolve_author_name(store, &m.pubkey) {
+        obj["author_name"] = serde_json::json!(name);
+    }
+    obj
+}
+
+pub(super) fn resolve_author_name(store: &AppDataStore, pubkey: &str) -> Option<String> {
     for project in store.get_projects() {
         for agent in project_roster_agents(store, project) {
             if agent.pubkey == pubkey {
@@ -2118,6 +4515,69 @@ fn find_agent_in_project(
     Err(AgentLookupError::AgentNotFound)
 }
 
+/// Find a project the logged-in user may republish. A kind:31933 signed by
+/// anyone else would land under their own coordinate instead.
+fn find_owned_project(
+    store: &AppDataStore,
+    project_slug: &str,
+) -> Result<tenex_core::models::Project, (&'static str, String)> {
+    let project = store
+        .get_projects()
+        .iter()
+        .find(|p| p.id == project_slug)
+        .cloned()
+        .ok_or_else(|| {
+            (
+                "PROJECT_NOT_FOUND",
+                format!("Project '{}' not found", project_slug),
+            )
+        })?;
+
+    let is_owner = store
+        .user_pubkey
+        .as_deref()
+        .is_some_and(|pk| project.pubkey.eq_ignore_ascii_case(pk));
+    if !is_owner {
+        return Err((
+            "NOT_OWNER",
+            format!("Only the owner of project '{}' can change it", project_slug),
+        ));
+    }
+
+    Ok(project)
+}
+
+/// Resolve an agent given as a hex pubkey or a slug: the project roster
+/// first, then the approved backend inventory
+fn resolve_agent_pubkey(
+    store: &AppDataStore,
+    project: Option<&tenex_core::models::Project>,
+    agent: &str,
+) -> Result<String, String> {
+    if agent.len() == 64 && agent.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(agent.to_lowercase());
+    }
+    if let Some(found) = project.and_then(|project| find_roster_agent(store, project, agent)) {
+        return Ok(found.pubkey);
+    }
+
+    let matches: Vec<String> = store
+        .agent_inventory()
+        .into_iter()
+        .filter(|item| item.slug == agent)
+        .map(|item| item.pubkey)
+        .collect();
+    match matches.as_slice() {
+        [pubkey] => Ok(pubkey.clone()),
+        [] => Err(format!("Agent '{}' not found", agent)),
+        _ => Err(format!(
+            "Agent slug '{}' matches {} agents; pass the pubkey instead",
+            agent,
+            matches.len()
+        )),
+    }
+}
+
 /// Validates skill IDs from JSON-RPC parameters.
 ///
 /// Performs comprehensive validation:
@@ -2274,10 +4734,109 @@ fn validate_nudge_ids_param(nudge_ids_param: &serde_json::Value) -> Result<Vec<S
     Ok(validated)
 }
 
+/// Nudge tool permissions are either exclusive (only-tool) or additive
+/// (allow-tool / deny-tool). When only-tool is present the worker drops the
+/// allow/deny tags (see `build_nudge_tool_tags`), so mixing the modes is
+/// rejected instead of silently discarding tools.
+///
+/// Returns the trimmed, deduplicated (allow, deny, only) lists.
+#[allow(clippy::type_complexity)]
+fn validate_nudge_tool_modes(
+    allow_tools: Vec<String>,
+    deny_tools: Vec<String>,
+    only_tools: Vec<String>,
+) -> Result<(Vec<String>, Vec<String>, Vec<String>), String> {
+    let normalize = |tools: Vec<String>| {
+        let mut normalized: Vec<String> = Vec::new();
+        for tool in tools {
+            let tool = tool.trim();
+            if !tool.is_empty() && !normalized.iter().any(|t| t == tool) {
+                normalized.push(tool.to_string());
+            }
+        }
+        normalized
+    };
+    let (allow_tools, deny_tools, only_tools) = (
+        normalize(allow_tools),
+        normalize(deny_tools),
+        normalize(only_tools),
+    );
+
+    if !only_tools.is_empty() && (!allow_tools.is_empty() || !deny_tools.is_empty()) {
+        return Err(
+            "only_tools (exclusive mode) cannot be combined with allow_tools or deny_tools (additive mode)"
+                .to_string(),
+        );
+    }
+    if let Some(tool) = allow_tools.iter().find(|t| deny_tools.contains(t)) {
+        return Err(format!("Tool '{}' cannot be both allowed and denied", tool));
+    }
+
+    Ok((allow_tools, deny_tools, only_tools))
+}
+
+/// Version for a fork: the source's numeric version plus one (as in the TUI fork form)
+fn next_agent_version(version: Option<&str>) -> String {
+    version
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .map(|v| (v + 1).to_string())
+        .unwrap_or_else(|| "2".to_string())
+}
+
+/// Trim hashtags, drop a leading '#' and duplicates
+fn normalize_hashtags(hashtags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in hashtags {
+        let tag = tag.trim().trim_start_matches('#');
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn tools(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn nudge_tool_modes_are_exclusive() {
+        let (allow, deny, only) =
+            validate_nudge_tool_modes(tools(&[" shell ", "shell"]), tools(&["delegate"]), vec![])
+                .unwrap();
+        assert_eq!(allow, tools(&["shell"]));
+        assert_eq!(deny, tools(&["delegate"]));
+        assert!(only.is_empty());
+
+        assert!(validate_nudge_tool_modes(vec![], vec![], tools(&["read_path"])).is_ok());
+        assert!(
+            validate_nudge_tool_modes(tools(&["shell"]), vec![], tools(&["read_path"])).is_err()
+        );
+        assert!(
+            validate_nudge_tool_modes(vec![], tools(&["shell"]), tools(&["read_path"])).is_err()
+        );
+        assert!(validate_nudge_tool_modes(tools(&["shell"]), tools(&["shell"]), vec![]).is_err());
+    }
+
+    #[test]
+    fn fork_bumps_agent_version() {
+        assert_eq!(next_agent_version(Some("3")), "4");
+        assert_eq!(next_agent_version(Some("beta")), "2");
+        assert_eq!(next_agent_version(None), "2");
+    }
+
+    #[test]
+    fn hashtags_are_normalized() {
+        assert_eq!(
+            normalize_hashtags(tools(&["#rust", "rust", " ", "cli "])),
+            tools(&["rust", "cli"])
+        );
+    }
+
     fn make_pending_request(
         request_id: &str,
         requester_pubkey: &str,
@@ -0,0 +1,262 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+/// Render markdown as plain terminal text: underlined headings, bulleted and
+/// numbered lists, indented code blocks, and link targets after their text.
+pub(super) fn render_markdown(text: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+
+    let mut out = String::new();
+    let mut line = String::new();
+    let mut heading: Option<HeadingLevel> = None;
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut quote_depth = 0usize;
+    let mut in_code_block = false;
+    let mut link_url: Option<String> = None;
+    let mut link_text_start = 0usize;
+    let mut table_row: Vec<String> = Vec::new();
+
+    let flush = |out: &mut String, line: &mut String, quote_depth: usize| {
+        let prefix = "> ".repeat(quote_depth);
+        out.push_str(&prefix);
+        out.push_str(line.trim_end());
+        out.push('\n');
+        line.clear();
+    };
+    let blank = |out: &mut String| {
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    };
+
+    for event in Parser::new_ext(text, options) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Heading { level, .. } => {
+                    blank(&mut out);
+                    heading = Some(level);
+                }
+                Tag::Paragraph => {
+                    if lists.is_empty() {
+                        blank(&mut out);
+                    }
+                }
+                Tag::BlockQuote(_) => {
+                    blank(&mut out);
+                    quote_depth += 1;
+                }
+                Tag::CodeBlock(_) => {
+                    blank(&mut out);
+                    in_code_block = true;
+                }
+                Tag::List(start) => {
+                    if lists.is_empty() {
+                        blank(&mut out);
+                    } else if !line.trim().is_empty() {
+                        flush(&mut out, &mut line, quote_depth);
+                    }
+                    lists.push(start);
+                }
+                Tag::Item => {
+                    let indent = "  ".repeat(lists.len().saturating_sub(1));
+                    let marker = match lists.last_mut() {
+                        Some(Some(n)) => {
+                            *n += 1;
+                            format!("{}. ", *n - 1)
+                        }
+                        _ => "- ".to_string(),
+                    };
+                    line.push_str(&indent);
+                    line.push_str(&marker);
+                }
+                Tag::Link { dest_url, .. } => {
+                    link_url = Some(dest_url.to_string());
+                    link_text_start = line.len();
+                }
+                Tag::Table(_) => blank(&mut out),
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Heading(_) => {
+                    let width = line.trim_end().chars().count();
+                    flush(&mut out, &mut line, quote_depth);
+                    match heading.take() {
+                        Some(HeadingLevel::H1) => out.push_str(&"=".repeat(width)),
+                        _ => out.push_str(&"-".repeat(width)),
+                    }
+                    out.push('\n');
+                }
+                TagEnd::Paragraph => {
+                    if !line.trim().is_empty() {
+                        flush(&mut out, &mut line, quote_depth);
+                    }
+                }
+                TagEnd::BlockQuote(_) => quote_depth = quote_depth.saturating_sub(1),
+                TagEnd::CodeBlock => in_code_block = false,
+                TagEnd::List(_) => {
+                    lists.pop();
+                }
+                TagEnd::Item => {
+                    if !line.trim().is_empty() {
+                        flush(&mut out, &mut line, quote_depth);
+                    }
+                }
+                TagEnd::Link => {
+                    if let Some(url) = link_url.take() {
+                        if line[link_text_start..] != url {
+                            line.push_str(&format!(" ({})", url));
+                        }
+                    }
+                }
+                TagEnd::TableCell => table_row.push(std::mem::take(&mut line)),
+                TagEnd::TableHead | TagEnd::TableRow => {
+                    line = table_row.join(" | ");
+                    table_row.clear();
+                    flush(&mut out, &mut line, quote_depth);
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if in_code_block {
+                    for code_line in text.lines() {
+                        line.push_str("    ");
+                        line.push_str(code_line);
+                        flush(&mut out, &mut line, quote_depth);
+                    }
+                } else {
+                    line.push_str(&text);
+                }
+            }
+            Event::Code(code) => {
+                line.push('`');
+                line.push_str(&code);
+                line.push('`');
+            }
+            Event::SoftBreak => line.push(' '),
+            Event::HardBreak => flush(&mut out, &mut line, quote_depth),
+            Event::Rule => {
+                blank(&mut out);
+                out.push_str("---\n");
+            }
+            _ => {}
+        }
+    }
+    if !line.trim().is_empty() {
+        flush(&mut out, &mut line, quote_depth);
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Title underlined, then "By ... · N min read", hashtags, and the body
+fn render_document(title: &str, byline: &[String], hashtags: &[String], body: &str) -> String {
+    let mut out = format!("{}\n{}\n", title, "=".repeat(title.chars().count()));
+    if !byline.is_empty() {
+        out.push_str(&byline.join(" · "));
+        out.push('\n');
+    }
+    if !hashtags.is_empty() {
+        let tags: Vec<String> = hashtags.iter().map(|t| format!("#{}", t)).collect();
+        out.push_str(&tags.join(" "));
+        out.push('\n');
+    }
+    out.push('\n');
+    out.push_str(body);
+    out
+}
+
+fn string_list(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn author_line(document: &serde_json::Value) -> Option<String> {
+    document["author_name"]
+        .as_str()
+        .or_else(|| document["author"].as_str())
+        .map(|author| format!("By {}", author))
+}
+
+/// Render a `show_report` result as a readable document
+pub(super) fn render_report(report: &serde_json::Value) -> String {
+    let mut byline: Vec<String> = author_line(report).into_iter().collect();
+    if let Some(mins) = report["reading_time_mins"].as_u64() {
+        byline.push(format!("{} min read", mins));
+    }
+    render_document(
+        report["title"].as_str().unwrap_or("Untitled"),
+        &byline,
+        &string_list(&report["hashtags"]),
+        &render_markdown(report["content"].as_str().unwrap_or("")),
+    )
+}
+
+/// Render a `show_lesson` result with each of its sections under a heading
+pub(super) fn render_lesson(lesson: &serde_json::Value) -> String {
+    let mut byline: Vec<String> = author_line(lesson).into_iter().collect();
+    if let Some(category) = lesson["category"].as_str() {
+        byline.push(category.to_string());
+    }
+
+    let mut body = render_markdown(lesson["content"].as_str().unwrap_or(""));
+    for (key, heading) in [
+        ("detailed", "Detailed"),
+        ("reasoning", "Reasoning"),
+        ("metacognition", "Metacognition"),
+        ("reflection", "Reflection"),
+    ] {
+        if let Some(section) = lesson[key].as_str().filter(|s| !s.trim().is_empty()) {
+            body.push_str(&format!(
+                "\n\n{}\n{}\n{}",
+                heading,
+                "-".repeat(heading.len()),
+                render_markdown(section)
+            ));
+        }
+    }
+
+    render_document(
+        lesson["title"].as_str().unwrap_or("Untitled Lesson"),
+        &byline,
+        &string_list(&lesson["hashtags"]),
+        &body,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown_blocks() {
+        let rendered = render_markdown(
+            "# Plan\n\nShip **it** soon.\n\n- one\n- [docs](https://example.com)\n\n1. first\n2. second\n\n```\nlet x = 1;\n```",
+        );
+        assert_eq!(
+            rendered,
+            "Plan\n====\n\nShip it soon.\n\n- one\n- docs (https://example.com)\n\n1. first\n2. second\n\n    let x = 1;"
+        );
+    }
+
+    #[test]
+    fn test_render_report_header() {
+        let report = serde_json::json!({
+            "title": "Weekly",
+            "author_name": "planner",
+            "reading_time_mins": 2,
+            "hashtags": ["status"],
+            "content": "All good.",
+        });
+        assert_eq!(
+            render_report(&report),
+            "Weekly\n======\nBy planner · 2 min read\n#status\n\nAll good."
+        );
+    }
+}
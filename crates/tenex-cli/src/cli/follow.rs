@@ -0,0 +1,480 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::client::{get_thread_messages, send_command_raw, subscribe};
+use super::config::CliConfig;
+use super::protocol::{
+    CliCommand, Notification, SubscribeFilter, EVENT_MESSAGE, EVENT_OPERATIONS_STATUS,
+    EVENT_STREAM_DELTA,
+};
+
+const FOLLOW_RECV_TIMEOUT_MS: u64 = 500;
+/// How long `follow` waits for work to start when the thread is idle at startup
+const FOLLOW_START_GRACE_SECS: u64 = 10;
+/// How long --until-idle waits for the first agent to start
+const UNTIL_IDLE_START_GRACE_SECS: u64 = 60;
+/// Quiet period after the last agent stops before the tree counts as idle.
+/// Covers the gap between a delegation being published and the delegate starting.
+const IDLE_SETTLE_SECS: u64 = 5;
+
+/// A conversation in a thread's delegation tree (`get_conversation_tree`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct TreeConversation {
+    pub id: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub depth: usize,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub active_agents: Vec<String>,
+    #[serde(default)]
+    pub runtime_ms: u64,
+    #[serde(default)]
+    pub message_count: usize,
+}
+
+/// Fetch the delegation tree of a thread, root first (depth-first order)
+pub(super) fn get_conversation_tree(
+    thread_id: &str,
+    data_dir: &Path,
+    config: Option<&CliConfig>,
+) -> Result<Vec<TreeConversation>> {
+    let response = send_command_raw(
+        &CliCommand::GetConversationTree {
+            thread_id: thread_id.to_string(),
+        },
+        data_dir,
+        config,
+    )?;
+    if let Some(error) = response.error {
+        anyhow::bail!("Error [{}]: {}", error.code, error.message);
+    }
+
+    let conversations = response
+        .result
+        .and_then(|mut result| result.get_mut("conversations").map(|c| c.take()))
+        .unwrap_or_default();
+    Ok(serde_json::from_value(conversations)?)
+}
+
+/// Tracks which conversations of a followed tree have working agents,
+/// from kind:24133 operations status notifications
+pub(super) struct ActivityTracker {
+    /// created_at of the latest status seen per conversation
+    latest: HashMap<String, u64>,
+    /// Conversations with working agents
+    active: HashMap<String, Vec<String>>,
+    seen_activity: bool,
+    started_at: Instant,
+    idle_since: Option<Instant>,
+    start_grace: Duration,
+}
+
+impl ActivityTracker {
+    /// Start from the tree's current state. If nothing is active yet, wait up
+    /// to `start_grace` for work to begin before reporting the tree as idle.
+    pub(super) fn new(tree: &[TreeConversation], start_grace: Duration) -> Self {
+        let now = Instant::now();
+        let active: HashMap<String, Vec<String>> = tree
+            .iter()
+            .filter(|c| !c.active_agents.is_empty())
+            .map(|c| (c.id.clone(), c.active_agents.clone()))
+            .collect();
+
+        Self {
+            latest: HashMap::new(),
+            seen_activity: !active.is_empty(),
+            idle_since: active.is_empty().then_some(now),
+            active,
+            started_at: now,
+            start_grace,
+        }
+    }
+
+    /// Apply an operations status notification; other events are ignored
+    pub(super) fn observe(&mut self, notification: &Notification) {
+        if notification.event != EVENT_OPERATIONS_STATUS {
+            return;
+        }
+        let Some(conversation_id) = notification.thread_id.clone() else {
+            return;
+        };
+
+        // Ignore out-of-order statuses
+        let created_at = notification.data["created_at"].as_u64().unwrap_or_default();
+        if self
+            .latest
+            .get(&conversation_id)
+            .is_some_and(|latest| *latest > created_at)
+        {
+            return;
+        }
+        self.latest.insert(conversation_id.clone(), created_at);
+
+        let agents: Vec<String> =
+            serde_json::from_value(notification.data["agent_pubkeys"].clone()).unwrap_or_default();
+        if agents.is_empty() {
+            self.active.remove(&conversation_id);
+        } else {
+            self.seen_activity = true;
+            self.active.insert(conversation_id, agents);
+        }
+
+        if self.active.is_empty() {
+            self.idle_since.get_or_insert_with(Instant::now);
+        } else {
+            self.idle_since = None;
+        }
+    }
+
+    /// Whether every agent has stopped and stayed stopped for the settle period
+    pub(super) fn is_settled(&self, now: Instant) -> bool {
+        let Some(idle_since) = self.idle_since else {
+            return false;
+        };
+        let waited_for_start =
+            self.seen_activity || now.duration_since(self.started_at) >= self.start_grace;
+        waited_for_start && now.duration_since(idle_since) >= Duration::from_secs(IDLE_SETTLE_SECS)
+    }
+}
+
+/// Prints messages and stream deltas of a delegation tree, indenting
+/// sub-conversations by their delegation depth
+struct FollowPrinter {
+    root: String,
+    depths: HashMap<String, usize>,
+    printed: HashSet<String>,
+    /// Conversation and agent whose deltas are on the current line
+    streaming: Option<(String, String)>,
+}
+
+impl FollowPrinter {
+    fn new(root: &str, tree: &[TreeConversation]) -> Self {
+        Self {
+            root: root.to_string(),
+            depths: tree.iter().map(|c| (c.id.clone(), c.depth)).collect(),
+            printed: HashSet::new(),
+            streaming: None,
+        }
+    }
+
+    fn indent(&mut self, conversation_id: &str, ancestors: &[String]) -> String {
+        let depth = match self.depths.get(conversation_id) {
+            Some(depth) => *depth,
+            None => {
+                let depth = ancestors
+                    .iter()
+                    .position(|a| *a == self.root)
+                    .map(|i| i + 1)
+                    .unwrap_or_default();
+                self.depths.insert(conversation_id.to_string(), depth);
+                depth
+            }
+        };
+        "  ".repeat(depth)
+    }
+
+    /// End a line of streamed deltas
+    fn finish(&mut self) {
+        if self.streaming.take().is_some() {
+            println!();
+        }
+    }
+
+    fn message(
+        &mut self,
+        conversation_id: &str,
+        ancestors: &[String],
+        message: &serde_json::Value,
+    ) {
+        let id = message["id"].as_str().unwrap_or_default();
+        if !self.printed.insert(id.to_string()) {
+            return;
+        }
+
+        // The content was already shown as stream deltas
+        let pubkey = message["pubkey"].as_str().unwrap_or_default();
+        if self.streaming.as_ref() == Some(&(conversation_id.to_string(), pubkey.to_string())) {
+            self.finish();
+            return;
+        }
+        self.finish();
+
+        let indent = self.indent(conversation_id, ancestors);
+        let content = message["content"].as_str().unwrap_or_default();
+        let mut lines = content.lines();
+        println!(
+            "{}{}: {}",
+            indent,
+            author_label(message),
+            lines.next().unwrap_or_default()
+        );
+        for line in lines {
+            println!("{}  {}", indent, line);
+        }
+    }
+
+    fn delta(&mut self, notification: &Notification) {
+        let Some(conversation_id) = notification.thread_id.as_deref() else {
+            return;
+        };
+        let Some(text) = notification.data["text_delta"].as_str() else {
+            return;
+        };
+
+        let indent = self.indent(conversation_id, &notification.ancestors);
+        let agent = notification.data["agent_pubkey"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let stream_key = (conversation_id.to_string(), agent);
+        if self.streaming.as_ref() != Some(&stream_key) {
+            self.finish();
+            print!("{}{}: ", indent, author_label(&notification.data));
+            self.streaming = Some(stream_key);
+        }
+
+        print!("{}", text.replace('\n', &format!("\n{}  ", indent)));
+        std::io::stdout().flush().ok();
+    }
+}
+
+/// Roster name when known, otherwise a short pubkey
+fn author_label(value: &serde_json::Value) -> String {
+    if let Some(name) = value["author_name"].as_str() {
+        return name.to_string();
+    }
+    let pubkey = value["pubkey"]
+        .as_str()
+        .or_else(|| value["agent_pubkey"].as_str())
+        .unwrap_or_default();
+    pubkey.chars().take(8).collect()
+}
+
+/// Print a thread and its delegated sub-conversations, then stream new
+/// messages and deltas until no agents are active
+pub(super) fn follow_thread(
+    thread_id: &str,
+    data_dir: &Path,
+    config: Option<&CliConfig>,
+) -> Result<()> {
+    // Subscribe before reading history so nothing published in between is missed
+    let mut stream = subscribe(
+        SubscribeFilter {
+            threads: vec![thread_id.to_string()],
+            descendants: true,
+            events: vec![
+                EVENT_MESSAGE.to_string(),
+                EVENT_STREAM_DELTA.to_string(),
+                EVENT_OPERATIONS_STATUS.to_string(),
+            ],
+            ..Default::default()
+        },
+        data_dir,
+        config,
+    )?;
+
+    let tree = get_conversation_tree(thread_id, data_dir, config)?;
+    if tree.iter().all(|c| c.message_count == 0) {
+        eprintln!("No messages for thread {} yet, waiting...", thread_id);
+    }
+
+    let mut printer = FollowPrinter::new(thread_id, &tree);
+    let mut history = Vec::new();
+    for conversation in &tree {
+        for message in get_thread_messages(&conversation.id, data_dir, config)? {
+            history.push((conversation.id.clone(), message));
+        }
+    }
+    history.sort_by_key(|(_, message)| message["created_at"].as_u64().unwrap_or_default());
+    for (conversation_id, message) in &history {
+        printer.message(conversation_id, &[], message);
+    }
+
+    let mut activity = ActivityTracker::new(&tree, Duration::from_secs(FOLLOW_START_GRACE_SECS));
+    loop {
+        if let Some(notification) =
+            stream.recv_timeout(Duration::from_millis(FOLLOW_RECV_TIMEOUT_MS))?
+        {
+            activity.observe(&notification);
+            let conversation_id = notification.thread_id.as_deref().unwrap_or(thread_id);
+            match notification.event.as_str() {
+                EVENT_MESSAGE => {
+                    printer.message(conversation_id, &notification.ancestors, &notification.data)
+                }
+                EVENT_STREAM_DELTA => printer.delta(&notification),
+                _ => {}
+            }
+        }
+
+        if activity.is_settled(Instant::now()) {
+            printer.finish();
+            eprintln!("No agents active");
+            return Ok(());
+        }
+    }
+}
+
+/// Wait until every agent in the thread and its delegated sub-conversations
+/// has stopped, then print the final answer and the delegation tree with
+/// per-conversation runtimes. Returns false on timeout.
+pub(super) fn wait_until_idle(
+    thread_id: &str,
+    our_message_id: &str,
+    timeout_secs: u64,
+    data_dir: &Path,
+    config: Option<&CliConfig>,
+    pretty: bool,
+) -> Result<bool> {
+    eprintln!(
+        "Waiting up to {} seconds for all agents to finish...",
+        timeout_secs
+    );
+
+    let mut stream = subscribe(
+        SubscribeFilter {
+            threads: vec![thread_id.to_string()],
+            descendants: true,
+            events: vec![EVENT_OPERATIONS_STATUS.to_string()],
+            ..Default::default()
+        },
+        data_dir,
+        config,
+    )?;
+    let tree = get_conversation_tree(thread_id, data_dir, config)?;
+    let mut activity =
+        ActivityTracker::new(&tree, Duration::from_secs(UNTIL_IDLE_START_GRACE_SECS));
+
+    let start = Instant::now();
+    let timeout = Duration::from_secs(timeout_secs);
+    let idle = loop {
+        if let Some(notification) =
+            stream.recv_timeout(Duration::from_millis(FOLLOW_RECV_TIMEOUT_MS))?
+        {
+            activity.observe(&notification);
+        }
+        if activity.is_settled(Instant::now()) {
+            break true;
+        }
+        if start.elapsed() >= timeout {
+            break false;
+        }
+    };
+
+    // Re-read the tree: delegations and runtimes are final now
+    let conversations = get_conversation_tree(thread_id, data_dir, config)?;
+    let messages = get_thread_messages(thread_id, data_dir, config)?;
+    let summary = serde_json::json!({
+        "idle": idle,
+        "answer": final_answer(&messages, our_message_id),
+        "total_runtime_ms": conversations.iter().map(|c| c.runtime_ms).sum::<u64>(),
+        "conversations": conversations,
+    });
+    if pretty {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!("{}", serde_json::to_string(&summary)?);
+    }
+
+    Ok(idle)
+}
+
+/// The latest message after ours from another author: once delegations have
+/// finished this is the answer rather than an "I'll ask X" hand-off
+fn final_answer(messages: &[serde_json::Value], our_message_id: &str) -> Option<serde_json::Value> {
+    let ours = messages.iter().find(|m| m["id"] == our_message_id);
+    let our_pubkey = ours.map(|m| &m["pubkey"]);
+    let since = ours
+        .and_then(|m| m["created_at"].as_u64())
+        .unwrap_or_default();
+
+    messages
+        .iter()
+        .filter(|m| m["id"] != our_message_id && Some(&m["pubkey"]) != our_pubkey)
+        .filter(|m| m["created_at"].as_u64().unwrap_or_default() >= since)
+        .max_by_key(|m| m["created_at"].as_u64().unwrap_or_default())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(id: &str, depth: usize, active_agents: &[&str]) -> TreeConversation {
+        TreeConversation {
+            id: id.to_string(),
+            parent_id: None,
+            depth,
+            title: None,
+            active_agents: active_agents.iter().map(|a| a.to_string()).collect(),
+            runtime_ms: 0,
+            message_count: 0,
+        }
+    }
+
+    fn status(thread_id: &str, agents: &[&str], created_at: u64) -> Notification {
+        Notification {
+            id: 1,
+            event: EVENT_OPERATIONS_STATUS.to_string(),
+            project: None,
+            thread_id: Some(thread_id.to_string()),
+            ancestors: vec![],
+            data: serde_json::json!({ "agent_pubkeys": agents, "created_at": created_at }),
+        }
+    }
+
+    #[test]
+    fn test_settles_after_delegation_finishes() {
+        let tree = [conversation("root", 0, &["pm"])];
+        let mut activity = ActivityTracker::new(&tree, Duration::ZERO);
+        let settled_later = || Instant::now() + Duration::from_secs(IDLE_SETTLE_SECS + 1);
+        assert!(!activity.is_settled(settled_later()));
+
+        // PM hands off to a delegate before stopping
+        activity.observe(&status("child", &["coder"], 2));
+        activity.observe(&status("root", &[], 3));
+        assert!(!activity.is_settled(settled_later()));
+
+        activity.observe(&status("child", &[], 4));
+        assert!(activity.is_settled(settled_later()));
+        assert!(!activity.is_settled(Instant::now()));
+    }
+
+    #[test]
+    fn test_ignores_out_of_order_status() {
+        let tree = [conversation("root", 0, &[])];
+        let mut activity = ActivityTracker::new(&tree, Duration::ZERO);
+        activity.observe(&status("root", &[], 5));
+        activity.observe(&status("root", &["pm"], 4));
+        let after_settle = Instant::now() + Duration::from_secs(IDLE_SETTLE_SECS + 1);
+        assert!(activity.is_settled(after_settle));
+    }
+
+    #[test]
+    fn test_waits_for_work_to_start() {
+        let tree = [conversation("root", 0, &[])];
+        let activity = ActivityTracker::new(&tree, Duration::from_secs(60));
+        let after_settle = Instant::now() + Duration::from_secs(IDLE_SETTLE_SECS + 1);
+        assert!(!activity.is_settled(after_settle));
+        assert!(activity.is_settled(Instant::now() + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_final_answer_skips_handoffs() {
+        let messages = vec![
+            serde_json::json!({"id": "old", "pubkey": "pm", "created_at": 1, "content": "earlier"}),
+            serde_json::json!({"id": "ask", "pubkey": "user", "created_at": 2, "content": "question"}),
+            serde_json::json!({"id": "handoff", "pubkey": "pm", "created_at": 3, "content": "I'll ask coder"}),
+            serde_json::json!({"id": "answer", "pubkey": "pm", "created_at": 9, "content": "done"}),
+        ];
+        let answer = final_answer(&messages, "ask").unwrap();
+        assert_eq!(answer["id"], "answer");
+        assert!(final_answer(&messages[..2], "ask").is_none());
+    }
+}
@@ -1,26 +1,33 @@
 use anyhow::Result;
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{
         sse::{Event, KeepAlive},
         IntoResponse, Sse,
     },
-    routing::post,
+    routing::{get, post},
     Json, Router,
 };
 use futures::stream::Stream;
+use nostr_sdk::Keys;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
+use std::path::Path as FsPath;
 use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tower_http::cors::CorsLayer;
 
-use super::roster::default_agent_pubkey;
+use super::api_tokens::{ApiToken, ApiTokenRegistry};
+use super::follow::ActivityTracker;
+use super::protocol::Notification;
+use super::response_chains::{ChainsSnapshot, ResponseChainEntry, ResponseChainStore};
+use super::roster::{default_agent_pubkey, find_roster_agent, project_roster_agents};
+use super::subscriptions::operations_status_notification;
 use crate::nostr::{DataChange, NostrCommand};
 use crate::store::AppDataStore;
-use tenex_core::models::Project;
+use tenex_core::models::{Message, OperationsStatus, Project};
 use tenex_core::runtime::CoreHandle;
 
 // ============================================================================
@@ -33,10 +40,26 @@ use tenex_core::runtime::CoreHandle;
 pub enum InputContentItem {
     #[serde(rename = "input_text")]
     InputText { text: String },
+    /// Image given as a base64 data URL or a remote URL
     #[serde(rename = "input_image")]
-    InputImage { image_url: String },
+    InputImage {
+        #[serde(default)]
+        image_url: Option<String>,
+        #[serde(default)]
+        file_id: Option<String>,
+    },
+    /// File given as base64 `file_data` (optionally a data URL) or a remote `file_url`
     #[serde(rename = "input_file")]
-    InputFile { file_id: String },
+    InputFile {
+        #[serde(default)]
+        file_data: Option<String>,
+        #[serde(default)]
+        file_url: Option<String>,
+        #[serde(default)]
+        filename: Option<String>,
+        #[serde(default)]
+        file_id: Option<String>,
+    },
 }
 
 /// A message in the input array
@@ -121,12 +144,58 @@ pub struct OutputMessage {
     pub content: Vec<OutputContentItem>,
 }
 
+/// Reasoning text inside a reasoning output item
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum ReasoningContentItem {
+    #[serde(rename = "reasoning_text")]
+    ReasoningText { text: String },
+}
+
+/// Agent reasoning surfaced as a `reasoning` output item
+#[derive(Debug, Clone, Serialize)]
+pub struct ReasoningItem {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub summary: Vec<serde_json::Value>,
+    pub content: Vec<ReasoningContentItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// A tool the agent called, surfaced as a `function_call` output item.
+/// These are informational: the agent already executed the tool.
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionCallItem {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Item in the response output array (each variant carries its own `type`)
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum OutputItem {
+    Message(OutputMessage),
+    Reasoning(ReasoningItem),
+    FunctionCall(FunctionCallItem),
+}
+
 /// Usage information
 #[derive(Debug, Clone, Serialize)]
 pub struct ResponseUsage {
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub total_tokens: u32,
+    /// TENEX extension: summed llm-cost-usd of the contributing agent messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenex_cost_usd: Option<f64>,
 }
 
 /// OpenAI Responses API response object
@@ -140,7 +209,7 @@ pub struct ResponsesResponse {
     pub object: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<serde_json::Value>,
-    pub output: Vec<OutputMessage>,
+    pub output: Vec<OutputItem>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_text: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -166,7 +235,7 @@ pub enum StreamEvent {
     #[serde(rename = "response.output_item.added")]
     OutputItemAdded {
         output_index: usize,
-        item: OutputMessage,
+        item: OutputItem,
     },
     #[serde(rename = "response.content_part.added")]
     ContentPartAdded {
@@ -186,15 +255,484 @@ pub enum StreamEvent {
         content_index: usize,
         text: String,
     },
+    #[serde(rename = "response.reasoning_text.delta")]
+    ReasoningTextDelta {
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.reasoning_text.done")]
+    ReasoningTextDone {
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        text: String,
+    },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: usize,
+        arguments: String,
+    },
     #[serde(rename = "response.output_item.done")]
     OutputItemDone {
         output_index: usize,
-        item: OutputMessage,
+        item: OutputItem,
     },
     #[serde(rename = "response.completed")]
     ResponseCompleted { response: ResponsesResponse },
 }
 
+impl StreamEvent {
+    /// SSE event name (matches the `type` field)
+    fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::ResponseCreated { .. } => "response.created",
+            StreamEvent::ResponseInProgress { .. } => "response.in_progress",
+            StreamEvent::OutputItemAdded { .. } => "response.output_item.added",
+            StreamEvent::ContentPartAdded { .. } => "response.content_part.added",
+            StreamEvent::OutputTextDelta { .. } => "response.output_text.delta",
+            StreamEvent::OutputTextDone { .. } => "response.output_text.done",
+            StreamEvent::ReasoningTextDelta { .. } => "response.reasoning_text.delta",
+            StreamEvent::ReasoningTextDone { .. } => "response.reasoning_text.done",
+            StreamEvent::FunctionCallArgumentsDelta { .. } => {
+                "response.function_call_arguments.delta"
+            }
+            StreamEvent::FunctionCallArgumentsDone { .. } => {
+                "response.function_call_arguments.done"
+            }
+            StreamEvent::OutputItemDone { .. } => "response.output_item.done",
+            StreamEvent::ResponseCompleted { .. } => "response.completed",
+        }
+    }
+}
+
+// ============================================================================
+// OpenAI Chat Completions API Types
+// ============================================================================
+
+/// Content part of a chat message (only text parts are forwarded to agents)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ChatContentPart {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "image_url")]
+    ImageUrl { image_url: serde_json::Value },
+}
+
+/// Content of a chat message - either a simple string or array of content parts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ChatMessageContent {
+    Text(String),
+    Parts(Vec<ChatContentPart>),
+}
+
+impl ChatMessageContent {
+    /// Concatenated text of the message (non-text parts are skipped)
+    pub fn text(&self) -> String {
+        match self {
+            ChatMessageContent::Text(text) => text.clone(),
+            ChatMessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ChatContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// A message in the chat completions messages array
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    /// Content may be null (e.g. assistant tool-call messages)
+    #[serde(default)]
+    pub content: Option<ChatMessageContent>,
+}
+
+impl ChatMessage {
+    fn text(&self) -> String {
+        self.content
+            .as_ref()
+            .map(ChatMessageContent::text)
+            .unwrap_or_default()
+    }
+}
+
+/// OpenAI Chat Completions request body
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    /// Agent slug within the project; omit or use "tenex" for the PM/default agent
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    /// Whether to stream the response as chat.completion.chunk events
+    #[serde(default)]
+    pub stream: bool,
+    /// Streaming options; `include_usage` adds a final chunk carrying `usage`
+    #[serde(default)]
+    pub stream_options: Option<ChatStreamOptions>,
+    /// Optional user identifier
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// `stream_options` of a chat completions request
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChatStreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// Assistant message in a chat completion choice
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatResponseMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// A completed choice
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatChoice {
+    pub index: usize,
+    pub message: ChatResponseMessage,
+    pub finish_reason: String,
+}
+
+/// Chat completions usage information
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+    /// TENEX extension: summed llm-cost-usd of the contributing agent messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenex_cost_usd: Option<f64>,
+}
+
+impl From<ResponseUsage> for ChatUsage {
+    fn from(usage: ResponseUsage) -> Self {
+        Self {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.total_tokens,
+            tenex_cost_usd: usage.tenex_cost_usd,
+        }
+    }
+}
+
+/// OpenAI Chat Completions response object (non-streaming)
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionResponse {
+    pub id: String,
+    pub object: String,
+    /// Unix timestamp in seconds
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ChatUsage>,
+}
+
+/// Incremental delta of a streamed choice
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChatDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// A streamed choice
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatChunkChoice {
+    pub index: usize,
+    pub delta: ChatDelta,
+    pub finish_reason: Option<String>,
+}
+
+/// OpenAI chat.completion.chunk streaming object
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: String,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatChunkChoice>,
+    /// Only set on the final chunk when `stream_options.include_usage` is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ChatUsage>,
+}
+
+impl ChatCompletionChunk {
+    fn new(
+        id: &str,
+        created: u64,
+        model: &str,
+        delta: ChatDelta,
+        finish_reason: Option<&str>,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created,
+            model: model.to_string(),
+            choices: vec![ChatChunkChoice {
+                index: 0,
+                delta,
+                finish_reason: finish_reason.map(String::from),
+            }],
+            usage: None,
+        }
+    }
+
+    /// Final chunk with no choices, carrying the turn's usage
+    fn usage(id: &str, created: u64, model: &str, usage: ChatUsage) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created,
+            model: model.to_string(),
+            choices: Vec::new(),
+            usage: Some(usage),
+        }
+    }
+}
+
+// ============================================================================
+// Anthropic Messages API Types
+// ============================================================================
+
+/// Request content block (only text blocks are forwarded to agents)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+pub enum AnthropicRequestBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Message or system content - either a simple string or array of content blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicContent {
+    Text(String),
+    Blocks(Vec<AnthropicRequestBlock>),
+}
+
+impl AnthropicContent {
+    /// Concatenated text of the content (non-text blocks are skipped)
+    pub fn text(&self) -> String {
+        match self {
+            AnthropicContent::Text(text) => text.clone(),
+            AnthropicContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| match block {
+                    AnthropicRequestBlock::Text { text } => Some(text.as_str()),
+                    AnthropicRequestBlock::Other => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// A message in the Messages API messages array
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicMessage {
+    pub role: String,
+    pub content: AnthropicContent,
+}
+
+/// Anthropic Messages API request body
+#[derive(Debug, Deserialize)]
+pub struct MessagesRequest {
+    /// Agent slug within the project; "tenex" targets the PM/default agent
+    #[serde(default)]
+    pub model: Option<String>,
+    pub messages: Vec<AnthropicMessage>,
+    /// System prompt - a string or array of text blocks
+    #[serde(default)]
+    pub system: Option<AnthropicContent>,
+    /// Accepted for compatibility; agents decide their own output length
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Whether to stream the response as Messages API SSE events
+    #[serde(default)]
+    pub stream: bool,
+    /// Extended thinking; agent reasoning is returned only when enabled
+    #[serde(default)]
+    pub thinking: Option<AnthropicThinking>,
+}
+
+/// `thinking` request option: `{"type": "enabled", "budget_tokens": ...}` or
+/// `{"type": "disabled"}`. The budget is ignored; agents decide how much to reason.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicThinking {
+    #[serde(rename = "type")]
+    pub thinking_type: String,
+}
+
+impl MessagesRequest {
+    /// Whether agent reasoning should be returned as thinking blocks
+    pub fn thinking_enabled(&self) -> bool {
+        self.thinking
+            .as_ref()
+            .is_some_and(|thinking| thinking.thinking_type != "disabled")
+    }
+}
+
+/// Response content block
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum AnthropicResponseBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    /// Agent reasoning. TENEX agents do not produce Anthropic signatures, so
+    /// `signature` is always empty.
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+}
+
+/// Messages API usage information
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AnthropicUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl From<ResponseUsage> for AnthropicUsage {
+    fn from(usage: ResponseUsage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+        }
+    }
+}
+
+/// Anthropic Messages API response object
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagesResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub role: String,
+    pub model: String,
+    pub content: Vec<AnthropicResponseBlock>,
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+    pub usage: AnthropicUsage,
+}
+
+/// Delta payload of a content_block_delta event
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum AnthropicBlockDelta {
+    #[serde(rename = "text_delta")]
+    TextDelta { text: String },
+    #[serde(rename = "thinking_delta")]
+    ThinkingDelta { thinking: String },
+}
+
+/// Stop information carried by message_delta
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicMessageDelta {
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+}
+
+/// Messages API streaming events
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum AnthropicStreamEvent {
+    #[serde(rename = "message_start")]
+    MessageStart { message: MessagesResponse },
+    #[serde(rename = "content_block_start")]
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicResponseBlock,
+    },
+    #[serde(rename = "content_block_delta")]
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicBlockDelta,
+    },
+    #[serde(rename = "content_block_stop")]
+    ContentBlockStop { index: usize },
+    #[serde(rename = "message_delta")]
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: AnthropicUsage,
+    },
+    #[serde(rename = "message_stop")]
+    MessageStop,
+    #[serde(rename = "error")]
+    Error { error: AnthropicErrorBody },
+}
+
+impl AnthropicStreamEvent {
+    /// SSE event name (matches the `type` field)
+    fn name(&self) -> &'static str {
+        match self {
+            AnthropicStreamEvent::MessageStart { .. } => "message_start",
+            AnthropicStreamEvent::ContentBlockStart { .. } => "content_block_start",
+            AnthropicStreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+            AnthropicStreamEvent::ContentBlockStop { .. } => "content_block_stop",
+            AnthropicStreamEvent::MessageDelta { .. } => "message_delta",
+            AnthropicStreamEvent::MessageStop => "message_stop",
+            AnthropicStreamEvent::Error { .. } => "error",
+        }
+    }
+}
+
+/// Anthropic-style error response
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicError {
+    #[serde(rename = "type")]
+    pub response_type: String,
+    pub error: AnthropicErrorBody,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnthropicErrorBody {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    pub message: String,
+}
+
+impl From<OpenAIError> for AnthropicError {
+    /// Translate the shared OpenAI-style errors into the Anthropic error shape
+    fn from(error: OpenAIError) -> Self {
+        let error_type = match error.error.error_type.as_str() {
+            "invalid_request_error"
+            | "not_found_error"
+            | "authentication_error"
+            | "permission_error" => error.error.error_type.as_str(),
+            _ => "api_error",
+        };
+        Self {
+            response_type: "error".to_string(),
+            error: AnthropicErrorBody {
+                error_type: error_type.to_string(),
+                message: error.error.message,
+            },
+        }
+    }
+}
+
 // ============================================================================
 // Server State and Handlers
 // ============================================================================
@@ -240,6 +778,16 @@ impl OpenAIError {
     pub fn timeout(message: impl Into<String>) -> Self {
         Self::new(message, "timeout_error")
     }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        let mut error = Self::new(message, "authentication_error");
+        error.error.code = Some("invalid_api_key".to_string());
+        error
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(message, "permission_error")
+    }
 }
 
 /// Shared server state
@@ -250,9 +798,18 @@ pub struct HTTPServerState {
     pub data_store: Arc<Mutex<AppDataStore>>,
     /// Broadcast channel for DataChange events - each SSE stream subscribes to get its own receiver
     pub data_tx: broadcast::Sender<DataChange>,
-    /// Maps OpenAI response IDs (resp_xxx) to Nostr event IDs for conversation chaining
-    /// Uses tokio::sync::Mutex since it's only accessed in async handlers
-    pub response_id_map: Arc<tokio::sync::Mutex<HashMap<String, String>>>,
+    /// Maps OpenAI response IDs (resp_xxx) to their thread for conversation chaining,
+    /// persisted in the data dir. Uses tokio::sync::Mutex since it's only accessed in async handlers
+    pub response_chains: Arc<tokio::sync::Mutex<ResponseChainStore>>,
+    /// Maps chat transcripts to the thread that produced them, so a
+    /// follow-up chat completions request continues the same thread
+    pub chat_thread_map: Arc<tokio::sync::Mutex<ChatThreadMap>>,
+    /// Signing keys of the logged-in user, used to upload attachments to Blossom
+    pub keys: Option<Keys>,
+    /// Bearer tokens shared with the daemon, which creates and revokes them
+    pub api_tokens: Arc<Mutex<ApiTokenRegistry>>,
+    /// Reject requests while no tokens exist (set for non-loopback binds)
+    pub require_auth: bool,
 }
 
 /// Start the OpenAI Responses API server
@@ -261,17 +818,33 @@ pub async fn run_server(
     core_handle: CoreHandle,
     data_store: Arc<Mutex<AppDataStore>>,
     data_tx: broadcast::Sender<DataChange>,
+    api_tokens: Arc<Mutex<ApiTokenRegistry>>,
+    keys: Option<Keys>,
+    data_dir: &FsPath,
 ) -> Result<()> {
+    let require_auth = !is_loopback_bind(&bind_addr);
     let state = HTTPServerState {
         core_handle,
         data_store,
         data_tx,
-        // response_id_map uses tokio::sync::Mutex since it's accessed in async handlers
-        response_id_map: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+        // response_chains uses tokio::sync::Mutex since it's accessed in async handlers
+        response_chains: Arc::new(tokio::sync::Mutex::new(ResponseChainStore::load(data_dir))),
+        chat_thread_map: Arc::new(tokio::sync::Mutex::new(ChatThreadMap::new(
+            MAX_CHAT_TRANSCRIPTS,
+        ))),
+        keys,
+        api_tokens,
+        require_auth,
     };
 
     let app = Router::new()
         .route("/:project_dtag/responses", post(responses_handler))
+        .route(
+            "/:project_dtag/chat/completions",
+            post(chat_completions_handler),
+        )
+        .route("/:project_dtag/v1/messages", post(messages_handler))
+        .route("/:project_dtag/ws", get(super::ws::ws_handler))
         .layer(CorsLayer::permissive())
         .with_state(state);
 
@@ -281,6 +854,12 @@ pub async fn run_server(
         bind_addr
     );
     eprintln!("Endpoint: http://{}/:project_dtag/responses", bind_addr);
+    eprintln!(
+        "Endpoint: http://{}/:project_dtag/chat/completions",
+        bind_addr
+    );
+    eprintln!("Endpoint: http://{}/:project_dtag/v1/messages", bind_addr);
+    eprintln!("Endpoint: ws://{}/:project_dtag/ws", bind_addr);
 
     axum::serve(listener, app).await?;
 
@@ -288,33 +867,143 @@ pub async fn run_server(
 }
 
 /// Helper to create an OpenAI error JSON response
-fn openai_error_response(
+pub(super) fn openai_error_response(
     status: StatusCode,
     error: OpenAIError,
 ) -> (StatusCode, axum::Json<OpenAIError>) {
     (status, axum::Json(error))
 }
 
-/// Safely extract a prefix of a string (handles multi-byte UTF-8)
-fn safe_string_prefix(s: &str, max_chars: usize) -> String {
-    s.chars().take(max_chars).collect()
+/// Error half of handler results: HTTP status plus OpenAI-style error body
+pub(super) type ApiError = (StatusCode, axum::Json<OpenAIError>);
+
+/// Whether a bind address only accepts local connections
+pub(super) fn is_loopback_bind(bind_addr: &str) -> bool {
+    match bind_addr.parse::<std::net::SocketAddr>() {
+        Ok(addr) => addr.ip().is_loopback(),
+        Err(_) => bind_addr
+            .rsplit_once(':')
+            .is_some_and(|(host, _)| host.eq_ignore_ascii_case("localhost")),
+    }
 }
 
-/// Handler for POST /:project_dtag/responses
-async fn responses_handler(
-    Path(project_dtag): Path<String>,
-    State(state): State<HTTPServerState>,
-    Json(request): Json<ResponsesRequest>,
-) -> Result<impl IntoResponse, (StatusCode, axum::Json<OpenAIError>)> {
-    // Validate message roles if using messages array
-    if let ResponseInput::Messages(ref messages) = request.input {
-        for msg in messages {
-            if !["user", "assistant", "system"].contains(&msg.role.as_str()) {
-                return Err(openai_error_response(
-                    StatusCode::BAD_REQUEST,
-                    OpenAIError::bad_request(format!(
-                        "Invalid message role '{}'. Must be one of: user, assistant, system",
-                        msg.role
+/// Authenticate the request's API token and check it grants access to the project.
+/// Accepts `Authorization: Bearer <token>` or `x-api-key: <token>`.
+/// Returns the matched token, or `None` when no tokens are configured and the
+/// server only listens on loopback.
+pub(super) fn authorize_request(
+    state: &HTTPServerState,
+    headers: &HeaderMap,
+    project_dtag: &str,
+) -> Result<Option<ApiToken>, ApiError> {
+    let registry = state.api_tokens.lock().unwrap();
+    if !registry.is_enabled() {
+        if state.require_auth {
+            return Err(openai_error_response(
+                StatusCode::UNAUTHORIZED,
+                OpenAIError::unauthorized(
+                    "No API tokens exist. Create one with 'tenex-cli token create' \
+                     before exposing the server beyond localhost.",
+                ),
+            ));
+        }
+        return Ok(None);
+    }
+
+    let Some(secret) = request_api_key(headers) else {
+        return Err(openai_error_response(
+            StatusCode::UNAUTHORIZED,
+            OpenAIError::unauthorized(
+                "Missing API key. Provide it as 'Authorization: Bearer <token>'.",
+            ),
+        ));
+    };
+    let Some(token) = registry.authenticate(secret) else {
+        return Err(openai_error_response(
+            StatusCode::UNAUTHORIZED,
+            OpenAIError::unauthorized("Incorrect API key provided."),
+        ));
+    };
+    if !token.allows_project(project_dtag) {
+        return Err(openai_error_response(
+            StatusCode::FORBIDDEN,
+            OpenAIError::forbidden(format!(
+                "API key does not grant access to project '{}'",
+                project_dtag
+            )),
+        ));
+    }
+
+    Ok(Some(token.clone()))
+}
+
+/// Raw API key from the Authorization (Bearer) or x-api-key header
+fn request_api_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, token)| token);
+    let api_key = || {
+        headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+    };
+    bearer
+        .or_else(api_key)
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Check an agent-scoped token may address the resolved recipient agent
+pub(super) fn authorize_agent(
+    state: &HTTPServerState,
+    project: &Project,
+    token: Option<&ApiToken>,
+    agent_pubkey: &str,
+) -> Result<(), ApiError> {
+    let Some(token) = token.filter(|token| !token.agents.is_empty()) else {
+        return Ok(());
+    };
+
+    let store = state.data_store.lock().unwrap();
+    let allowed = project_roster_agents(&store, project)
+        .iter()
+        .any(|agent| agent.pubkey == agent_pubkey && token.allows_agent(&agent.name));
+    if allowed {
+        Ok(())
+    } else {
+        Err(openai_error_response(
+            StatusCode::FORBIDDEN,
+            OpenAIError::forbidden("API key does not grant access to this agent"),
+        ))
+    }
+}
+
+/// Safely extract a prefix of a string (handles multi-byte UTF-8)
+fn safe_string_prefix(s: &str, max_chars: usize) -> String {
+    s.chars().take(max_chars).collect()
+}
+
+/// Handler for POST /:project_dtag/responses
+async fn responses_handler(
+    Path(project_dtag): Path<String>,
+    State(state): State<HTTPServerState>,
+    headers: HeaderMap,
+    Json(request): Json<ResponsesRequest>,
+) -> Result<impl IntoResponse, (StatusCode, axum::Json<OpenAIError>)> {
+    let token = authorize_request(&state, &headers, &project_dtag)?;
+
+    // Validate message roles if using messages array
+    if let ResponseInput::Messages(ref messages) = request.input {
+        for msg in messages {
+            if !["user", "assistant", "system"].contains(&msg.role.as_str()) {
+                return Err(openai_error_response(
+                    StatusCode::BAD_REQUEST,
+                    OpenAIError::bad_request(format!(
+                        "Invalid message role '{}'. Must be one of: user, assistant, system",
+                        msg.role
                     )),
                 ));
             }
@@ -322,7 +1011,7 @@ async fn responses_handler(
     }
 
     // Extract the user message content from the input
-    let user_content = extract_user_content(&request.input).map_err(|e| {
+    let user_parts = extract_user_content(&request.input).map_err(|e| {
         openai_error_response(
             StatusCode::BAD_REQUEST,
             OpenAIError::bad_request(e.to_string()),
@@ -345,102 +1034,83 @@ async fn responses_handler(
             OpenAIError::server_error("Project has no roster agents".to_string()),
         ));
     };
-
-    // Create a title from the user message (first 50 chars, safely handling UTF-8)
-    let title: String = if user_content.chars().count() > 50 {
-        format!("{}...", safe_string_prefix(&user_content, 50))
-    } else {
-        user_content.clone()
+    authorize_agent(&state, &project, token.as_ref(), &agent_pubkey)?;
+
+    // A resp_* ID continues the thread it was answered in; any other value is
+    // treated as a Nostr event ID and referenced from a new thread
+    let previous_response = match request.previous_response_id.as_deref() {
+        Some(prev_id) if prev_id.starts_with("resp_") => {
+            let entry = state.response_chains.lock().await.get(prev_id).cloned();
+            match entry {
+                Some(entry) if entry.project_a_tag == project_a_tag => Some(entry),
+                Some(_) => {
+                    return Err(openai_error_response(
+                        StatusCode::BAD_REQUEST,
+                        OpenAIError::bad_request(format!(
+                            "Previous response '{}' belongs to a different project",
+                            prev_id
+                        )),
+                    ));
+                }
+                None => {
+                    return Err(openai_error_response(
+                        StatusCode::NOT_FOUND,
+                        OpenAIError::not_found(format!(
+                            "Previous response with id '{}' not found",
+                            prev_id
+                        )),
+                    ));
+                }
+            }
+        }
+        _ => None,
     };
 
-    // Resolve previous_response_id to actual Nostr event ID if provided
-    let reference_conversation_id = if let Some(ref prev_id) = request.previous_response_id {
-        // Strip "resp_" prefix and look up the actual Nostr event ID
-        if prev_id.starts_with("resp_") {
-            let lookup_key = prev_id.clone();
-            // Using tokio::sync::Mutex for async-friendly locking
-            let map = state.response_id_map.lock().await;
-            map.get(&lookup_key).cloned()
-        } else {
-            // Assume it's already a Nostr event ID
-            Some(prev_id.clone())
+    // Attachments are uploaded only once the request is known to be valid
+    let user_content = upload_user_content(&state, user_parts).await?;
+
+    let (thread_id, event_id, known_ids) = match previous_response {
+        Some(previous) => {
+            // Reply in the existing thread (kind:1 with root e-tag), threaded under the last turn
+            let known_ids = thread_message_ids(&state, &previous.thread_id);
+            let event_id = publish_reply(
+                &state,
+                &previous.thread_id,
+                &previous.last_event_id,
+                &project_a_tag,
+                &agent_pubkey,
+                &user_content,
+            )
+            .await?;
+            (previous.thread_id, event_id, known_ids)
+        }
+        None => {
+            // Publish a new thread root (kind:1 with no e-tags) p-tagging the agent
+            let thread_id = create_thread(
+                &state,
+                &project_a_tag,
+                &agent_pubkey,
+                &user_content,
+                request.previous_response_id.clone(),
+            )
+            .await?;
+            (thread_id.clone(), thread_id, HashSet::new())
         }
-    } else {
-        None
     };
 
-    // Create response channel to get the thread ID (which is a valid 64-char hex event ID)
-    // Using std::sync::mpsc::sync_channel to match what NostrCommand::PublishThread expects
-    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
-
-    // Publish a new thread using PublishThread command
-    // This creates a proper kind:1 event with no e-tags (thread root)
-    let publish_result = state.core_handle.send(NostrCommand::PublishThread {
-        project_a_tag: project_a_tag.clone(),
-        title,
-        content: user_content.clone(),
-        agent_pubkey: Some(agent_pubkey.clone()),
-        nudge_ids: Vec::new(),
-        skill_ids: Vec::new(),
-        reference_conversation_id,
-        reference_report_a_tag: None,
-        fork_message_id: None,
-        response_tx: Some(response_tx),
-    });
-
-    if let Err(e) = publish_result {
-        return Err(openai_error_response(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            OpenAIError::server_error(format!("Failed to create thread: {}", e)),
-        ));
-    }
-
-    // Wait for the thread ID (with timeout)
-    // Using tokio::task::spawn_blocking to avoid blocking the async runtime
-    let thread_id = tokio::time::timeout(
-        tokio::time::Duration::from_secs(5),
-        tokio::task::spawn_blocking(move || response_rx.recv()),
-    )
-    .await
-    .map_err(|_| {
-        openai_error_response(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            OpenAIError::timeout("Timeout waiting for thread creation"),
-        )
-    })?
-    .map_err(|_| {
-        openai_error_response(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            OpenAIError::server_error("Thread creation task panicked"),
-        )
-    })?
-    .map_err(|_| {
-        openai_error_response(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            OpenAIError::server_error("Thread creation failed - channel closed"),
-        )
-    })?;
-
-    // Validate thread_id length before slicing
-    if thread_id.len() < 32 {
+    // Validate event_id length before slicing
+    if event_id.len() < 32 {
         return Err(openai_error_response(
             StatusCode::INTERNAL_SERVER_ERROR,
             OpenAIError::server_error(format!(
-                "Invalid thread ID length: expected at least 32 chars, got {}",
-                thread_id.len()
+                "Invalid event ID length: expected at least 32 chars, got {}",
+                event_id.len()
             )),
         ));
     }
 
-    // Generate response ID in OpenAI format (safely handle UTF-8)
-    let response_id = format!("resp_{}", safe_string_prefix(&thread_id, 32));
-
-    // Store the mapping from response_id to actual Nostr event ID
-    {
-        // Using tokio::sync::Mutex for async-friendly locking
-        let mut map = state.response_id_map.lock().await;
-        map.insert(response_id.clone(), thread_id.clone());
-    }
+    // Generate response ID in OpenAI format from this turn's event (safely handle UTF-8)
+    let response_id = format!("resp_{}", safe_string_prefix(&event_id, 32));
 
     // Use Unix timestamp in seconds as integer (not float)
     let created_at = std::time::SystemTime::now()
@@ -448,6 +1118,21 @@ async fn responses_handler(
         .unwrap()
         .as_secs();
 
+    // Persist the mapping from response_id to the thread so chains survive restarts
+    let entry = ResponseChainEntry {
+        thread_id: thread_id.clone(),
+        project_a_tag: project_a_tag.clone(),
+        last_event_id: event_id.clone(),
+        created_at,
+    };
+    // Bind the snapshot first so the chains lock is released before writing
+    let snapshot = state
+        .response_chains
+        .lock()
+        .await
+        .insert(response_id.clone(), entry);
+    persist_response_chains(Some(snapshot)).await;
+
     // If streaming is requested, return SSE stream
     if request.stream {
         // Subscribe to the broadcast channel - each stream gets its own receiver
@@ -455,8 +1140,10 @@ async fn responses_handler(
 
         let stream = create_responses_sse_stream(
             data_rx,
+            state.data_store.clone(),
+            state.response_chains.clone(),
             thread_id,
-            agent_pubkey,
+            known_ids,
             response_id,
             created_at,
             request.previous_response_id,
@@ -466,24 +1153,182 @@ async fn responses_handler(
             .keep_alive(KeepAlive::default())
             .into_response())
     } else {
-        // Non-streaming: collect all chunks and return complete response
-        Err(openai_error_response(
-            StatusCode::NOT_IMPLEMENTED,
-            OpenAIError::bad_request(
-                "Non-streaming mode not yet implemented. Please use stream=true",
-            ),
-        ))
+        // Non-streaming: wait for the agent's final reply and return the complete response
+        let reply = wait_for_final_reply(&state, &thread_id, &known_ids).await?;
+        let snapshot = state
+            .response_chains
+            .lock()
+            .await
+            .set_last_event(&response_id, &reply.id);
+        persist_response_chains(snapshot).await;
+        let (output, usage) = {
+            let store = state.data_store.lock().unwrap();
+            let output = output_items_for_reply(
+                store.get_messages(&thread_id),
+                &reply,
+                &known_ids,
+                &safe_string_prefix(&event_id, 24),
+            );
+            (
+                output,
+                turn_usage(&store, &thread_id, &known_ids, created_at),
+            )
+        };
+
+        let response = ResponsesResponse {
+            id: response_id,
+            created_at,
+            status: "completed".to_string(),
+            model: "tenex".to_string(),
+            object: "response".to_string(),
+            error: None,
+            output,
+            output_text: Some(reply.content.clone()),
+            usage,
+            previous_response_id: request.previous_response_id,
+            metadata: request.metadata,
+        };
+        Ok(Json(response).into_response())
+    }
+}
+
+/// Create a thread title from the message content (first 50 chars, safely handling UTF-8)
+pub(super) fn thread_title(content: &str) -> String {
+    if content.chars().count() > 50 {
+        format!("{}...", safe_string_prefix(content, 50))
+    } else {
+        content.to_string()
+    }
+}
+
+/// Publish a new thread (kind:1 root) p-tagging the agent and wait for its event ID
+pub(super) async fn create_thread(
+    state: &HTTPServerState,
+    project_a_tag: &str,
+    agent_pubkey: &str,
+    content: &str,
+    reference_conversation_id: Option<String>,
+) -> Result<String, ApiError> {
+    // Using std::sync::mpsc::sync_channel to match what NostrCommand::PublishThread expects
+    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+
+    state
+        .core_handle
+        .send(NostrCommand::PublishThread {
+            project_a_tag: project_a_tag.to_string(),
+            title: thread_title(content),
+            content: content.to_string(),
+            agent_pubkey: Some(agent_pubkey.to_string()),
+            nudge_ids: Vec::new(),
+            skill_ids: Vec::new(),
+            reference_conversation_id,
+            reference_report_a_tag: None,
+            fork_message_id: None,
+            response_tx: Some(response_tx),
+        })
+        .map_err(|e| {
+            openai_error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                OpenAIError::server_error(format!("Failed to create thread: {}", e)),
+            )
+        })?;
+
+    await_event_id(response_rx, "thread creation").await
+}
+
+/// Publish a reply into an existing thread p-tagging the agent and wait for its event ID
+pub(super) async fn publish_reply(
+    state: &HTTPServerState,
+    thread_id: &str,
+    reply_to: &str,
+    project_a_tag: &str,
+    agent_pubkey: &str,
+    content: &str,
+) -> Result<String, ApiError> {
+    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+
+    state
+        .core_handle
+        .send(NostrCommand::PublishMessage {
+            thread_id: thread_id.to_string(),
+            project_a_tag: project_a_tag.to_string(),
+            content: content.to_string(),
+            agent_pubkey: Some(agent_pubkey.to_string()),
+            reply_to: Some(reply_to.to_string()),
+            nudge_ids: Vec::new(),
+            skill_ids: Vec::new(),
+            ask_author_pubkey: None,
+            response_tx: Some(response_tx),
+        })
+        .map_err(|e| {
+            openai_error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                OpenAIError::server_error(format!("Failed to send message: {}", e)),
+            )
+        })?;
+
+    await_event_id(response_rx, "message publishing").await
+}
+
+/// Wait for the signed event ID from the worker (with timeout).
+/// Uses tokio::task::spawn_blocking to avoid blocking the async runtime.
+pub(super) async fn await_event_id(
+    response_rx: std::sync::mpsc::Receiver<String>,
+    operation: &str,
+) -> Result<String, ApiError> {
+    tokio::time::timeout(
+        tokio::time::Duration::from_secs(5),
+        tokio::task::spawn_blocking(move || response_rx.recv()),
+    )
+    .await
+    .map_err(|_| {
+        openai_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            OpenAIError::timeout(format!("Timeout waiting for {}", operation)),
+        )
+    })?
+    .map_err(|_| {
+        openai_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            OpenAIError::server_error(format!("Task waiting for {} panicked", operation)),
+        )
+    })?
+    .map_err(|_| {
+        openai_error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            OpenAIError::server_error(format!("{} failed - channel closed", operation)),
+        )
+    })
+}
+
+/// Write changed response chains to disk, logging failures
+async fn persist_response_chains(snapshot: Option<ChainsSnapshot>) {
+    if let Some(snapshot) = snapshot {
+        if let Err(e) = snapshot.persist().await {
+            eprintln!("Failed to persist response chain: {}", e);
+        }
     }
 }
 
-/// Extract the user content from the input field
-fn extract_user_content(input: &ResponseInput) -> Result<String> {
+/// IDs of the messages already present in a thread, so replies to a new
+/// message can be told apart from earlier ones.
+fn thread_message_ids(state: &HTTPServerState, thread_id: &str) -> HashSet<String> {
+    let store = state.data_store.lock().unwrap();
+    store
+        .get_messages(thread_id)
+        .iter()
+        .map(|m| m.id.clone())
+        .collect()
+}
+
+/// Split the last user message into text and attachment parts
+fn extract_user_content(input: &ResponseInput) -> Result<Vec<UserContentPart>> {
     match input {
         ResponseInput::Text(text) => {
             if text.is_empty() {
                 return Err(anyhow::anyhow!("input cannot be empty"));
             }
-            Ok(text.clone())
+            Ok(vec![UserContentPart::Text(text.clone())])
         }
         ResponseInput::Messages(messages) => {
             if messages.is_empty() {
@@ -497,35 +1342,170 @@ fn extract_user_content(input: &ResponseInput) -> Result<String> {
                 .find(|m| m.role == "user")
                 .ok_or_else(|| anyhow::anyhow!("No user message found in input array"))?;
 
-            // Extract text content
             match &user_message.content {
                 InputMessageContent::Text(text) => {
                     if text.is_empty() {
                         return Err(anyhow::anyhow!("user message content cannot be empty"));
                     }
-                    Ok(text.clone())
+                    Ok(vec![UserContentPart::Text(text.clone())])
                 }
                 InputMessageContent::Array(items) => {
-                    // Concatenate all text items
-                    let text: String = items
+                    let parts = items
                         .iter()
-                        .filter_map(|item| match item {
-                            InputContentItem::InputText { text } => Some(text.clone()),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>()
-                        .join("\n");
+                        .filter_map(|item| user_content_part(item).transpose())
+                        .collect::<Result<Vec<_>>>()?;
 
-                    if text.is_empty() {
-                        return Err(anyhow::anyhow!("No text content found in user message"));
+                    if parts.is_empty() {
+                        return Err(anyhow::anyhow!("No content found in user message"));
                     }
-                    Ok(text)
+                    Ok(parts)
                 }
             }
         }
     }
 }
 
+/// A piece of the user's message, in input order
+#[derive(Debug, Clone, PartialEq)]
+enum UserContentPart {
+    Text(String),
+    /// Decoded inline data, uploaded to Blossom as-is
+    Blob {
+        mime_type: String,
+        data: Vec<u8>,
+    },
+    /// Remote file, fetched and re-uploaded to Blossom
+    RemoteUrl(String),
+}
+
+/// Convert one input content item. Empty text yields `None`.
+fn user_content_part(item: &InputContentItem) -> Result<Option<UserContentPart>> {
+    match item {
+        InputContentItem::InputText { text } => {
+            Ok((!text.is_empty()).then(|| UserContentPart::Text(text.clone())))
+        }
+        InputContentItem::InputImage {
+            image_url: Some(url),
+            ..
+        } => attachment_part(url, None).map(Some),
+        InputContentItem::InputFile {
+            file_data: Some(data),
+            filename,
+            ..
+        } => attachment_part(data, filename.as_deref()).map(Some),
+        InputContentItem::InputFile {
+            file_url: Some(url),
+            ..
+        } => attachment_part(url, None).map(Some),
+        InputContentItem::InputImage {
+            file_id: Some(_), ..
+        }
+        | InputContentItem::InputFile {
+            file_id: Some(_), ..
+        } => Err(anyhow::anyhow!(
+            "file_id references are not supported; send the content inline or as a URL"
+        )),
+        InputContentItem::InputImage { .. } => {
+            Err(anyhow::anyhow!("input_image requires image_url"))
+        }
+        InputContentItem::InputFile { .. } => {
+            Err(anyhow::anyhow!("input_file requires file_data or file_url"))
+        }
+    }
+}
+
+/// Parse an attachment given as a data URL, a remote URL, or (for files) bare base64
+fn attachment_part(value: &str, filename: Option<&str>) -> Result<UserContentPart> {
+    let value = value.trim();
+    if value.starts_with("data:") {
+        let (mime_type, data) = crate::nostr::decode_data_url(value)
+            .map_err(|e| anyhow::anyhow!("Invalid data URL: {}", e))?;
+        return Ok(UserContentPart::Blob { mime_type, data });
+    }
+    if value.starts_with("https://") || value.starts_with("http://") {
+        return Ok(UserContentPart::RemoteUrl(value.to_string()));
+    }
+    let Some(filename) = filename else {
+        return Err(anyhow::anyhow!(
+            "Attachments must be a data URL or an http(s) URL"
+        ));
+    };
+
+    let data = crate::nostr::base64_decode(value)
+        .map_err(|e| anyhow::anyhow!("Invalid file_data: {}", e))?;
+    Ok(UserContentPart::Blob {
+        mime_type: mime_type_for_filename(filename).to_string(),
+        data,
+    })
+}
+
+/// MIME type for bare base64 file data, guessed from the filename extension
+fn mime_type_for_filename(filename: &str) -> &'static str {
+    let extension = FsPath::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Upload attachments to Blossom and build the kind:1 content, with each
+/// attachment referenced by its URL in place (as the TUI does for pasted images)
+async fn upload_user_content(
+    state: &HTTPServerState,
+    parts: Vec<UserContentPart>,
+) -> Result<String, ApiError> {
+    let mut content = Vec::with_capacity(parts.len());
+    for part in parts {
+        let url = match part {
+            UserContentPart::Text(text) => {
+                content.push(text);
+                continue;
+            }
+            UserContentPart::Blob { mime_type, data } => {
+                let keys = upload_keys(state)?;
+                crate::nostr::upload_blob(&data, keys, &mime_type).await
+            }
+            UserContentPart::RemoteUrl(url) => {
+                let keys = upload_keys(state)?;
+                crate::nostr::upload_from_url(&url, keys).await
+            }
+        };
+        let url = url.map_err(|e| {
+            openai_error_response(
+                StatusCode::BAD_GATEWAY,
+                OpenAIError::server_error(format!("Attachment upload failed: {}", e)),
+            )
+        })?;
+        content.push(url);
+    }
+    Ok(content.join("\n"))
+}
+
+/// Keys for signing Blossom uploads; attachments need a logged-in daemon
+fn upload_keys(state: &HTTPServerState) -> Result<&Keys, ApiError> {
+    state.keys.as_ref().ok_or_else(|| {
+        openai_error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            OpenAIError::server_error(
+                "Daemon is not logged in; attachments cannot be uploaded".to_string(),
+            ),
+        )
+    })
+}
+
 /// StreamEvent for response.failed
 #[derive(Debug, Clone, Serialize)]
 pub struct ResponseFailedEvent {
@@ -537,116 +1517,1597 @@ pub struct ResponseFailedEvent {
 /// Timeout for waiting for agent response (5 minutes)
 const AGENT_RESPONSE_TIMEOUT_SECS: u64 = 300;
 
-/// Create an SSE stream for the Responses API format
-fn create_responses_sse_stream(
-    mut data_rx: broadcast::Receiver<DataChange>,
-    thread_id: String,
-    _agent_pubkey: String,
-    response_id: String,
-    created_at: u64,
-    previous_response_id: Option<String>,
-    metadata: Option<serde_json::Value>,
-) -> impl Stream<Item = Result<Event, Infallible>> {
-    let stream = async_stream::stream! {
-        // Safely generate msg_id (handle potential short thread_id)
-        let msg_id = format!("msg_{}", safe_string_prefix(&thread_id, 24));
-        let mut accumulated_text = String::new();
-        let mut received_any_chunk = false;
-        let stream_start = tokio::time::Instant::now();
-
-        // Create initial response object
-        let initial_response = ResponsesResponse {
-            id: response_id.clone(),
-            created_at,
-            status: "in_progress".to_string(),
-            model: "tenex".to_string(),
-            object: "response".to_string(),
-            error: None,
-            output: vec![],
-            output_text: None,
-            usage: None,
-            previous_response_id: previous_response_id.clone(),
-            metadata: metadata.clone(),
+/// Poll interval for the data store while waiting for a non-streaming reply
+const AGENT_RESPONSE_POLL_INTERVAL_MS: u64 = 250;
+
+/// Wait for the agent's final kind:1 reply in the thread (non-streaming mode).
+/// Messages in `known_ids` predate the request and are skipped.
+async fn wait_for_final_reply(
+    state: &HTTPServerState,
+    thread_id: &str,
+    known_ids: &HashSet<String>,
+) -> Result<Message, ApiError> {
+    let start = tokio::time::Instant::now();
+    let timeout = tokio::time::Duration::from_secs(AGENT_RESPONSE_TIMEOUT_SECS);
+
+    loop {
+        let reply = {
+            let store = state.data_store.lock().unwrap();
+            find_final_reply(
+                store.get_messages(thread_id),
+                store.user_pubkey.as_deref(),
+                known_ids,
+            )
         };
 
-        // Send response.created event
-        let created_event = StreamEvent::ResponseCreated {
-            response: initial_response.clone(),
-        };
-        if let Ok(json) = serde_json::to_string(&created_event) {
-            yield Ok(Event::default().event("response.created").data(json));
+        if let Some(reply) = reply {
+            return Ok(reply);
+        }
+
+        if start.elapsed() > timeout {
+            return Err(openai_error_response(
+                StatusCode::GATEWAY_TIMEOUT,
+                OpenAIError::timeout("Agent response timeout"),
+            ));
         }
 
-        // Send response.in_progress event
-        let in_progress_event = StreamEvent::ResponseInProgress {
-            response: initial_response.clone(),
+        tokio::time::sleep(tokio::time::Duration::from_millis(
+            AGENT_RESPONSE_POLL_INTERVAL_MS,
+        ))
+        .await;
+    }
+}
+
+/// Find the first final agent reply among a thread's messages.
+fn find_final_reply(
+    messages: &[Message],
+    user_pubkey: Option<&str>,
+    known_ids: &HashSet<String>,
+) -> Option<Message> {
+    let user_pubkey = user_pubkey?;
+    messages
+        .iter()
+        .find(|m| !known_ids.contains(&m.id) && is_final_reply(m, user_pubkey))
+        .cloned()
+}
+
+/// A final reply is an agent kind:1 that p-tags the user and is neither a
+/// reasoning message nor a tool call. Agents only p-tag the user on the
+/// message that completes their turn.
+pub(super) fn is_final_reply(message: &Message, user_pubkey: &str) -> bool {
+    message.pubkey != user_pubkey
+        && !message.is_reasoning
+        && message.tool_name.is_none()
+        && message.p_tags.iter().any(|p| p == user_pubkey)
+}
+
+/// Slack for clock differences between this machine and agent backends when
+/// matching delegated sub-conversation messages to a turn
+const USAGE_CLOCK_SKEW_SECS: u64 = 60;
+
+/// How long a finished stream waits for the final reply (and its llm-* tags)
+/// to reach the store before reporting usage
+const STREAM_USAGE_SETTLE_MS: u64 = 3000;
+
+/// Sum usage over messages' llm-* tags. None if no message reported tokens or cost.
+fn usage_from_messages<'a>(
+    messages: impl IntoIterator<Item = &'a Message>,
+) -> Option<ResponseUsage> {
+    let mut usage = ResponseUsage {
+        input_tokens: 0,
+        output_tokens: 0,
+        total_tokens: 0,
+        tenex_cost_usd: None,
+    };
+    let mut reported = false;
+
+    for message in messages {
+        let token_count = |key: &str| {
+            message
+                .llm_metadata
+                .get(key)
+                .and_then(|v| v.parse::<u32>().ok())
         };
-        if let Ok(json) = serde_json::to_string(&in_progress_event) {
-            yield Ok(Event::default().event("response.in_progress").data(json));
+
+        let input_tokens = token_count("prompt-tokens");
+        let output_tokens = token_count("completion-tokens");
+        if input_tokens.is_some() || output_tokens.is_some() {
+            reported = true;
+            let input_tokens = input_tokens.unwrap_or(0);
+            let output_tokens = output_tokens.unwrap_or(0);
+            let total_tokens =
+                token_count("total-tokens").unwrap_or(input_tokens.saturating_add(output_tokens));
+            usage.input_tokens = usage.input_tokens.saturating_add(input_tokens);
+            usage.output_tokens = usage.output_tokens.saturating_add(output_tokens);
+            usage.total_tokens = usage.total_tokens.saturating_add(total_tokens);
+        }
+
+        if let Some(cost) = message
+            .llm_metadata
+            .get("cost-usd")
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            reported = true;
+            *usage.tenex_cost_usd.get_or_insert(0.0) += cost;
+        }
+    }
+
+    reported.then_some(usage)
+}
+
+/// Usage of one turn: every agent message published in the thread since the
+/// request (messages in `known_ids` predate it), plus messages in
+/// sub-conversations delegated from the thread since `since` (unix seconds).
+fn turn_usage(
+    store: &AppDataStore,
+    thread_id: &str,
+    known_ids: &HashSet<String>,
+    since: u64,
+) -> Option<ResponseUsage> {
+    let user_pubkey = store.user_pubkey.as_deref();
+    let is_agent = |m: &&Message| Some(m.pubkey.as_str()) != user_pubkey;
+
+    let thread_messages = store
+        .get_messages(thread_id)
+        .iter()
+        .filter(is_agent)
+        .filter(|m| !known_ids.contains(&m.id));
+
+    let since = since.saturating_sub(USAGE_CLOCK_SKEW_SECS);
+    let descendants = store.runtime_hierarchy.get_descendants(thread_id);
+    let delegated_messages = descendants
+        .iter()
+        .flat_map(|id| store.get_messages(id))
+        .filter(is_agent)
+        .filter(|m| m.created_at >= since);
+
+    usage_from_messages(thread_messages.chain(delegated_messages))
+}
+
+/// Turn usage for a finished stream, waiting briefly for the final reply to
+/// land in the store so its llm-* tags are counted
+async fn settled_turn_usage(
+    data_store: &Arc<Mutex<AppDataStore>>,
+    thread_id: &str,
+    known_ids: &HashSet<String>,
+    since: u64,
+) -> Option<ResponseUsage> {
+    let deadline =
+        tokio::time::Instant::now() + tokio::time::Duration::from_millis(STREAM_USAGE_SETTLE_MS);
+
+    loop {
+        {
+            let store = data_store.lock().unwrap();
+            let has_reply = find_final_reply(
+                store.get_messages(thread_id),
+                store.user_pubkey.as_deref(),
+                known_ids,
+            )
+            .is_some();
+            if has_reply || tokio::time::Instant::now() >= deadline {
+                return turn_usage(&store, thread_id, known_ids, since);
+            }
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(
+            AGENT_RESPONSE_POLL_INTERVAL_MS,
+        ))
+        .await;
+    }
+}
+
+/// Decides when a streamed turn is over. The finish flag on the thread's
+/// stream is not enough: a PM that delegates finishes an interim reply
+/// ("I'll ask X...") before the delegation answers. The turn ends once the
+/// root agent has finished and the delegation tree has settled, as with
+/// `--until-idle`.
+struct TurnCompletion {
+    thread_id: String,
+    activity: ActivityTracker,
+    /// The root agent's last delta carried the finish flag
+    finished: bool,
+}
+
+impl TurnCompletion {
+    fn new(thread_id: &str) -> Self {
+        Self {
+            thread_id: thread_id.to_string(),
+            // The turn was just published; finishing is what shows work started
+            activity: ActivityTracker::new(&[], std::time::Duration::ZERO),
+            finished: false,
+        }
+    }
+
+    /// Record a delta on the thread's own stream
+    fn delta(&mut self, is_finish: bool) {
+        self.finished = is_finish;
+    }
+
+    /// Track agents starting and stopping anywhere in the delegation tree
+    fn observe(&mut self, status: &Notification) {
+        let in_tree = status.thread_id.as_deref() == Some(self.thread_id.as_str())
+            || status.ancestors.contains(&self.thread_id);
+        if in_tree {
+            self.activity.observe(status);
         }
+    }
+
+    fn is_complete(&self, now: std::time::Instant) -> bool {
+        self.finished && self.activity.is_settled(now)
+    }
+}
+
+/// Operations status notification for a kind:24133 DataChange
+fn operations_status(
+    data_store: &Mutex<AppDataStore>,
+    change: &DataChange,
+) -> Option<Notification> {
+    let DataChange::ProjectStatus { json } = change else {
+        return None;
+    };
+    let event = serde_json::from_str::<serde_json::Value>(json).ok()?;
+    let status = OperationsStatus::from_value(&event)?;
+    Some(operations_status_notification(
+        &data_store.lock().unwrap(),
+        &status,
+    ))
+}
+
+/// Output item currently receiving deltas in a Responses stream
+#[derive(Debug)]
+enum OpenOutputItem {
+    Reasoning {
+        index: usize,
+        id: String,
+        text: String,
+    },
+    Message {
+        index: usize,
+        id: String,
+        text: String,
+    },
+}
+
+/// Builds the Responses output array while streaming. Reasoning deltas, text
+/// deltas and tool calls each get their own output item; switching kinds
+/// closes the item that was open.
+#[derive(Debug)]
+struct ResponsesOutputTracker {
+    /// Suffix shared by all item IDs of this response
+    id_base: String,
+    open: Option<OpenOutputItem>,
+    items: Vec<OutputItem>,
+    output_text: String,
+}
+
+impl ResponsesOutputTracker {
+    fn new(id_base: &str) -> Self {
+        Self {
+            id_base: id_base.to_string(),
+            open: None,
+            items: Vec::new(),
+            output_text: String::new(),
+        }
+    }
+
+    /// Output index the next item will get
+    fn next_index(&self) -> usize {
+        self.items.len() + usize::from(self.open.is_some())
+    }
+
+    fn item_id(&self, prefix: &str, index: usize) -> String {
+        if index == 0 {
+            format!("{}_{}", prefix, self.id_base)
+        } else {
+            format!("{}_{}_{}", prefix, self.id_base, index)
+        }
+    }
+
+    fn reasoning_delta(&mut self, delta: String) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        if !matches!(self.open, Some(OpenOutputItem::Reasoning { .. })) {
+            events.extend(self.close());
+            let index = self.next_index();
+            let id = self.item_id("rs", index);
+            events.push(StreamEvent::OutputItemAdded {
+                output_index: index,
+                item: reasoning_item(&id, None, "in_progress"),
+            });
+            self.open = Some(OpenOutputItem::Reasoning {
+                index,
+                id,
+                text: String::new(),
+            });
+        }
+
+        if let Some(OpenOutputItem::Reasoning { index, id, text }) = &mut self.open {
+            text.push_str(&delta);
+            events.push(StreamEvent::ReasoningTextDelta {
+                item_id: id.clone(),
+                output_index: *index,
+                content_index: 0,
+                delta,
+            });
+        }
+        events
+    }
+
+    fn text_delta(&mut self, delta: String) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        if !matches!(self.open, Some(OpenOutputItem::Message { .. })) {
+            events.extend(self.open_message());
+        }
+
+        self.output_text.push_str(&delta);
+        if let Some(OpenOutputItem::Message { index, text, .. }) = &mut self.open {
+            text.push_str(&delta);
+            events.push(StreamEvent::OutputTextDelta {
+                output_index: *index,
+                content_index: 0,
+                delta,
+            });
+        }
+        events
+    }
+
+    /// Events for a completed tool-call message (arrives whole, not as deltas)
+    fn tool_call(&mut self, message: &Message) -> Vec<StreamEvent> {
+        let Some(call) = function_call_item(message) else {
+            return Vec::new();
+        };
+
+        let mut events = self.close();
+        let index = self.next_index();
+        let mut added = call.clone();
+        added.arguments = String::new();
+        added.status = Some("in_progress".to_string());
+
+        events.push(StreamEvent::OutputItemAdded {
+            output_index: index,
+            item: OutputItem::FunctionCall(added),
+        });
+        events.push(StreamEvent::FunctionCallArgumentsDelta {
+            item_id: call.id.clone(),
+            output_index: index,
+            delta: call.arguments.clone(),
+        });
+        events.push(StreamEvent::FunctionCallArgumentsDone {
+            item_id: call.id.clone(),
+            output_index: index,
+            arguments: call.arguments.clone(),
+        });
+        let item = OutputItem::FunctionCall(call);
+        events.push(StreamEvent::OutputItemDone {
+            output_index: index,
+            item: item.clone(),
+        });
+        self.items.push(item);
+        events
+    }
+
+    /// Close the open item. Guarantees the output contains a message item,
+    /// adding an empty one if the agent produced no text.
+    fn finish(&mut self) -> Vec<StreamEvent> {
+        let mut events = self.close();
+        let has_message = self
+            .items
+            .iter()
+            .any(|item| matches!(item, OutputItem::Message(_)));
+        if !has_message {
+            events.extend(self.open_message());
+            events.extend(self.close());
+        }
+        events
+    }
+
+    fn open_message(&mut self) -> Vec<StreamEvent> {
+        let mut events = self.close();
+        let index = self.next_index();
+        let id = self.item_id("msg", index);
+        events.push(StreamEvent::OutputItemAdded {
+            output_index: index,
+            item: OutputItem::Message(output_message(&id, None, "in_progress")),
+        });
+        events.push(StreamEvent::ContentPartAdded {
+            output_index: index,
+            content_index: 0,
+            part: OutputContentItem::OutputText {
+                text: String::new(),
+                annotations: vec![],
+            },
+        });
+        self.open = Some(OpenOutputItem::Message {
+            index,
+            id,
+            text: String::new(),
+        });
+        events
+    }
+
+    fn close(&mut self) -> Vec<StreamEvent> {
+        match self.open.take() {
+            Some(OpenOutputItem::Reasoning { index, id, text }) => {
+                let item = reasoning_item(&id, Some(&text), "completed");
+                self.items.push(item.clone());
+                vec![
+                    StreamEvent::ReasoningTextDone {
+                        item_id: id,
+                        output_index: index,
+                        content_index: 0,
+                        text,
+                    },
+                    StreamEvent::OutputItemDone {
+                        output_index: index,
+                        item,
+                    },
+                ]
+            }
+            Some(OpenOutputItem::Message { index, id, text }) => {
+                let item = OutputItem::Message(output_message(&id, Some(&text), "completed"));
+                self.items.push(item.clone());
+                vec![
+                    StreamEvent::OutputTextDone {
+                        output_index: index,
+                        content_index: 0,
+                        text,
+                    },
+                    StreamEvent::OutputItemDone {
+                        output_index: index,
+                        item,
+                    },
+                ]
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Assistant message output item (no content while in progress)
+fn output_message(id: &str, text: Option<&str>, status: &str) -> OutputMessage {
+    OutputMessage {
+        id: id.to_string(),
+        message_type: "message".to_string(),
+        role: "assistant".to_string(),
+        status: Some(status.to_string()),
+        content: text
+            .map(|text| {
+                vec![OutputContentItem::OutputText {
+                    text: text.to_string(),
+                    annotations: vec![],
+                }]
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Reasoning output item (no content while in progress)
+fn reasoning_item(id: &str, text: Option<&str>, status: &str) -> OutputItem {
+    OutputItem::Reasoning(ReasoningItem {
+        id: id.to_string(),
+        item_type: "reasoning".to_string(),
+        summary: vec![],
+        content: text
+            .map(|text| {
+                vec![ReasoningContentItem::ReasoningText {
+                    text: text.to_string(),
+                }]
+            })
+            .unwrap_or_default(),
+        status: Some(status.to_string()),
+    })
+}
+
+/// Completed function_call item for a tool-call message
+fn function_call_item(message: &Message) -> Option<FunctionCallItem> {
+    let name = message.tool_name.clone()?;
+    Some(FunctionCallItem {
+        id: format!("fc_{}", safe_string_prefix(&message.id, 24)),
+        item_type: "function_call".to_string(),
+        call_id: format!("call_{}", safe_string_prefix(&message.id, 24)),
+        name,
+        arguments: message
+            .tool_args
+            .clone()
+            .filter(|args| !args.trim().is_empty())
+            .unwrap_or_else(|| "{}".to_string()),
+        status: Some("completed".to_string()),
+    })
+}
+
+/// Output items for a completed turn: the agent's reasoning and tool calls
+/// published since the request, in thread order, followed by the reply.
+fn output_items_for_reply(
+    messages: &[Message],
+    reply: &Message,
+    known_ids: &HashSet<String>,
+    id_base: &str,
+) -> Vec<OutputItem> {
+    let mut items = Vec::new();
+    for message in messages {
+        if known_ids.contains(&message.id)
+            || message.id == reply.id
+            || message.pubkey != reply.pubkey
+            || message.created_at > reply.created_at
+        {
+            continue;
+        }
+        if message.tool_name.is_some() {
+            items.extend(function_call_item(message).map(OutputItem::FunctionCall));
+        } else if message.is_reasoning && !message.content.trim().is_empty() {
+            let id = format!("rs_{}", safe_string_prefix(&message.id, 24));
+            items.push(reasoning_item(&id, Some(&message.content), "completed"));
+        }
+    }
+
+    let msg_id = format!("msg_{}", id_base);
+    items.push(OutputItem::Message(output_message(
+        &msg_id,
+        Some(&reply.content),
+        "completed",
+    )));
+    items
+}
+
+/// New tool-call messages in the thread that the stream has not reported yet
+fn unseen_tool_calls(
+    data_store: &Arc<Mutex<AppDataStore>>,
+    thread_id: &str,
+    seen_ids: &HashSet<String>,
+) -> Vec<Message> {
+    let store = data_store.lock().unwrap();
+    let user_pubkey = store.user_pubkey.clone();
+    store
+        .get_messages(thread_id)
+        .iter()
+        .filter(|m| {
+            m.tool_name.is_some()
+                && Some(&m.pubkey) != user_pubkey.as_ref()
+                && !seen_ids.contains(&m.id)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Create an SSE stream for the Responses API format
+#[allow(clippy::too_many_arguments)]
+fn create_responses_sse_stream(
+    mut data_rx: broadcast::Receiver<DataChange>,
+    data_store: Arc<Mutex<AppDataStore>>,
+    response_chains: Arc<tokio::sync::Mutex<ResponseChainStore>>,
+    thread_id: String,
+    known_ids: HashSet<String>,
+    response_id: String,
+    created_at: u64,
+    previous_response_id: Option<String>,
+    metadata: Option<serde_json::Value>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let stream = async_stream::stream! {
+        // Item IDs share this turn's event prefix (response_id is "resp_" + event ID)
+        let id_base = safe_string_prefix(response_id.trim_start_matches("resp_"), 24);
+        let mut output = ResponsesOutputTracker::new(&id_base);
+        // Tool calls that predate the request are never reported
+        let mut seen_ids = known_ids.clone();
+        let mut last_tool_poll = tokio::time::Instant::now();
+        let mut received_any_chunk = false;
+        let stream_start = tokio::time::Instant::now();
+
+        // Create initial response object
+        let initial_response = ResponsesResponse {
+            id: response_id.clone(),
+            created_at,
+            status: "in_progress".to_string(),
+            model: "tenex".to_string(),
+            object: "response".to_string(),
+            error: None,
+            output: vec![],
+            output_text: None,
+            usage: None,
+            previous_response_id: previous_response_id.clone(),
+            metadata: metadata.clone(),
+        };
+
+        // Send response.created and response.in_progress events
+        for event in [
+            StreamEvent::ResponseCreated {
+                response: initial_response.clone(),
+            },
+            StreamEvent::ResponseInProgress {
+                response: initial_response,
+            },
+        ] {
+            if let Ok(json) = serde_json::to_string(&event) {
+                yield Ok(Event::default().event(event.name()).data(json));
+            }
+        }
+
+        // Poll for DataChange events using async recv with timeout
+        let mut completion = TurnCompletion::new(&thread_id);
+        loop {
+            // Send response.completed once the turn is over
+            if completion.is_complete(std::time::Instant::now()) {
+                for event in output.finish() {
+                    if let Ok(json) = serde_json::to_string(&event) {
+                        yield Ok(Event::default().event(event.name()).data(json));
+                    }
+                }
+                let usage =
+                    settled_turn_usage(&data_store, &thread_id, &known_ids, created_at).await;
+
+                // Chain the next response under the agent's reply, as the non-streaming path does
+                let reply_id = {
+                    let store = data_store.lock().unwrap();
+                    find_final_reply(
+                        store.get_messages(&thread_id),
+                        store.user_pubkey.as_deref(),
+                        &known_ids,
+                    )
+                    .map(|reply| reply.id)
+                };
+                if let Some(reply_id) = reply_id {
+                    let snapshot =
+                        response_chains.lock().await.set_last_event(&response_id, &reply_id);
+                    persist_response_chains(snapshot).await;
+                }
+                let completed_response = ResponsesResponse {
+                    id: response_id.clone(),
+                    created_at,
+                    status: "completed".to_string(),
+                    model: "tenex".to_string(),
+                    object: "response".to_string(),
+                    error: None,
+                    output: output.items.clone(),
+                    output_text: Some(output.output_text.clone()),
+                    usage,
+                    previous_response_id: previous_response_id.clone(),
+                    metadata: metadata.clone(),
+                };
+                let completed_event = StreamEvent::ResponseCompleted {
+                    response: completed_response,
+                };
+                if let Ok(json) = serde_json::to_string(&completed_event) {
+                    yield Ok(Event::default().event(completed_event.name()).data(json));
+                }
+
+                break;
+            }
+
+            // Check for overall timeout
+            if stream_start.elapsed() > tokio::time::Duration::from_secs(AGENT_RESPONSE_TIMEOUT_SECS) {
+                // Emit response.failed event
+                let failed_response = ResponsesResponse {
+                    id: response_id.clone(),
+                    created_at,
+                    status: "failed".to_string(),
+                    model: "tenex".to_string(),
+                    object: "response".to_string(),
+                    error: Some(serde_json::json!({
+                        "message": "Agent response timeout",
+                        "type": "timeout_error"
+                    })),
+                    output: vec![],
+                    output_text: None,
+                    usage: None,
+                    previous_response_id: previous_response_id.clone(),
+                    metadata: metadata.clone(),
+                };
+                let failed_event = ResponseFailedEvent {
+                    event_type: "response.failed".to_string(),
+                    response: failed_response,
+                };
+                if let Ok(json) = serde_json::to_string(&failed_event) {
+                    yield Ok(Event::default().event("response.failed").data(json));
+                }
+                break;
+            }
+
+            // Tool calls arrive as complete kind:1 messages in the store, not as deltas
+            if last_tool_poll.elapsed()
+                >= tokio::time::Duration::from_millis(AGENT_RESPONSE_POLL_INTERVAL_MS)
+            {
+                last_tool_poll = tokio::time::Instant::now();
+                for message in unseen_tool_calls(&data_store, &thread_id, &seen_ids) {
+                    seen_ids.insert(message.id.clone());
+                    for event in output.tool_call(&message) {
+                        if let Ok(json) = serde_json::to_string(&event) {
+                            yield Ok(Event::default().event(event.name()).data(json));
+                        }
+                    }
+                }
+            }
+
+            // Use async timeout for receiving
+            let recv_result = tokio::time::timeout(
+                tokio::time::Duration::from_millis(100),
+                data_rx.recv(),
+            )
+            .await;
+
+            match recv_result {
+                Ok(Ok(DataChange::StreamTextDelta {
+                    agent_pubkey: _chunk_agent,
+                    conversation_id,
+                    text_delta,
+                    reasoning_delta,
+                    is_finish,
+                })) => {
+                    // Only process chunks for our thread (conversation)
+                    if conversation_id != thread_id {
+                        continue;
+                    }
+
+                    received_any_chunk = true;
+                    completion.delta(is_finish);
+
+                    let mut events = Vec::new();
+                    if let Some(reasoning) = reasoning_delta {
+                        events.extend(output.reasoning_delta(reasoning));
+                    }
+                    if let Some(text) = text_delta {
+                        events.extend(output.text_delta(text));
+                    }
+                    // An interim reply closes its item; a later answer opens a new one
+                    if is_finish {
+                        events.extend(output.close());
+                    }
+                    for event in events {
+                        if let Ok(json) = serde_json::to_string(&event) {
+                            yield Ok(Event::default().event(event.name()).data(json));
+                        }
+                    }
+                }
+                Ok(Ok(change)) => {
+                    if let Some(status) = operations_status(&data_store, &change) {
+                        completion.observe(&status);
+                    }
+                }
+                Ok(Err(broadcast::error::RecvError::Lagged(n))) => {
+                    // We missed some messages due to slow processing
+                    // Log this but continue - the stream should still work
+                    eprintln!("SSE stream lagged, missed {} messages", n);
+                    continue;
+                }
+                Ok(Err(broadcast::error::RecvError::Closed)) => {
+                    // Channel closed, end stream with failure if we haven't completed
+                    if !received_any_chunk {
+                        let failed_response = ResponsesResponse {
+                            id: response_id.clone(),
+                            created_at,
+                            status: "failed".to_string(),
+                            model: "tenex".to_string(),
+                            object: "response".to_string(),
+                            error: Some(serde_json::json!({
+                                "message": "Data channel closed unexpectedly",
+                                "type": "server_error"
+                            })),
+                            output: vec![],
+                            output_text: None,
+                            usage: None,
+                            previous_response_id: previous_response_id.clone(),
+                            metadata: metadata.clone(),
+                        };
+                        let failed_event = ResponseFailedEvent {
+                            event_type: "response.failed".to_string(),
+                            response: failed_response,
+                        };
+                        if let Ok(json) = serde_json::to_string(&failed_event) {
+                            yield Ok(Event::default().event("response.failed").data(json));
+                        }
+                    }
+                    break;
+                }
+                Err(_) => {
+                    // Timeout - continue polling
+                    continue;
+                }
+            }
+        }
+    };
+
+    stream
+}
+
+/// Model name reported when the request targets the PM/default agent
+const DEFAULT_MODEL_NAME: &str = "tenex";
+
+/// Handler for POST /:project_dtag/chat/completions
+async fn chat_completions_handler(
+    Path(project_dtag): Path<String>,
+    State(state): State<HTTPServerState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatCompletionRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let token = authorize_request(&state, &headers, &project_dtag)?;
+
+    for msg in &request.messages {
+        if !["system", "developer", "user", "assistant"].contains(&msg.role.as_str()) {
+            return Err(openai_error_response(
+                StatusCode::BAD_REQUEST,
+                OpenAIError::bad_request(format!(
+                    "Invalid message role '{}'. Must be one of: system, developer, user, assistant",
+                    msg.role
+                )),
+            ));
+        }
+    }
+
+    // The last message is the new user turn; everything before it is history
+    let Some((last, earlier)) = request.messages.split_last() else {
+        return Err(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("messages array cannot be empty"),
+        ));
+    };
+    if last.role != "user" {
+        return Err(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("The last message must have role 'user'"),
+        ));
+    }
+    let user_content = last.text();
+    if user_content.trim().is_empty() {
+        return Err(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("No text content found in the last user message"),
+        ));
+    }
+
+    let project = resolve_project(&state, &project_dtag).await.map_err(|e| {
+        openai_error_response(
+            StatusCode::NOT_FOUND,
+            OpenAIError::not_found(format!("Project not found: {}", e)),
+        )
+    })?;
+    let project_a_tag = project.a_tag();
+    let (agent_pubkey, model) = resolve_chat_agent(&state, &project, request.model.as_deref())?;
+    authorize_agent(&state, &project, token.as_ref(), &agent_pubkey)?;
+
+    let history_turns: Vec<(String, String)> =
+        earlier.iter().map(|m| (m.role.clone(), m.text())).collect();
+    let scope = TranscriptScope {
+        project_a_tag,
+        agent_pubkey,
+        token_id: token.map(|token| token.id),
+    };
+    let (thread_id, turn_event_id, known_ids) =
+        start_or_continue_transcript(&state, &scope, &history_turns, &user_content).await?;
+
+    let completion_id = format!("chatcmpl-{}", safe_string_prefix(&turn_event_id, 32));
+    let created = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut request_turns = history_turns;
+    request_turns.push(("user".to_string(), user_content));
+
+    if request.stream {
+        let data_rx = state.data_tx.subscribe();
+        let include_usage = request
+            .stream_options
+            .as_ref()
+            .is_some_and(|options| options.include_usage);
+        let turn = StreamedChatTurn {
+            data_store: state.data_store.clone(),
+            chat_thread_map: state.chat_thread_map.clone(),
+            scope,
+            thread_id,
+            known_ids,
+            started_at: created,
+            request_turns,
+        };
+        let stream =
+            create_chat_completion_sse_stream(data_rx, turn, completion_id, model, include_usage);
+        Ok(Sse::new(stream)
+            .keep_alive(KeepAlive::default())
+            .into_response())
+    } else {
+        let reply = wait_for_final_reply(&state, &thread_id, &known_ids).await?;
+        let usage = {
+            let store = state.data_store.lock().unwrap();
+            turn_usage(&store, &thread_id, &known_ids, created)
+        };
+
+        request_turns.push(("assistant".to_string(), reply.content.clone()));
+        state
+            .chat_thread_map
+            .lock()
+            .await
+            .insert(scope.key(&request_turns), thread_id);
+
+        let response = ChatCompletionResponse {
+            id: completion_id,
+            object: "chat.completion".to_string(),
+            created,
+            model,
+            choices: vec![ChatChoice {
+                index: 0,
+                message: ChatResponseMessage {
+                    role: "assistant".to_string(),
+                    content: reply.content.clone(),
+                },
+                finish_reason: "stop".to_string(),
+            }],
+            usage: usage.map(ChatUsage::from),
+        };
+        Ok(Json(response).into_response())
+    }
+}
+
+/// Publish the new user turn of a stateless chat transcript. A transcript we
+/// answered before maps to its thread and is continued with a reply; anything
+/// else starts a new thread carrying the history as context.
+/// Returns the thread ID, the event ID of the published turn and the message
+/// IDs that predate this turn.
+async fn start_or_continue_transcript(
+    state: &HTTPServerState,
+    scope: &TranscriptScope,
+    history: &[(String, String)],
+    user_content: &str,
+) -> Result<(String, String, HashSet<String>), ApiError> {
+    let existing_thread = state
+        .chat_thread_map
+        .lock()
+        .await
+        .get(&scope.key(history))
+        .filter(|thread_id| {
+            // Never continue a thread that moved out of the request's project
+            let store = state.data_store.lock().unwrap();
+            store.get_project_a_tag_for_thread(thread_id).as_deref()
+                == Some(scope.project_a_tag.as_str())
+        });
+
+    match existing_thread {
+        Some(thread_id) => {
+            let known_ids = thread_message_ids(state, &thread_id);
+            let event_id = publish_reply(
+                state,
+                &thread_id,
+                &thread_id,
+                &scope.project_a_tag,
+                &scope.agent_pubkey,
+                user_content,
+            )
+            .await?;
+            Ok((thread_id, event_id, known_ids))
+        }
+        None => {
+            let content = compose_thread_content(history, user_content);
+            let thread_id = create_thread(
+                state,
+                &scope.project_a_tag,
+                &scope.agent_pubkey,
+                &content,
+                None,
+            )
+            .await?;
+            Ok((thread_id.clone(), thread_id, HashSet::new()))
+        }
+    }
+}
+
+/// Resolve the `model` field to a recipient agent: an agent slug within the
+/// project, or the PM/default agent when omitted or "tenex".
+/// Returns the agent pubkey and the model name to report back.
+fn resolve_chat_agent(
+    state: &HTTPServerState,
+    project: &Project,
+    model: Option<&str>,
+) -> Result<(String, String), ApiError> {
+    match model.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(slug) if slug != DEFAULT_MODEL_NAME => {
+            let store = state.data_store.lock().unwrap();
+            let agent = find_roster_agent(&store, project, slug).ok_or_else(|| {
+                let mut error = OpenAIError::not_found(format!(
+                    "Agent '{}' not found in project '{}'",
+                    slug, project.id
+                ));
+                error.error.code = Some("model_not_found".to_string());
+                openai_error_response(StatusCode::NOT_FOUND, error)
+            })?;
+            Ok((agent.pubkey, agent.name))
+        }
+        _ => {
+            // First 31933 p-tag is the PM/default agent.
+            let agent_pubkey = default_agent_pubkey(project).ok_or_else(|| {
+                openai_error_response(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    OpenAIError::server_error("Project has no roster agents".to_string()),
+                )
+            })?;
+            Ok((agent_pubkey, DEFAULT_MODEL_NAME.to_string()))
+        }
+    }
+}
+
+/// Chat transcripts remembered for continuation; the least recently used are
+/// dropped beyond this many
+const MAX_CHAT_TRANSCRIPTS: usize = 10_000;
+
+/// Who a chat transcript was exchanged with: identical transcripts sent to
+/// another project or agent, or with another API token, never share a thread.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TranscriptScope {
+    project_a_tag: String,
+    agent_pubkey: String,
+    /// ID of the request's API token (`None` while auth is disabled)
+    token_id: Option<String>,
+}
+
+impl TranscriptScope {
+    /// Key of a transcript (role/text pairs in order) within this scope
+    fn key(&self, turns: &[(String, String)]) -> TranscriptKey {
+        TranscriptKey {
+            scope: self.clone(),
+            digest: transcript_digest(turns),
+        }
+    }
+}
+
+/// Identifies a chat transcript we answered, to recognize a follow-up
+/// request that replays it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TranscriptKey {
+    scope: TranscriptScope,
+    digest: [u8; 32],
+}
+
+/// SHA-256 over the role and trimmed text of each turn, length-prefixed so
+/// that turn boundaries are unambiguous
+fn transcript_digest(turns: &[(String, String)]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for (role, text) in turns {
+        for part in [role.as_str(), text.trim()] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// Threads of the chat transcripts we answered, bounded to the most
+/// recently used
+#[derive(Debug)]
+pub struct ChatThreadMap {
+    /// Thread ID and last use of each transcript
+    entries: HashMap<TranscriptKey, (String, u64)>,
+    capacity: usize,
+    clock: u64,
+}
+
+impl ChatThreadMap {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, key: &TranscriptKey) -> Option<String> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|(thread_id, used)| {
+            *used = clock;
+            thread_id.clone()
+        })
+    }
+
+    fn insert(&mut self, key: TranscriptKey, thread_id: String) {
+        self.clock += 1;
+        self.entries.insert(key, (thread_id, self.clock));
+        if self.entries.len() > self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Build the root content for a new thread. System prompts and earlier turns
+/// the agent has not seen are included as context ahead of the new message.
+fn compose_thread_content(history: &[(String, String)], message: &str) -> String {
+    let system: Vec<&str> = history
+        .iter()
+        .filter(|(role, _)| role == "system" || role == "developer")
+        .map(|(_, text)| text.as_str())
+        .filter(|text| !text.trim().is_empty())
+        .collect();
+    let turns: Vec<String> = history
+        .iter()
+        .filter(|(role, _)| role == "user" || role == "assistant")
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(role, text)| format!("{}: {}", role, text))
+        .collect();
+
+    if system.is_empty() && turns.is_empty() {
+        return message.to_string();
+    }
+
+    let mut content = String::new();
+    if !system.is_empty() {
+        content.push_str("System instructions:\n");
+        content.push_str(&system.join("\n\n"));
+        content.push_str("\n\n");
+    }
+    if !turns.is_empty() {
+        content.push_str("Conversation so far:\n");
+        content.push_str(&turns.join("\n"));
+        content.push_str("\n\n");
+    }
+    content.push_str(message);
+    content
+}
+
+/// A published chat transcript turn whose reply is being streamed
+struct StreamedChatTurn {
+    data_store: Arc<Mutex<AppDataStore>>,
+    chat_thread_map: Arc<tokio::sync::Mutex<ChatThreadMap>>,
+    scope: TranscriptScope,
+    thread_id: String,
+    /// Messages that predate the turn
+    known_ids: HashSet<String>,
+    /// Unix timestamp (seconds) of the request
+    started_at: u64,
+    /// Transcript up to and including the new user turn
+    request_turns: Vec<(String, String)>,
+}
+
+impl StreamedChatTurn {
+    /// Usage of the turn, once the final reply has reached the store
+    async fn usage(&self) -> Option<ResponseUsage> {
+        settled_turn_usage(
+            &self.data_store,
+            &self.thread_id,
+            &self.known_ids,
+            self.started_at,
+        )
+        .await
+    }
+
+    /// Remember the transcript so the client's next turn continues this thread
+    async fn remember_reply(&mut self, reply: String) {
+        self.request_turns.push(("assistant".to_string(), reply));
+        self.chat_thread_map
+            .lock()
+            .await
+            .insert(self.scope.key(&self.request_turns), self.thread_id.clone());
+    }
+}
+
+/// Create an SSE stream in the chat.completion.chunk format
+fn create_chat_completion_sse_stream(
+    mut data_rx: broadcast::Receiver<DataChange>,
+    mut turn: StreamedChatTurn,
+    completion_id: String,
+    model: String,
+    include_usage: bool,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    async_stream::stream! {
+        let created = turn.started_at;
+        let mut accumulated_text = String::new();
+        let stream_start = tokio::time::Instant::now();
+
+        // First chunk announces the assistant role
+        let role_chunk = ChatCompletionChunk::new(
+            &completion_id,
+            created,
+            &model,
+            ChatDelta {
+                role: Some("assistant".to_string()),
+                content: Some(String::new()),
+            },
+            None,
+        );
+        if let Ok(json) = serde_json::to_string(&role_chunk) {
+            yield Ok(Event::default().data(json));
+        }
+
+        let mut completion = TurnCompletion::new(&turn.thread_id);
+        loop {
+            if completion.is_complete(std::time::Instant::now()) {
+                let stop_chunk = ChatCompletionChunk::new(
+                    &completion_id,
+                    created,
+                    &model,
+                    ChatDelta::default(),
+                    Some("stop"),
+                );
+                if let Ok(json) = serde_json::to_string(&stop_chunk) {
+                    yield Ok(Event::default().data(json));
+                }
+
+                if include_usage {
+                    if let Some(usage) = turn.usage().await {
+                        let usage_chunk = ChatCompletionChunk::usage(
+                            &completion_id,
+                            created,
+                            &model,
+                            ChatUsage::from(usage),
+                        );
+                        if let Ok(json) = serde_json::to_string(&usage_chunk) {
+                            yield Ok(Event::default().data(json));
+                        }
+                    }
+                }
+
+                turn.remember_reply(accumulated_text.clone()).await;
+                break;
+            }
+
+            if stream_start.elapsed() > tokio::time::Duration::from_secs(AGENT_RESPONSE_TIMEOUT_SECS) {
+                let error = OpenAIError::timeout("Agent response timeout");
+                if let Ok(json) = serde_json::to_string(&error) {
+                    yield Ok(Event::default().data(json));
+                }
+                break;
+            }
+
+            let recv_result = tokio::time::timeout(
+                tokio::time::Duration::from_millis(100),
+                data_rx.recv(),
+            )
+            .await;
+
+            match recv_result {
+                Ok(Ok(DataChange::StreamTextDelta {
+                    conversation_id,
+                    text_delta,
+                    is_finish,
+                    ..
+                })) => {
+                    if conversation_id != turn.thread_id {
+                        continue;
+                    }
+                    completion.delta(is_finish);
+
+                    if let Some(text) = text_delta {
+                        accumulated_text.push_str(&text);
+                        let chunk = ChatCompletionChunk::new(
+                            &completion_id,
+                            created,
+                            &model,
+                            ChatDelta {
+                                role: None,
+                                content: Some(text),
+                            },
+                            None,
+                        );
+                        if let Ok(json) = serde_json::to_string(&chunk) {
+                            yield Ok(Event::default().data(json));
+                        }
+                    }
+                }
+                Ok(Ok(change)) => {
+                    if let Some(status) = operations_status(&turn.data_store, &change) {
+                        completion.observe(&status);
+                    }
+                }
+                Ok(Err(broadcast::error::RecvError::Lagged(n))) => {
+                    eprintln!("SSE stream lagged, missed {} messages", n);
+                    continue;
+                }
+                Ok(Err(broadcast::error::RecvError::Closed)) => {
+                    let error = OpenAIError::server_error("Data channel closed unexpectedly");
+                    if let Ok(json) = serde_json::to_string(&error) {
+                        yield Ok(Event::default().data(json));
+                    }
+                    break;
+                }
+                Err(_) => continue,
+            }
+        }
+
+        yield Ok(Event::default().data("[DONE]"));
+    }
+}
+
+/// Error half of Messages API handler results: HTTP status plus Anthropic-style error body
+type AnthropicApiError = (StatusCode, axum::Json<AnthropicError>);
+
+/// Convert an OpenAI-style handler error into the Anthropic error shape
+fn anthropic_error((status, axum::Json(error)): ApiError) -> AnthropicApiError {
+    (status, axum::Json(AnthropicError::from(error)))
+}
+
+/// Handler for POST /:project_dtag/v1/messages
+async fn messages_handler(
+    Path(project_dtag): Path<String>,
+    State(state): State<HTTPServerState>,
+    headers: HeaderMap,
+    Json(request): Json<MessagesRequest>,
+) -> Result<impl IntoResponse, AnthropicApiError> {
+    let token = authorize_request(&state, &headers, &project_dtag).map_err(anthropic_error)?;
+
+    for msg in &request.messages {
+        if !["user", "assistant"].contains(&msg.role.as_str()) {
+            return Err(anthropic_error(openai_error_response(
+                StatusCode::BAD_REQUEST,
+                OpenAIError::bad_request(format!(
+                    "Invalid message role '{}'. Must be one of: user, assistant",
+                    msg.role
+                )),
+            )));
+        }
+    }
+
+    // The last message is the new user turn; everything before it is history
+    let Some((last, earlier)) = request.messages.split_last() else {
+        return Err(anthropic_error(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("messages array cannot be empty"),
+        )));
+    };
+    if last.role != "user" {
+        return Err(anthropic_error(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("The last message must have role 'user'"),
+        )));
+    }
+    let user_content = last.content.text();
+    if user_content.trim().is_empty() {
+        return Err(anthropic_error(openai_error_response(
+            StatusCode::BAD_REQUEST,
+            OpenAIError::bad_request("No text content found in the last user message"),
+        )));
+    }
+
+    let project = resolve_project(&state, &project_dtag).await.map_err(|e| {
+        anthropic_error(openai_error_response(
+            StatusCode::NOT_FOUND,
+            OpenAIError::not_found(format!("Project not found: {}", e)),
+        ))
+    })?;
+    let project_a_tag = project.a_tag();
+    let (agent_pubkey, model) =
+        resolve_chat_agent(&state, &project, request.model.as_deref()).map_err(anthropic_error)?;
+    authorize_agent(&state, &project, token.as_ref(), &agent_pubkey).map_err(anthropic_error)?;
+
+    // The top-level system prompt becomes a leading system turn so it is both
+    // part of the transcript fingerprint and carried into new threads
+    let mut history_turns: Vec<(String, String)> = Vec::new();
+    if let Some(system) = request.system.as_ref().map(AnthropicContent::text) {
+        if !system.trim().is_empty() {
+            history_turns.push(("system".to_string(), system));
+        }
+    }
+    history_turns.extend(earlier.iter().map(|m| (m.role.clone(), m.content.text())));
+
+    let scope = TranscriptScope {
+        project_a_tag,
+        agent_pubkey,
+        token_id: token.map(|token| token.id),
+    };
+    let (thread_id, turn_event_id, known_ids) =
+        start_or_continue_transcript(&state, &scope, &history_turns, &user_content)
+            .await
+            .map_err(anthropic_error)?;
+
+    let message_id = format!("msg_{}", safe_string_prefix(&turn_event_id, 24));
+    let include_thinking = request.thinking_enabled();
+    let started_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let mut request_turns = history_turns;
+    request_turns.push(("user".to_string(), user_content));
+
+    if request.stream {
+        let data_rx = state.data_tx.subscribe();
+        let turn = StreamedChatTurn {
+            data_store: state.data_store.clone(),
+            chat_thread_map: state.chat_thread_map.clone(),
+            scope,
+            thread_id,
+            known_ids,
+            started_at,
+            request_turns,
+        };
+        let stream = create_messages_sse_stream(data_rx, turn, message_id, model, include_thinking);
+        Ok(Sse::new(stream)
+            .keep_alive(KeepAlive::default())
+            .into_response())
+    } else {
+        let reply = wait_for_final_reply(&state, &thread_id, &known_ids)
+            .await
+            .map_err(anthropic_error)?;
+
+        request_turns.push(("assistant".to_string(), reply.content.clone()));
+        let (thinking, usage) = {
+            let store = state.data_store.lock().unwrap();
+            let thinking = if include_thinking {
+                reasoning_for_reply(store.get_messages(&thread_id), &reply, &known_ids)
+            } else {
+                Vec::new()
+            };
+            (
+                thinking,
+                turn_usage(&store, &thread_id, &known_ids, started_at),
+            )
+        };
+        state
+            .chat_thread_map
+            .lock()
+            .await
+            .insert(scope.key(&request_turns), thread_id);
+
+        let mut content: Vec<AnthropicResponseBlock> = thinking
+            .into_iter()
+            .map(|thinking| AnthropicResponseBlock::Thinking {
+                thinking,
+                signature: String::new(),
+            })
+            .collect();
+        content.push(AnthropicResponseBlock::Text {
+            text: reply.content.clone(),
+        });
+
+        let usage = usage.map(AnthropicUsage::from).unwrap_or_default();
+
+        let response = MessagesResponse {
+            id: message_id,
+            message_type: "message".to_string(),
+            role: "assistant".to_string(),
+            model,
+            content,
+            stop_reason: Some("end_turn".to_string()),
+            stop_sequence: None,
+            usage,
+        };
+        Ok(Json(response).into_response())
+    }
+}
+
+/// Reasoning published by the replying agent during this turn, in thread order
+fn reasoning_for_reply(
+    messages: &[Message],
+    reply: &Message,
+    known_ids: &HashSet<String>,
+) -> Vec<String> {
+    messages
+        .iter()
+        .filter(|m| {
+            m.is_reasoning
+                && m.pubkey == reply.pubkey
+                && m.created_at <= reply.created_at
+                && !known_ids.contains(&m.id)
+                && !m.content.trim().is_empty()
+        })
+        .map(|m| m.content.clone())
+        .collect()
+}
+
+/// Kind of the content block currently open in a Messages API stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnthropicBlockKind {
+    Text,
+    Thinking,
+}
+
+/// Tracks content block indices while streaming, opening a new block whenever
+/// the agent switches between reasoning and text deltas.
+#[derive(Debug, Default)]
+struct AnthropicBlockTracker {
+    index: usize,
+    open: Option<AnthropicBlockKind>,
+}
 
-        // Send output_item.added for the assistant message
-        let output_item = OutputMessage {
-            id: msg_id.clone(),
-            message_type: "message".to_string(),
-            role: "assistant".to_string(),
-            status: Some("in_progress".to_string()),
-            content: vec![],
-        };
-        let item_added_event = StreamEvent::OutputItemAdded {
-            output_index: 0,
-            item: output_item.clone(),
-        };
-        if let Ok(json) = serde_json::to_string(&item_added_event) {
-            yield Ok(Event::default().event("response.output_item.added").data(json));
+impl AnthropicBlockTracker {
+    /// Events for one delta, including any block stop/start it implies
+    fn delta(&mut self, kind: AnthropicBlockKind, text: String) -> Vec<AnthropicStreamEvent> {
+        let mut events = Vec::new();
+        if self.open != Some(kind) {
+            events.extend(self.close());
+            let content_block = match kind {
+                AnthropicBlockKind::Text => AnthropicResponseBlock::Text {
+                    text: String::new(),
+                },
+                AnthropicBlockKind::Thinking => AnthropicResponseBlock::Thinking {
+                    thinking: String::new(),
+                    signature: String::new(),
+                },
+            };
+            events.push(AnthropicStreamEvent::ContentBlockStart {
+                index: self.index,
+                content_block,
+            });
+            self.open = Some(kind);
         }
 
-        // Send content_part.added for the text content
-        let content_part = OutputContentItem::OutputText {
-            text: String::new(),
-            annotations: vec![],
+        let delta = match kind {
+            AnthropicBlockKind::Text => AnthropicBlockDelta::TextDelta { text },
+            AnthropicBlockKind::Thinking => AnthropicBlockDelta::ThinkingDelta { thinking: text },
         };
-        let part_added_event = StreamEvent::ContentPartAdded {
-            output_index: 0,
-            content_index: 0,
-            part: content_part,
+        events.push(AnthropicStreamEvent::ContentBlockDelta {
+            index: self.index,
+            delta,
+        });
+        events
+    }
+
+    /// Stop event for the open block, if any
+    fn close(&mut self) -> Option<AnthropicStreamEvent> {
+        self.open.take()?;
+        let event = AnthropicStreamEvent::ContentBlockStop { index: self.index };
+        self.index += 1;
+        Some(event)
+    }
+}
+
+/// Encode a Messages API stream event as a named SSE event
+fn anthropic_sse_event(event: &AnthropicStreamEvent) -> Option<Event> {
+    serde_json::to_string(event)
+        .ok()
+        .map(|json| Event::default().event(event.name()).data(json))
+}
+
+/// Create an SSE stream in the Anthropic Messages streaming format
+fn create_messages_sse_stream(
+    mut data_rx: broadcast::Receiver<DataChange>,
+    mut turn: StreamedChatTurn,
+    message_id: String,
+    model: String,
+    include_thinking: bool,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    async_stream::stream! {
+        let mut accumulated_text = String::new();
+        let mut blocks = AnthropicBlockTracker::default();
+        let stream_start = tokio::time::Instant::now();
+
+        let start = AnthropicStreamEvent::MessageStart {
+            message: MessagesResponse {
+                id: message_id.clone(),
+                message_type: "message".to_string(),
+                role: "assistant".to_string(),
+                model: model.clone(),
+                content: Vec::new(),
+                stop_reason: None,
+                stop_sequence: None,
+                usage: AnthropicUsage::default(),
+            },
         };
-        if let Ok(json) = serde_json::to_string(&part_added_event) {
-            yield Ok(Event::default().event("response.content_part.added").data(json));
+        if let Some(event) = anthropic_sse_event(&start) {
+            yield Ok(event);
         }
 
-        // Poll for DataChange events using async recv with timeout
+        let mut completion = TurnCompletion::new(&turn.thread_id);
         loop {
-            // Check for overall timeout
+            if completion.is_complete(std::time::Instant::now()) {
+                let mut closing: Vec<AnthropicStreamEvent> = blocks.close().into_iter().collect();
+                let usage = turn.usage().await.map(AnthropicUsage::from).unwrap_or_default();
+                closing.push(AnthropicStreamEvent::MessageDelta {
+                    delta: AnthropicMessageDelta {
+                        stop_reason: Some("end_turn".to_string()),
+                        stop_sequence: None,
+                    },
+                    usage,
+                });
+                closing.push(AnthropicStreamEvent::MessageStop);
+                for event in &closing {
+                    if let Some(event) = anthropic_sse_event(event) {
+                        yield Ok(event);
+                    }
+                }
+
+                turn.remember_reply(accumulated_text.clone()).await;
+                break;
+            }
+
             if stream_start.elapsed() > tokio::time::Duration::from_secs(AGENT_RESPONSE_TIMEOUT_SECS) {
-                // Emit response.failed event
-                let failed_response = ResponsesResponse {
-                    id: response_id.clone(),
-                    created_at,
-                    status: "failed".to_string(),
-                    model: "tenex".to_string(),
-                    object: "response".to_string(),
-                    error: Some(serde_json::json!({
-                        "message": "Agent response timeout",
-                        "type": "timeout_error"
-                    })),
-                    output: vec![],
-                    output_text: None,
-                    usage: None,
-                    previous_response_id: previous_response_id.clone(),
-                    metadata: metadata.clone(),
-                };
-                let failed_event = ResponseFailedEvent {
-                    event_type: "response.failed".to_string(),
-                    response: failed_response,
-                };
-                if let Ok(json) = serde_json::to_string(&failed_event) {
-                    yield Ok(Event::default().event("response.failed").data(json));
+                let error = AnthropicError::from(OpenAIError::timeout("Agent response timeout"));
+                if let Some(event) = anthropic_sse_event(&AnthropicStreamEvent::Error { error: error.error }) {
+                    yield Ok(event);
                 }
                 break;
             }
 
-            // Use async timeout for receiving
             let recv_result = tokio::time::timeout(
                 tokio::time::Duration::from_millis(100),
                 data_rx.recv(),
@@ -655,143 +3116,66 @@ fn create_responses_sse_stream(
 
             match recv_result {
                 Ok(Ok(DataChange::StreamTextDelta {
-                    agent_pubkey: _chunk_agent,
                     conversation_id,
                     text_delta,
-                    reasoning_delta: _,
+                    reasoning_delta,
                     is_finish,
+                    ..
                 })) => {
-                    // Only process chunks for our thread (conversation)
-                    if conversation_id != thread_id {
+                    if conversation_id != turn.thread_id {
                         continue;
                     }
+                    completion.delta(is_finish);
 
-                    received_any_chunk = true;
-
-                    // Send text delta if present
+                    let mut events = Vec::new();
+                    if let Some(reasoning) = reasoning_delta.filter(|_| include_thinking) {
+                        events.extend(blocks.delta(AnthropicBlockKind::Thinking, reasoning));
+                    }
                     if let Some(text) = text_delta {
                         accumulated_text.push_str(&text);
-
-                        let delta_event = StreamEvent::OutputTextDelta {
-                            output_index: 0,
-                            content_index: 0,
-                            delta: text,
-                        };
-
-                        if let Ok(json) = serde_json::to_string(&delta_event) {
-                            yield Ok(Event::default().event("response.output_text.delta").data(json));
-                        }
+                        events.extend(blocks.delta(AnthropicBlockKind::Text, text));
                     }
-
-                    // Send completion events if this is the last chunk
+                    // An interim reply ends its block; a later answer opens a new one
                     if is_finish {
-                        // Send output_text.done
-                        let text_done_event = StreamEvent::OutputTextDone {
-                            output_index: 0,
-                            content_index: 0,
-                            text: accumulated_text.clone(),
-                        };
-                        if let Ok(json) = serde_json::to_string(&text_done_event) {
-                            yield Ok(Event::default().event("response.output_text.done").data(json));
-                        }
-
-                        // Send output_item.done with completed message
-                        let completed_item = OutputMessage {
-                            id: msg_id.clone(),
-                            message_type: "message".to_string(),
-                            role: "assistant".to_string(),
-                            status: Some("completed".to_string()),
-                            content: vec![OutputContentItem::OutputText {
-                                text: accumulated_text.clone(),
-                                annotations: vec![],
-                            }],
-                        };
-                        let item_done_event = StreamEvent::OutputItemDone {
-                            output_index: 0,
-                            item: completed_item.clone(),
-                        };
-                        if let Ok(json) = serde_json::to_string(&item_done_event) {
-                            yield Ok(Event::default().event("response.output_item.done").data(json));
-                        }
-
-                        // Send response.completed (without usage since we don't track tokens)
-                        let completed_response = ResponsesResponse {
-                            id: response_id.clone(),
-                            created_at,
-                            status: "completed".to_string(),
-                            model: "tenex".to_string(),
-                            object: "response".to_string(),
-                            error: None,
-                            output: vec![completed_item],
-                            output_text: Some(accumulated_text.clone()),
-                            usage: None, // Don't include fake token counts
-                            previous_response_id: previous_response_id.clone(),
-                            metadata: metadata.clone(),
-                        };
-                        let completed_event = StreamEvent::ResponseCompleted {
-                            response: completed_response,
-                        };
-                        if let Ok(json) = serde_json::to_string(&completed_event) {
-                            yield Ok(Event::default().event("response.completed").data(json));
+                        events.extend(blocks.close());
+                    }
+                    for event in &events {
+                        if let Some(event) = anthropic_sse_event(event) {
+                            yield Ok(event);
                         }
-
-                        break;
                     }
                 }
-                Ok(Ok(_)) => {
-                    // Ignore other DataChange variants
-                    continue;
+                Ok(Ok(change)) => {
+                    if let Some(status) = operations_status(&turn.data_store, &change) {
+                        completion.observe(&status);
+                    }
                 }
                 Ok(Err(broadcast::error::RecvError::Lagged(n))) => {
-                    // We missed some messages due to slow processing
-                    // Log this but continue - the stream should still work
                     eprintln!("SSE stream lagged, missed {} messages", n);
                     continue;
                 }
                 Ok(Err(broadcast::error::RecvError::Closed)) => {
-                    // Channel closed, end stream with failure if we haven't completed
-                    if !received_any_chunk {
-                        let failed_response = ResponsesResponse {
-                            id: response_id.clone(),
-                            created_at,
-                            status: "failed".to_string(),
-                            model: "tenex".to_string(),
-                            object: "response".to_string(),
-                            error: Some(serde_json::json!({
-                                "message": "Data channel closed unexpectedly",
-                                "type": "server_error"
-                            })),
-                            output: vec![],
-                            output_text: None,
-                            usage: None,
-                            previous_response_id: previous_response_id.clone(),
-                            metadata: metadata.clone(),
-                        };
-                        let failed_event = ResponseFailedEvent {
-                            event_type: "response.failed".to_string(),
-                            response: failed_response,
-                        };
-                        if let Ok(json) = serde_json::to_string(&failed_event) {
-                            yield Ok(Event::default().event("response.failed").data(json));
-                        }
+                    let error = AnthropicError::from(OpenAIError::server_error(
+                        "Data channel closed unexpectedly",
+                    ));
+                    if let Some(event) = anthropic_sse_event(&AnthropicStreamEvent::Error { error: error.error }) {
+                        yield Ok(event);
                     }
                     break;
                 }
-                Err(_) => {
-                    // Timeout - continue polling
-                    continue;
-                }
+                Err(_) => continue,
             }
         }
-    };
-
-    stream
+    }
 }
 
 /// Resolve project dtag to the latest project roster.
 /// Queries nostrdb directly to get fresh data, bypassing the in-memory cache
 /// which doesn't receive project discovery events in the HTTP server context.
-async fn resolve_project(state: &HTTPServerState, project_dtag: &str) -> Result<Project> {
+pub(super) async fn resolve_project(
+    state: &HTTPServerState,
+    project_dtag: &str,
+) -> Result<Project> {
     let store = state.data_store.lock().unwrap();
     store
         .query_projects_from_ndb()
@@ -903,7 +3287,10 @@ mod tests {
     fn test_extract_user_content_text() {
         let input = ResponseInput::Text("Hello world".to_string());
         let result = extract_user_content(&input).unwrap();
-        assert_eq!(result, "Hello world");
+        assert_eq!(
+            result,
+            vec![UserContentPart::Text("Hello world".to_string())]
+        );
     }
 
     #[test]
@@ -927,7 +3314,7 @@ mod tests {
             },
         ]);
         let result = extract_user_content(&input).unwrap();
-        assert_eq!(result, "Hello!");
+        assert_eq!(result, vec![UserContentPart::Text("Hello!".to_string())]);
     }
 
     #[test]
@@ -949,6 +3336,52 @@ mod tests {
         assert!(result.unwrap_err().to_string().contains("empty"));
     }
 
+    #[test]
+    fn test_extract_user_content_attachments() {
+        let input: ResponseInput = serde_json::from_value(serde_json::json!([{
+            "role": "user",
+            "content": [
+                {"type": "input_text", "text": "What is in these?"},
+                {"type": "input_image", "image_url": "data:image/png;base64,aGVsbG8="},
+                {"type": "input_image", "image_url": "https://example.com/cat.jpg"},
+                {"type": "input_file", "filename": "notes.pdf", "file_data": "aGVsbG8="}
+            ]
+        }]))
+        .unwrap();
+
+        let parts = extract_user_content(&input).unwrap();
+        assert_eq!(
+            parts,
+            vec![
+                UserContentPart::Text("What is in these?".to_string()),
+                UserContentPart::Blob {
+                    mime_type: "image/png".to_string(),
+                    data: b"hello".to_vec(),
+                },
+                UserContentPart::RemoteUrl("https://example.com/cat.jpg".to_string()),
+                UserContentPart::Blob {
+                    mime_type: "application/pdf".to_string(),
+                    data: b"hello".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_user_content_rejects_unusable_attachments() {
+        for item in [
+            serde_json::json!({"type": "input_image", "file_id": "file-abc"}),
+            serde_json::json!({"type": "input_image", "image_url": "ftp://example.com/a.png"}),
+            serde_json::json!({"type": "input_file", "file_data": "aGVsbG8="}),
+            serde_json::json!({"type": "input_file", "filename": "a.txt"}),
+        ] {
+            let input: ResponseInput =
+                serde_json::from_value(serde_json::json!([{"role": "user", "content": [item]}]))
+                    .unwrap();
+            assert!(extract_user_content(&input).is_err());
+        }
+    }
+
     /// Test that ResponsesResponse uses u64 for created_at (not f64)
     #[test]
     fn test_responses_response_timestamp_type() {
@@ -998,6 +3431,353 @@ mod tests {
         assert!(parsed.get("usage").is_none());
     }
 
+    fn make_message(pubkey: &str, p_tags: Vec<&str>) -> Message {
+        Message {
+            id: "msg".to_string(),
+            content: "Done".to_string(),
+            pubkey: pubkey.to_string(),
+            thread_id: "thread".to_string(),
+            created_at: 0,
+            reply_to: None,
+            is_reasoning: false,
+            ask_event: None,
+            q_tags: vec![],
+            a_tags: vec![],
+            p_tags: p_tags.into_iter().map(String::from).collect(),
+            tool_name: None,
+            tool_args: None,
+            llm_metadata: HashMap::new(),
+            delegation_tag: None,
+            branch: None,
+        }
+    }
+
+    #[test]
+    fn test_is_final_reply() {
+        let reply = make_message("agent", vec!["user"]);
+        assert!(is_final_reply(&reply, "user"));
+
+        // Own messages never count as a reply
+        assert!(!is_final_reply(&make_message("user", vec!["user"]), "user"));
+
+        // Tool calls and messages that don't p-tag the user are intermediate
+        let mut tool_call = make_message("agent", vec!["user"]);
+        tool_call.tool_name = Some("shell".to_string());
+        assert!(!is_final_reply(&tool_call, "user"));
+        assert!(!is_final_reply(&make_message("agent", vec![]), "user"));
+    }
+
+    #[test]
+    fn test_find_final_reply_requires_user() {
+        let messages = vec![make_message("agent", vec!["user"])];
+        let known = HashSet::new();
+        assert!(find_final_reply(&messages, None, &known).is_none());
+        assert!(find_final_reply(&messages, Some("user"), &known).is_some());
+    }
+
+    #[test]
+    fn test_find_final_reply_skips_known_messages() {
+        let messages = vec![make_message("agent", vec!["user"])];
+        let known: HashSet<String> = ["msg".to_string()].into_iter().collect();
+        assert!(find_final_reply(&messages, Some("user"), &known).is_none());
+    }
+
+    #[test]
+    fn test_usage_from_messages() {
+        let mut message = make_message("agent", vec!["user"]);
+        assert!(usage_from_messages([&message]).is_none());
+
+        message
+            .llm_metadata
+            .insert("prompt-tokens".to_string(), "100".to_string());
+        message
+            .llm_metadata
+            .insert("completion-tokens".to_string(), "20".to_string());
+        let usage = usage_from_messages([&message]).unwrap();
+        assert_eq!(usage.input_tokens, 100);
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.total_tokens, 120);
+        assert!(usage.tenex_cost_usd.is_none());
+
+        // Usage adds up across messages, including cost
+        let mut tool_call = make_message("agent", vec![]);
+        tool_call.llm_metadata = HashMap::from([
+            ("prompt-tokens".to_string(), "50".to_string()),
+            ("completion-tokens".to_string(), "5".to_string()),
+            ("total-tokens".to_string(), "55".to_string()),
+            ("cost-usd".to_string(), "0.25".to_string()),
+        ]);
+        message
+            .llm_metadata
+            .insert("cost-usd".to_string(), "0.5".to_string());
+        let usage = usage_from_messages([&tool_call, &message]).unwrap();
+        assert_eq!(usage.input_tokens, 150);
+        assert_eq!(usage.output_tokens, 25);
+        assert_eq!(usage.total_tokens, 175);
+        assert_eq!(usage.tenex_cost_usd, Some(0.75));
+
+        let json = serde_json::to_value(ChatUsage::from(usage)).unwrap();
+        assert_eq!(json["prompt_tokens"], 150);
+        assert_eq!(json["tenex_cost_usd"], 0.75);
+    }
+
+    #[test]
+    fn test_usage_in_streamed_and_non_streamed_shapes() {
+        let mut delegated = make_message("delegate", vec!["agent"]);
+        delegated.llm_metadata = HashMap::from([
+            ("prompt-tokens".to_string(), "30".to_string()),
+            ("completion-tokens".to_string(), "10".to_string()),
+            ("total-tokens".to_string(), "40".to_string()),
+            ("cost-usd".to_string(), "0.25".to_string()),
+        ]);
+        let mut reply = make_message("agent", vec!["user"]);
+        reply.llm_metadata = HashMap::from([
+            ("prompt-tokens".to_string(), "100".to_string()),
+            ("completion-tokens".to_string(), "20".to_string()),
+            ("cost-usd".to_string(), "0.5".to_string()),
+        ]);
+        let usage = usage_from_messages([&delegated, &reply]).unwrap();
+
+        // Responses: the response body and response.completed carry the same object
+        let response = ResponsesResponse {
+            id: "resp_1".to_string(),
+            created_at: 1234567890,
+            status: "completed".to_string(),
+            model: "tenex".to_string(),
+            object: "response".to_string(),
+            error: None,
+            output: vec![],
+            output_text: None,
+            usage: Some(usage.clone()),
+            previous_response_id: None,
+            metadata: None,
+        };
+        let body = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            body["usage"],
+            serde_json::json!({
+                "input_tokens": 130,
+                "output_tokens": 30,
+                "total_tokens": 160,
+                "tenex_cost_usd": 0.75,
+            })
+        );
+        let completed = serde_json::to_value(StreamEvent::ResponseCompleted { response }).unwrap();
+        assert_eq!(completed["response"]["usage"], body["usage"]);
+
+        // Chat Completions: the response body and the include_usage chunk
+        let chat_usage = ChatUsage::from(usage.clone());
+        let body = serde_json::to_value(ChatCompletionResponse {
+            id: "chatcmpl-1".to_string(),
+            object: "chat.completion".to_string(),
+            created: 1234567890,
+            model: "tenex".to_string(),
+            choices: vec![],
+            usage: Some(chat_usage.clone()),
+        })
+        .unwrap();
+        assert_eq!(
+            body["usage"],
+            serde_json::json!({
+                "prompt_tokens": 130,
+                "completion_tokens": 30,
+                "total_tokens": 160,
+                "tenex_cost_usd": 0.75,
+            })
+        );
+        let chunk = serde_json::to_value(ChatCompletionChunk::usage(
+            "chatcmpl-1",
+            1234567890,
+            "tenex",
+            chat_usage,
+        ))
+        .unwrap();
+        assert_eq!(chunk["usage"], body["usage"]);
+
+        // Messages: the response body and message_delta
+        let body = serde_json::to_value(MessagesResponse {
+            id: "msg_1".to_string(),
+            message_type: "message".to_string(),
+            role: "assistant".to_string(),
+            model: "tenex".to_string(),
+            content: vec![],
+            stop_reason: Some("end_turn".to_string()),
+            stop_sequence: None,
+            usage: AnthropicUsage::from(usage.clone()),
+        })
+        .unwrap();
+        assert_eq!(
+            body["usage"],
+            serde_json::json!({ "input_tokens": 130, "output_tokens": 30 })
+        );
+        let delta = serde_json::to_value(AnthropicStreamEvent::MessageDelta {
+            delta: AnthropicMessageDelta {
+                stop_reason: Some("end_turn".to_string()),
+                stop_sequence: None,
+            },
+            usage: AnthropicUsage::from(usage),
+        })
+        .unwrap();
+        assert_eq!(delta["usage"], body["usage"]);
+    }
+
+    #[test]
+    fn test_chat_message_content_text() {
+        let content: ChatMessageContent = serde_json::from_value(serde_json::json!([
+            {"type": "text", "text": "Hello"},
+            {"type": "image_url", "image_url": {"url": "https://example.com/a.png"}},
+            {"type": "text", "text": "world"}
+        ]))
+        .unwrap();
+        assert_eq!(content.text(), "Hello\nworld");
+
+        let message: ChatMessage =
+            serde_json::from_value(serde_json::json!({"role": "assistant", "content": null}))
+                .unwrap();
+        assert_eq!(message.text(), "");
+    }
+
+    fn transcript_scope(project_a_tag: &str, token_id: Option<&str>) -> TranscriptScope {
+        TranscriptScope {
+            project_a_tag: project_a_tag.to_string(),
+            agent_pubkey: "agent".to_string(),
+            token_id: token_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_transcript_key() {
+        let turns = vec![
+            ("user".to_string(), "Hi".to_string()),
+            ("assistant".to_string(), "Hello!".to_string()),
+        ];
+        let padded = vec![
+            ("user".to_string(), "Hi ".to_string()),
+            ("assistant".to_string(), "Hello!\n".to_string()),
+        ];
+        let swapped = vec![
+            ("assistant".to_string(), "Hi".to_string()),
+            ("user".to_string(), "Hello!".to_string()),
+        ];
+        let scope = transcript_scope("31933:pk:a", Some("tok_1"));
+        assert_eq!(scope.key(&turns), scope.key(&padded));
+        assert_ne!(scope.key(&turns), scope.key(&swapped));
+
+        // The same transcript from another project or token is a different conversation
+        assert_ne!(
+            scope.key(&turns),
+            transcript_scope("31933:pk:b", Some("tok_1")).key(&turns)
+        );
+        assert_ne!(
+            scope.key(&turns),
+            transcript_scope("31933:pk:a", Some("tok_2")).key(&turns)
+        );
+    }
+
+    #[test]
+    fn test_chat_thread_map_evicts_least_recently_used() {
+        let scope = transcript_scope("31933:pk:a", None);
+        let key = |text: &str| scope.key(&[("user".to_string(), text.to_string())]);
+        let mut map = ChatThreadMap::new(2);
+        map.insert(key("one"), "t1".to_string());
+        map.insert(key("two"), "t2".to_string());
+        assert_eq!(map.get(&key("one")), Some("t1".to_string()));
+
+        map.insert(key("three"), "t3".to_string());
+        assert_eq!(map.get(&key("two")), None);
+        assert_eq!(map.get(&key("one")), Some("t1".to_string()));
+        assert_eq!(map.get(&key("three")), Some("t3".to_string()));
+    }
+
+    #[test]
+    fn test_turn_completion_waits_for_delegation() {
+        use crate::cli::protocol::EVENT_OPERATIONS_STATUS;
+        use std::time::{Duration, Instant};
+
+        let status =
+            |thread_id: &str, ancestors: &[&str], agents: &[&str], created_at: u64| Notification {
+                id: 0,
+                event: EVENT_OPERATIONS_STATUS.to_string(),
+                project: None,
+                thread_id: Some(thread_id.to_string()),
+                ancestors: ancestors.iter().map(|a| a.to_string()).collect(),
+                data: serde_json::json!({ "agent_pubkeys": agents, "created_at": created_at }),
+            };
+        let later = || Instant::now() + Duration::from_secs(60);
+        let mut completion = TurnCompletion::new("root");
+        completion.observe(&status("root", &[], &["pm"], 1));
+
+        // The PM finishes an interim reply and hands off to a delegate
+        completion.delta(false);
+        completion.delta(true);
+        completion.observe(&status("child", &["root"], &["coder"], 2));
+        completion.observe(&status("root", &[], &[], 3));
+        // Agents in other threads don't hold the turn open
+        completion.observe(&status("other", &[], &["pm"], 3));
+        assert!(!completion.is_complete(later()));
+
+        // The delegate answers and the PM resumes with the real reply
+        completion.observe(&status("child", &["root"], &[], 4));
+        completion.observe(&status("root", &[], &["pm"], 5));
+        completion.delta(false);
+        assert!(!completion.is_complete(later()));
+        completion.delta(true);
+        completion.observe(&status("root", &[], &[], 6));
+        assert!(!completion.is_complete(Instant::now()));
+        assert!(completion.is_complete(later()));
+    }
+
+    #[test]
+    fn test_compose_thread_content() {
+        assert_eq!(compose_thread_content(&[], "Hello"), "Hello");
+
+        let history = vec![
+            ("system".to_string(), "Be brief".to_string()),
+            ("user".to_string(), "Hi".to_string()),
+            ("assistant".to_string(), "Hello!".to_string()),
+        ];
+        assert_eq!(
+            compose_thread_content(&history, "What's next?"),
+            "System instructions:\nBe brief\n\nConversation so far:\nuser: Hi\nassistant: Hello!\n\nWhat's next?"
+        );
+    }
+
+    #[test]
+    fn test_chat_completion_chunk_serialization() {
+        let chunk = ChatCompletionChunk::new(
+            "chatcmpl-1",
+            1234567890,
+            "planner",
+            ChatDelta {
+                role: None,
+                content: Some("Hi".to_string()),
+            },
+            None,
+        );
+        let parsed = serde_json::to_value(&chunk).unwrap();
+        assert_eq!(parsed["object"], "chat.completion.chunk");
+        assert_eq!(parsed["model"], "planner");
+        assert_eq!(parsed["choices"][0]["delta"]["content"], "Hi");
+        assert!(parsed["choices"][0]["delta"].get("role").is_none());
+        assert!(parsed["choices"][0]["finish_reason"].is_null());
+        assert!(parsed.get("usage").is_none());
+
+        let usage = ChatCompletionChunk::usage(
+            "chatcmpl-1",
+            1234567890,
+            "planner",
+            ChatUsage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+                tenex_cost_usd: None,
+            },
+        );
+        let parsed = serde_json::to_value(&usage).unwrap();
+        assert_eq!(parsed["choices"], serde_json::json!([]));
+        assert_eq!(parsed["usage"]["total_tokens"], 15);
+    }
+
     /// Test StreamEvent serialization
     #[test]
     fn test_stream_event_delta() {
@@ -1043,4 +3823,225 @@ mod tests {
         assert_eq!(parsed["response"]["status"], "failed");
         assert_eq!(parsed["response"]["error"]["type"], "timeout_error");
     }
+
+    #[test]
+    fn test_anthropic_content_text() {
+        let text: AnthropicContent = serde_json::from_str(r#""Hello""#).unwrap();
+        assert_eq!(text.text(), "Hello");
+
+        let blocks: AnthropicContent = serde_json::from_str(
+            r#"[{"type":"text","text":"Look"},{"type":"image","source":{}},{"type":"text","text":"here"}]"#,
+        )
+        .unwrap();
+        assert_eq!(blocks.text(), "Look\nhere");
+    }
+
+    #[test]
+    fn test_messages_request_thinking() {
+        let parse = |json: &str| serde_json::from_str::<MessagesRequest>(json).unwrap();
+        assert!(!parse(r#"{"messages":[]}"#).thinking_enabled());
+        assert!(!parse(r#"{"messages":[],"thinking":{"type":"disabled"}}"#).thinking_enabled());
+        assert!(
+            parse(r#"{"messages":[],"thinking":{"type":"enabled","budget_tokens":2048}}"#)
+                .thinking_enabled()
+        );
+    }
+
+    #[test]
+    fn test_anthropic_error_mapping() {
+        let error = AnthropicError::from(OpenAIError::bad_request("Bad"));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["type"], "error");
+        assert_eq!(json["error"]["type"], "invalid_request_error");
+        assert_eq!(json["error"]["message"], "Bad");
+
+        let error = AnthropicError::from(OpenAIError::timeout("Slow"));
+        assert_eq!(error.error.error_type, "api_error");
+    }
+
+    #[test]
+    fn test_anthropic_block_tracker() {
+        let mut blocks = AnthropicBlockTracker::default();
+
+        let events = blocks.delta(AnthropicBlockKind::Thinking, "Hmm".to_string());
+        assert_eq!(events.len(), 2);
+        let start = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(start["type"], "content_block_start");
+        assert_eq!(start["index"], 0);
+        assert_eq!(start["content_block"]["type"], "thinking");
+
+        // Same kind continues the open block
+        let events = blocks.delta(AnthropicBlockKind::Thinking, "...".to_string());
+        assert_eq!(events.len(), 1);
+
+        // Switching to text closes the thinking block and opens block 1
+        let events = blocks.delta(AnthropicBlockKind::Text, "Hi".to_string());
+        let json: Vec<serde_json::Value> = events
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap())
+            .collect();
+        assert_eq!(json[0]["type"], "content_block_stop");
+        assert_eq!(json[0]["index"], 0);
+        assert_eq!(json[1]["content_block"]["type"], "text");
+        assert_eq!(json[2]["index"], 1);
+        assert_eq!(json[2]["delta"]["type"], "text_delta");
+        assert_eq!(json[2]["delta"]["text"], "Hi");
+        assert_eq!(events[2].name(), "content_block_delta");
+
+        assert!(matches!(
+            blocks.close(),
+            Some(AnthropicStreamEvent::ContentBlockStop { index: 1 })
+        ));
+        assert!(blocks.close().is_none());
+    }
+
+    #[test]
+    fn test_reasoning_for_reply() {
+        let mut thinking = make_message("agent", vec![]);
+        thinking.id = "thinking".to_string();
+        thinking.is_reasoning = true;
+        thinking.content = "Let me check".to_string();
+        let mut old = thinking.clone();
+        old.id = "old".to_string();
+        let mut other = thinking.clone();
+        other.id = "other".to_string();
+        other.pubkey = "someone".to_string();
+        let reply = make_message("agent", vec!["user"]);
+
+        let messages = vec![old, thinking, other, reply.clone()];
+        let known: HashSet<String> = ["old".to_string()].into_iter().collect();
+        assert_eq!(
+            reasoning_for_reply(&messages, &reply, &known),
+            vec!["Let me check".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_request_api_key() {
+        let mut headers = HeaderMap::new();
+        assert!(request_api_key(&headers).is_none());
+
+        headers.insert("x-api-key", "tenex-abc".parse().unwrap());
+        assert_eq!(request_api_key(&headers), Some("tenex-abc"));
+
+        // Bearer token takes precedence over x-api-key
+        headers.insert(header::AUTHORIZATION, "Bearer tenex-xyz".parse().unwrap());
+        assert_eq!(request_api_key(&headers), Some("tenex-xyz"));
+
+        // The auth scheme is case-insensitive (RFC 7235)
+        headers.insert(header::AUTHORIZATION, "bearer tenex-low".parse().unwrap());
+        assert_eq!(request_api_key(&headers), Some("tenex-low"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer  ".parse().unwrap());
+        assert!(request_api_key(&headers).is_none());
+        headers.insert(header::AUTHORIZATION, "Basic dXNlcg==".parse().unwrap());
+        assert!(request_api_key(&headers).is_none());
+    }
+
+    #[test]
+    fn test_is_loopback_bind() {
+        assert!(is_loopback_bind("127.0.0.1:3000"));
+        assert!(is_loopback_bind("[::1]:3000"));
+        assert!(is_loopback_bind("localhost:3000"));
+        assert!(!is_loopback_bind("0.0.0.0:3000"));
+        assert!(!is_loopback_bind("192.168.1.10:3000"));
+        assert!(!is_loopback_bind("example.com:3000"));
+    }
+
+    #[test]
+    fn test_auth_error_types() {
+        let unauthorized = OpenAIError::unauthorized("Incorrect API key provided.");
+        assert_eq!(unauthorized.error.error_type, "authentication_error");
+        assert_eq!(unauthorized.error.code, Some("invalid_api_key".to_string()));
+
+        let forbidden = AnthropicError::from(OpenAIError::forbidden("No access"));
+        assert_eq!(forbidden.error.error_type, "permission_error");
+    }
+
+    fn event_types(events: &[StreamEvent]) -> Vec<&'static str> {
+        events.iter().map(StreamEvent::name).collect()
+    }
+
+    #[test]
+    fn test_output_tracker_items() {
+        let mut output = ResponsesOutputTracker::new("abc");
+
+        let events = output.reasoning_delta("Thinking".to_string());
+        assert_eq!(
+            event_types(&events),
+            vec![
+                "response.output_item.added",
+                "response.reasoning_text.delta"
+            ]
+        );
+
+        let mut tool = make_message("agent", vec![]);
+        tool.id = "tool-event".to_string();
+        tool.tool_name = Some("fs_read".to_string());
+        tool.tool_args = Some(r#"{"path":"README.md"}"#.to_string());
+        let events = output.tool_call(&tool);
+        assert_eq!(
+            event_types(&events),
+            vec![
+                "response.reasoning_text.done",
+                "response.output_item.done",
+                "response.output_item.added",
+                "response.function_call_arguments.delta",
+                "response.function_call_arguments.done",
+                "response.output_item.done",
+            ]
+        );
+
+        let events = output.text_delta("Done".to_string());
+        let added = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(added["output_index"], 2);
+        assert_eq!(added["item"]["type"], "message");
+        assert_eq!(added["item"]["id"], "msg_abc_2");
+
+        output.finish();
+        let items = serde_json::to_value(&output.items).unwrap();
+        assert_eq!(items[0]["type"], "reasoning");
+        assert_eq!(items[0]["id"], "rs_abc");
+        assert_eq!(items[0]["content"][0]["text"], "Thinking");
+        assert_eq!(items[1]["type"], "function_call");
+        assert_eq!(items[1]["name"], "fs_read");
+        assert_eq!(items[1]["arguments"], r#"{"path":"README.md"}"#);
+        assert_eq!(items[2]["content"][0]["text"], "Done");
+        assert_eq!(output.output_text, "Done");
+    }
+
+    #[test]
+    fn test_output_tracker_always_has_message() {
+        let mut output = ResponsesOutputTracker::new("abc");
+        let events = output.finish();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(output.items[0], OutputItem::Message(_)));
+    }
+
+    #[test]
+    fn test_output_items_for_reply() {
+        let mut reasoning = make_message("agent", vec![]);
+        reasoning.id = "reasoning".to_string();
+        reasoning.is_reasoning = true;
+        reasoning.content = "Let me look".to_string();
+        let mut tool = make_message("agent", vec![]);
+        tool.id = "tool".to_string();
+        tool.tool_name = Some("shell".to_string());
+        let mut old_tool = tool.clone();
+        old_tool.id = "old".to_string();
+        let reply = make_message("agent", vec!["user"]);
+
+        let messages = vec![old_tool, reasoning, tool, reply.clone()];
+        let known: HashSet<String> = ["old".to_string()].into_iter().collect();
+        let items =
+            serde_json::to_value(output_items_for_reply(&messages, &reply, &known, "abc")).unwrap();
+
+        assert_eq!(items.as_array().unwrap().len(), 3);
+        assert_eq!(items[0]["type"], "reasoning");
+        assert_eq!(items[1]["type"], "function_call");
+        assert_eq!(items[1]["arguments"], "{}");
+        assert_eq!(items[2]["type"], "message");
+        assert_eq!(items[2]["id"], "msg_abc");
+    }
 }
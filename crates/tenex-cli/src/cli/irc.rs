@@ -0,0 +1,1059 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use super::daemon::resolve_author_name;
+use super::http::thread_title;
+use super::protocol::{Notification, EVENT_MESSAGE};
+use super::roster::{default_agent_pubkey, project_roster_agents};
+use super::subscriptions::SubscriptionHub;
+use crate::nostr::NostrCommand;
+use crate::store::AppDataStore;
+use tenex_core::runtime::CoreHandle;
+
+/// Server name and host part of every prefix
+const SERVER_NAME: &str = "tenex";
+/// Content bytes per PRIVMSG, leaving room for the prefix within 512 bytes
+const MAX_MESSAGE_BYTES: usize = 400;
+/// Messages replayed when joining a thread channel
+const REPLAY_MESSAGES: usize = 20;
+/// Event ID characters in a thread channel name
+const SHORT_ID_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+struct IrcAgent {
+    nick: String,
+    pubkey: String,
+}
+
+/// A project as seen by the gateway: `#slug` with its agents as nicks
+#[derive(Debug, Clone, PartialEq)]
+struct IrcProject {
+    slug: String,
+    a_tag: String,
+    title: String,
+    agents: Vec<IrcAgent>,
+    default_agent: Option<String>,
+}
+
+impl IrcProject {
+    fn agent_by_nick(&self, nick: &str) -> Option<&IrcAgent> {
+        self.agents
+            .iter()
+            .find(|agent| agent.nick.eq_ignore_ascii_case(nick))
+    }
+
+    fn agent_by_pubkey(&self, pubkey: &str) -> Option<&IrcAgent> {
+        self.agents.iter().find(|agent| agent.pubkey == pubkey)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct IrcThread {
+    id: String,
+    title: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct IrcMessage {
+    pubkey: String,
+    author_name: Option<String>,
+    content: String,
+}
+
+/// Read access to projects, threads and messages
+trait IrcBackend {
+    fn projects(&self) -> Vec<IrcProject>;
+    fn threads(&self, project_a_tag: &str) -> Vec<IrcThread>;
+    fn messages(&self, thread_id: &str) -> Vec<IrcMessage>;
+    fn user_pubkey(&self) -> Option<String>;
+}
+
+impl IrcBackend for AppDataStore {
+    fn projects(&self) -> Vec<IrcProject> {
+        self.get_projects()
+            .iter()
+            .map(|project| IrcProject {
+                slug: project.id.clone(),
+                a_tag: project.a_tag(),
+                title: project.title.clone(),
+                agents: project_roster_agents(self, project)
+                    .into_iter()
+                    .map(|agent| IrcAgent {
+                        nick: irc_nick(&agent.name),
+                        pubkey: agent.pubkey,
+                    })
+                    .collect(),
+                default_agent: default_agent_pubkey(project),
+            })
+            .collect()
+    }
+
+    fn threads(&self, project_a_tag: &str) -> Vec<IrcThread> {
+        self.get_threads(project_a_tag)
+            .iter()
+            .map(|thread| IrcThread {
+                id: thread.id.clone(),
+                title: thread.title.clone(),
+            })
+            .collect()
+    }
+
+    fn messages(&self, thread_id: &str) -> Vec<IrcMessage> {
+        self.get_messages(thread_id)
+            .iter()
+            .map(|message| IrcMessage {
+                pubkey: message.pubkey.clone(),
+                author_name: resolve_author_name(self, &message.pubkey),
+                content: message.content.clone(),
+            })
+            .collect()
+    }
+
+    fn user_pubkey(&self) -> Option<String> {
+        self.user_pubkey.clone()
+    }
+}
+
+/// An event the connection should publish on behalf of the client
+#[derive(Debug, Clone, PartialEq)]
+enum Publish {
+    /// Message in a project channel: starts a new thread
+    Thread {
+        channel: String,
+        project_a_tag: String,
+        agent_pubkey: String,
+        content: String,
+    },
+    /// Message in a thread channel
+    Message {
+        channel: String,
+        thread_id: String,
+        project_a_tag: String,
+        agent_pubkey: String,
+        content: String,
+    },
+}
+
+/// What the connection should do after a client line or daemon event
+#[derive(Debug, Default)]
+struct Output {
+    lines: Vec<String>,
+    publish: Option<Publish>,
+    close: bool,
+}
+
+impl Output {
+    fn lines(lines: Vec<String>) -> Self {
+        Self {
+            lines,
+            ..Default::default()
+        }
+    }
+}
+
+enum Channel {
+    Project(IrcProject),
+    Thread(IrcProject, IrcThread),
+}
+
+/// IRC state of one client connection. Turns client lines into replies and
+/// publish requests, and daemon messages into PRIVMSGs.
+#[derive(Debug, Default)]
+struct IrcSession {
+    /// Password required by the server, if any
+    password: Option<String>,
+    pass: Option<String>,
+    nick: Option<String>,
+    user: Option<String>,
+    registered: bool,
+    joined: BTreeSet<String>,
+    /// Thread channels opened in this session, which may not be in the store yet
+    threads: HashMap<String, IrcThread>,
+    /// Agent that last spoke in each thread; unaddressed messages go to it
+    last_agent: HashMap<String, String>,
+}
+
+impl IrcSession {
+    fn new(password: Option<String>) -> Self {
+        Self {
+            password,
+            ..Default::default()
+        }
+    }
+
+    fn nick(&self) -> &str {
+        self.nick.as_deref().unwrap_or("*")
+    }
+
+    fn prefix(&self) -> String {
+        format!(
+            "{}!{}@{}",
+            self.nick(),
+            self.user.as_deref().unwrap_or("user"),
+            SERVER_NAME
+        )
+    }
+
+    fn numeric(&self, code: &str, params: &str) -> String {
+        format!(":{} {} {} {}", SERVER_NAME, code, self.nick(), params)
+    }
+
+    fn handle_line(&mut self, line: &str, backend: &dyn IrcBackend) -> Output {
+        let Some((command, params)) = parse_line(line) else {
+            return Output::default();
+        };
+        let param = |i: usize| params.get(i).map(String::as_str).unwrap_or("");
+
+        match command.as_str() {
+            "CAP" => match param(0).to_ascii_uppercase().as_str() {
+                "LS" | "LIST" => Output::lines(vec![format!(":{} CAP * LS :", SERVER_NAME)]),
+                "REQ" => Output::lines(vec![format!(":{} CAP * NAK :{}", SERVER_NAME, param(1))]),
+                _ => Output::default(),
+            },
+            "PASS" => {
+                self.pass = Some(param(0).to_string());
+                Output::default()
+            }
+            "NICK" => self.set_nick(param(0)),
+            "USER" => {
+                if self.user.is_none() {
+                    self.user = Some(irc_nick(param(0)));
+                }
+                self.try_register()
+            }
+            "PING" => Output::lines(vec![format!(
+                ":{} PONG {} :{}",
+                SERVER_NAME,
+                SERVER_NAME,
+                param(0)
+            )]),
+            "PONG" => Output::default(),
+            "QUIT" => Output {
+                lines: vec!["ERROR :Closing link".to_string()],
+                close: true,
+                ..Default::default()
+            },
+            _ if !self.registered => {
+                Output::lines(vec![self.numeric("451", ":You have not registered")])
+            }
+            "JOIN" => Output::lines(
+                param(0)
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .flat_map(|name| self.join(&name.to_lowercase(), backend))
+                    .collect(),
+            ),
+            "PART" => Output::lines(
+                param(0)
+                    .split(',')
+                    .flat_map(|name| self.part(&name.to_lowercase()))
+                    .collect(),
+            ),
+            "PRIVMSG" => self.privmsg(&param(0).to_lowercase(), param(1), backend),
+            "NOTICE" => Output::default(),
+            "NAMES" => {
+                let name = param(0).to_lowercase();
+                match self.resolve_channel(&name, backend) {
+                    Some(channel) => Output::lines(self.names(&name, &channel)),
+                    None => Output::lines(vec![
+                        self.numeric("366", &format!("{} :End of /NAMES list.", name))
+                    ]),
+                }
+            }
+            "TOPIC" => {
+                let name = param(0).to_lowercase();
+                match self.resolve_channel(&name, backend) {
+                    Some(channel) => Output::lines(vec![self.topic(&name, &channel)]),
+                    None => Output::lines(vec![self.no_such_channel(&name)]),
+                }
+            }
+            "LIST" => {
+                let mut lines = vec![self.numeric("321", "Channel :Users  Name")];
+                for project in backend.projects() {
+                    lines.push(self.numeric(
+                        "322",
+                        &format!(
+                            "#{} {} :{}",
+                            project.slug,
+                            project.agents.len(),
+                            project.title
+                        ),
+                    ));
+                }
+                lines.push(self.numeric("323", ":End of /LIST"));
+                Output::lines(lines)
+            }
+            "WHO" => Output::lines(vec![
+                self.numeric("315", &format!("{} :End of /WHO list.", param(0)))
+            ]),
+            "MODE" if param(0).starts_with('#') => {
+                Output::lines(vec![self.numeric("324", &format!("{} +nt", param(0)))])
+            }
+            "MODE" => Output::lines(vec![self.numeric("221", "+")]),
+            other => Output::lines(vec![
+                self.numeric("421", &format!("{} :Unknown command", other))
+            ]),
+        }
+    }
+
+    fn set_nick(&mut self, nick: &str) -> Output {
+        if nick.is_empty() || irc_nick(nick) != nick {
+            return Output::lines(vec![
+                self.numeric("432", &format!("{} :Erroneous nickname", nick))
+            ]);
+        }
+        let previous = self.prefix();
+        self.nick = Some(nick.to_string());
+        if self.registered {
+            Output::lines(vec![format!(":{} NICK :{}", previous, nick)])
+        } else {
+            self.try_register()
+        }
+    }
+
+    /// Compare SHA-256 digests in constant time, so the check takes as long
+    /// for a near-miss as for a wrong first character
+    fn password_accepted(&self) -> bool {
+        let Some(password) = &self.password else {
+            return true;
+        };
+        let Some(pass) = &self.pass else {
+            return false;
+        };
+        let expected = Sha256::digest(password.as_bytes());
+        let presented = Sha256::digest(pass.as_bytes());
+        expected
+            .iter()
+            .zip(presented.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+
+    fn try_register(&mut self) -> Output {
+        if self.registered || self.nick.is_none() || self.user.is_none() {
+            return Output::default();
+        }
+        if !self.password_accepted() {
+            return Output {
+                lines: vec![
+                    self.numeric("464", ":Password incorrect"),
+                    "ERROR :Closing link (bad password)".to_string(),
+                ],
+                close: true,
+                ..Default::default()
+            };
+        }
+
+        self.registered = true;
+        let nick = self.nick().to_string();
+        Output::lines(vec![
+            self.numeric(
+                "001",
+                &format!(":Welcome to the TENEX IRC gateway, {}", nick),
+            ),
+            self.numeric(
+                "002",
+                &format!(
+                    ":Your host is {}, running tenex-cli {}",
+                    SERVER_NAME,
+                    env!("CARGO_PKG_VERSION")
+                ),
+            ),
+            self.numeric("003", ":Projects are channels, agents are nicks"),
+            self.numeric(
+                "004",
+                &format!(
+                    "{} tenex-cli-{} o nt",
+                    SERVER_NAME,
+                    env!("CARGO_PKG_VERSION")
+                ),
+            ),
+            self.numeric(
+                "422",
+                ":Type /list for projects; address an agent with 'nick: message'",
+            ),
+        ])
+    }
+
+    /// `#slug` for a project, `#slug/<thread id prefix>` for one of its threads
+    fn resolve_channel(&self, name: &str, backend: &dyn IrcBackend) -> Option<Channel> {
+        let name = name.strip_prefix('#')?;
+        let (slug, thread_prefix) = match name.split_once('/') {
+            Some((slug, prefix)) => (slug, Some(prefix)),
+            None => (name, None),
+        };
+        let project = backend
+            .projects()
+            .into_iter()
+            .find(|project| project.slug.eq_ignore_ascii_case(slug))?;
+
+        let Some(prefix) = thread_prefix.filter(|prefix| !prefix.is_empty()) else {
+            return Some(Channel::Project(project));
+        };
+        let thread = match self.threads.get(&format!("#{}", name)) {
+            Some(thread) => thread.clone(),
+            None => backend
+                .threads(&project.a_tag)
+                .into_iter()
+                .find(|thread| thread.id.starts_with(prefix))?,
+        };
+        Some(Channel::Thread(project, thread))
+    }
+
+    fn no_such_channel(&self, name: &str) -> String {
+        self.numeric("403", &format!("{} :No such project or thread", name))
+    }
+
+    fn topic(&self, name: &str, channel: &Channel) -> String {
+        let topic = match channel {
+            Channel::Project(project) => project.title.clone(),
+            Channel::Thread(project, thread) => format!("{} ({})", thread.title, project.title),
+        };
+        self.numeric("332", &format!("{} :{}", name, topic))
+    }
+
+    fn names(&self, name: &str, channel: &Channel) -> Vec<String> {
+        let project = match channel {
+            Channel::Project(project) | Channel::Thread(project, _) => project,
+        };
+        // The default agent (project manager) is shown as operator
+        let mut names = vec![self.nick().to_string()];
+        names.extend(project.agents.iter().map(|agent| {
+            if project.default_agent.as_ref() == Some(&agent.pubkey) {
+                format!("@{}", agent.nick)
+            } else {
+                agent.nick.clone()
+            }
+        }));
+        vec![
+            self.numeric("353", &format!("= {} :{}", name, names.join(" "))),
+            self.numeric("366", &format!("{} :End of /NAMES list.", name)),
+        ]
+    }
+
+    fn join(&mut self, name: &str, backend: &dyn IrcBackend) -> Vec<String> {
+        if self.joined.contains(name) {
+            return vec![];
+        }
+        let Some(channel) = self.resolve_channel(name, backend) else {
+            return vec![self.no_such_channel(name)];
+        };
+        self.joined.insert(name.to_string());
+
+        let mut lines = vec![format!(":{} JOIN {}", self.prefix(), name)];
+        lines.push(self.topic(name, &channel));
+        lines.extend(self.names(name, &channel));
+
+        if let Channel::Thread(project, thread) = &channel {
+            self.threads.insert(name.to_string(), thread.clone());
+            let messages = backend.messages(&thread.id);
+            let skip = messages.len().saturating_sub(REPLAY_MESSAGES);
+            let user_pubkey = backend.user_pubkey();
+            for message in &messages[skip..] {
+                let sender = if user_pubkey.as_ref() == Some(&message.pubkey) {
+                    self.prefix()
+                } else {
+                    sender_prefix(project, message)
+                };
+                lines.extend(privmsg_lines(&sender, name, &message.content));
+            }
+        }
+        lines
+    }
+
+    fn part(&mut self, name: &str) -> Vec<String> {
+        if !self.joined.remove(name) {
+            return vec![self.numeric("442", &format!("{} :You're not on that channel", name))];
+        }
+        vec![format!(":{} PART {}", self.prefix(), name)]
+    }
+
+    fn privmsg(&mut self, target: &str, text: &str, backend: &dyn IrcBackend) -> Output {
+        // CTCP: keep /me actions, ignore the rest (VERSION, PING, ...)
+        let text = match text.strip_prefix('\x01') {
+            Some(ctcp) => match ctcp.trim_end_matches('\x01').strip_prefix("ACTION ") {
+                Some(action) => format!("* {} {}", self.nick(), action),
+                None => return Output::default(),
+            },
+            None => text.to_string(),
+        };
+        if text.trim().is_empty() {
+            return Output::lines(vec![self.numeric("412", ":No text to send")]);
+        }
+        if !target.starts_with('#') {
+            return Output::lines(vec![format!(
+                ":{} NOTICE {} :Talk to agents in a project channel, e.g. /join #<project> then '{}: hello'",
+                SERVER_NAME,
+                self.nick(),
+                target
+            )]);
+        }
+        let channel = match self.resolve_channel(target, backend) {
+            Some(channel) if self.joined.contains(target) => channel,
+            _ => {
+                return Output::lines(vec![
+                    self.numeric("404", &format!("{} :Cannot send to channel", target))
+                ])
+            }
+        };
+
+        let (project, thread) = match channel {
+            Channel::Project(project) => (project, None),
+            Channel::Thread(project, thread) => (project, Some(thread)),
+        };
+        // "nick: message" or "nick, message" addresses an agent
+        let addressed = text.split_once([':', ',']).and_then(|(nick, rest)| {
+            project
+                .agent_by_nick(nick.trim())
+                .map(|agent| (agent.pubkey.clone(), rest.trim().to_string()))
+        });
+        let (agent_pubkey, content) = match addressed {
+            Some((pubkey, content)) if !content.is_empty() => (Some(pubkey), content),
+            _ => (
+                thread
+                    .as_ref()
+                    .and_then(|thread| self.last_agent.get(&thread.id).cloned())
+                    .or_else(|| project.default_agent.clone()),
+                text,
+            ),
+        };
+        let Some(agent_pubkey) = agent_pubkey else {
+            return Output::lines(vec![
+                self.numeric("404", &format!("{} :Project has no agents", target))
+            ]);
+        };
+
+        Output {
+            publish: Some(match thread {
+                None => Publish::Thread {
+                    channel: target.to_string(),
+                    project_a_tag: project.a_tag,
+                    agent_pubkey,
+                    content,
+                },
+                Some(thread) => Publish::Message {
+                    channel: target.to_string(),
+                    thread_id: thread.id,
+                    project_a_tag: project.a_tag,
+                    agent_pubkey,
+                    content,
+                },
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Report a publish result: new threads are joined as sub-channels
+    fn published(
+        &mut self,
+        publish: &Publish,
+        result: Result<String, String>,
+        backend: &dyn IrcBackend,
+    ) -> Vec<String> {
+        let event_id = match result {
+            Ok(event_id) => event_id,
+            Err(e) => {
+                let (Publish::Thread { channel, .. } | Publish::Message { channel, .. }) = publish;
+                return vec![format!(
+                    ":{} NOTICE {} :Failed to send: {}",
+                    SERVER_NAME, channel, e
+                )];
+            }
+        };
+        let Publish::Thread {
+            channel, content, ..
+        } = publish
+        else {
+            return vec![];
+        };
+
+        let thread_channel = format!("{}/{}", channel, short_id(&event_id));
+        self.threads.insert(
+            thread_channel.clone(),
+            IrcThread {
+                id: event_id,
+                title: thread_title(content),
+            },
+        );
+        let mut lines = vec![format!(
+            ":{} NOTICE {} :Started thread {}",
+            SERVER_NAME, channel, thread_channel
+        )];
+        lines.extend(self.join(&thread_channel, backend));
+        lines
+    }
+
+    /// Relay a message from the daemon to the thread channel. Agents'
+    /// messages are sent as PRIVMSGs from their nick; the client's own
+    /// messages are not echoed.
+    fn on_message(&mut self, notification: &Notification, backend: &dyn IrcBackend) -> Vec<String> {
+        if !self.registered || notification.event != EVENT_MESSAGE {
+            return vec![];
+        }
+        let (Some(a_tag), Some(thread_id)) = (&notification.project, &notification.thread_id)
+        else {
+            return vec![];
+        };
+        let data = &notification.data;
+        let message = IrcMessage {
+            pubkey: data["pubkey"].as_str().unwrap_or_default().to_string(),
+            author_name: data["author_name"].as_str().map(String::from),
+            content: data["content"].as_str().unwrap_or_default().to_string(),
+        };
+        if backend.user_pubkey().as_ref() == Some(&message.pubkey) {
+            return vec![];
+        }
+        let Some(project) = backend
+            .projects()
+            .into_iter()
+            .find(|project| &project.a_tag == a_tag)
+        else {
+            return vec![];
+        };
+        if project.agent_by_pubkey(&message.pubkey).is_some() {
+            self.last_agent
+                .insert(thread_id.clone(), message.pubkey.clone());
+        }
+
+        let channel = format!("#{}/{}", project.slug, short_id(thread_id)).to_lowercase();
+        let mut lines = Vec::new();
+        if !self.joined.contains(&channel) {
+            // Only follow threads of projects the client has joined
+            if !self
+                .joined
+                .contains(&format!("#{}", project.slug).to_lowercase())
+            {
+                return vec![];
+            }
+            // Delegated conversations are not in the project's thread list
+            if !self.threads.contains_key(&channel) {
+                let thread = backend
+                    .threads(a_tag)
+                    .into_iter()
+                    .find(|thread| &thread.id == thread_id)
+                    .unwrap_or_else(|| IrcThread {
+                        id: thread_id.clone(),
+                        title: thread_title(&message.content),
+                    });
+                self.threads.insert(channel.clone(), thread);
+            }
+            lines.extend(self.join(&channel, backend));
+            // Joining replays the thread, which already includes this message
+            if backend
+                .messages(thread_id)
+                .iter()
+                .any(|m| m.content == message.content && m.pubkey == message.pubkey)
+            {
+                return lines;
+            }
+        }
+        lines.extend(privmsg_lines(
+            &sender_prefix(&project, &message),
+            &channel,
+            &message.content,
+        ));
+        lines
+    }
+}
+
+/// Leading characters of an event ID or pubkey
+fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+/// Agents speak as their nick; other authors as their (sanitized) name
+fn sender_prefix(project: &IrcProject, message: &IrcMessage) -> String {
+    let nick = match project.agent_by_pubkey(&message.pubkey) {
+        Some(agent) => agent.nick.clone(),
+        None => irc_nick(
+            message
+                .author_name
+                .as_deref()
+                .unwrap_or_else(|| short_id(&message.pubkey)),
+        ),
+    };
+    format!("{}!{}@{}", nick, nick, SERVER_NAME)
+}
+
+/// A valid IRC nick for an agent slug or display name
+fn irc_nick(name: &str) -> String {
+    let nick: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_[]\\`^{}|".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match nick.chars().next() {
+        Some(c) if c.is_ascii_digit() || c == '-' => format!("_{}", nick),
+        Some(_) => nick,
+        None => "_".to_string(),
+    }
+}
+
+/// One PRIVMSG per line of content, split to fit the IRC line limit
+fn privmsg_lines(prefix: &str, target: &str, content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        let mut chunk = String::new();
+        for c in line.chars() {
+            if chunk.len() + c.len_utf8() > MAX_MESSAGE_BYTES {
+                lines.push(format!(":{} PRIVMSG {} :{}", prefix, target, chunk));
+                chunk.clear();
+            }
+            chunk.push(c);
+        }
+        lines.push(format!(":{} PRIVMSG {} :{}", prefix, target, chunk));
+    }
+    lines
+}
+
+/// Split an IRC line into its upper-cased command and parameters, dropping
+/// any prefix. The trailing parameter (after " :") may contain spaces.
+fn parse_line(line: &str) -> Option<(String, Vec<String>)> {
+    let mut rest = line.trim_end_matches(['\r', '\n']);
+    if rest.starts_with(':') {
+        rest = rest.split_once(' ')?.1;
+    }
+    let (head, trailing) = match rest.split_once(" :") {
+        Some((head, trailing)) => (head, Some(trailing)),
+        None => (rest, None),
+    };
+    let mut words = head.split_whitespace();
+    let command = words.next()?.to_ascii_uppercase();
+    let mut params: Vec<String> = words.map(String::from).collect();
+    params.extend(trailing.map(String::from));
+    Some((command, params))
+}
+
+/// Sign and publish an event, returning its ID
+async fn publish_event(core_handle: &CoreHandle, publish: &Publish) -> Result<String, String> {
+    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+    let command = match publish.clone() {
+        Publish::Thread {
+            project_a_tag,
+            agent_pubkey,
+            content,
+            ..
+        } => NostrCommand::PublishThread {
+            project_a_tag,
+            title: thread_title(&content),
+            content,
+            agent_pubkey: Some(agent_pubkey),
+            nudge_ids: Vec::new(),
+            skill_ids: Vec::new(),
+            reference_conversation_id: None,
+            reference_report_a_tag: None,
+            fork_message_id: None,
+            response_tx: Some(response_tx),
+        },
+        Publish::Message {
+            thread_id,
+            project_a_tag,
+            agent_pubkey,
+            content,
+            ..
+        } => NostrCommand::PublishMessage {
+            thread_id,
+            project_a_tag,
+            content,
+            agent_pubkey: Some(agent_pubkey),
+            reply_to: None,
+            nudge_ids: Vec::new(),
+            skill_ids: Vec::new(),
+            ask_author_pubkey: None,
+            response_tx: Some(response_tx),
+        },
+    };
+    core_handle.send(command).map_err(|e| e.to_string())?;
+
+    match tokio::time::timeout(
+        tokio::time::Duration::from_secs(5),
+        tokio::task::spawn_blocking(move || response_rx.recv()),
+    )
+    .await
+    {
+        Ok(Ok(Ok(event_id))) => Ok(event_id),
+        Ok(_) => Err("publishing failed".to_string()),
+        Err(_) => Err("timed out waiting for the event to be signed".to_string()),
+    }
+}
+
+/// Serve the IRC gateway: each project is a channel, each agent a nick
+/// and each thread a `#project/<id>` sub-channel
+pub(super) async fn run_irc_server(
+    bind_addr: String,
+    data_store: Arc<Mutex<AppDataStore>>,
+    core_handle: CoreHandle,
+    subscriptions: SubscriptionHub,
+    password: Option<String>,
+) -> Result<()> {
+    let listener = TcpListener::bind(&bind_addr).await?;
+    eprintln!("IRC gateway listening on {}", bind_addr);
+    if password.is_none() {
+        eprintln!("Warning: no IRC password configured - any local client can talk to agents");
+    }
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let data_store = data_store.clone();
+        let core_handle = core_handle.clone();
+        let notifications = subscriptions.subscribe();
+        let password = password.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                serve_client(stream, data_store, core_handle, notifications, password).await
+            {
+                eprintln!("IRC client {} error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn serve_client(
+    stream: TcpStream,
+    data_store: Arc<Mutex<AppDataStore>>,
+    core_handle: CoreHandle,
+    mut notifications: broadcast::Receiver<Notification>,
+    password: Option<String>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader).lines();
+    let mut session = IrcSession::new(password);
+
+    loop {
+        let output = tokio::select! {
+            line = reader.next_line() => match line? {
+                Some(line) => {
+                    let store = data_store.lock().unwrap();
+                    session.handle_line(&line, &*store)
+                }
+                None => break,
+            },
+            notification = notifications.recv() => match notification {
+                Ok(notification) => {
+                    let store = data_store.lock().unwrap();
+                    Output::lines(session.on_message(&notification, &*store))
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+
+        let mut lines = output.lines;
+        if let Some(publish) = output.publish {
+            let result = publish_event(&core_handle, &publish).await;
+            let store = data_store.lock().unwrap();
+            lines.extend(session.published(&publish, result, &*store));
+        }
+        for line in lines {
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\r\n").await?;
+        }
+        writer.flush().await?;
+        if output.close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeBackend {
+        messages: Vec<IrcMessage>,
+    }
+
+    impl IrcBackend for FakeBackend {
+        fn projects(&self) -> Vec<IrcProject> {
+            vec![IrcProject {
+                slug: "ops".to_string(),
+                a_tag: "31933:owner:ops".to_string(),
+                title: "Operations".to_string(),
+                agents: vec![
+                    IrcAgent {
+                        nick: "pm".to_string(),
+                        pubkey: "pk-pm".to_string(),
+                    },
+                    IrcAgent {
+                        nick: "coder".to_string(),
+                        pubkey: "pk-coder".to_string(),
+                    },
+                ],
+                default_agent: Some("pk-pm".to_string()),
+            }]
+        }
+
+        fn threads(&self, _project_a_tag: &str) -> Vec<IrcThread> {
+            vec![IrcThread {
+                id: "abcdef0123456789".to_string(),
+                title: "Deploy".to_string(),
+            }]
+        }
+
+        fn messages(&self, _thread_id: &str) -> Vec<IrcMessage> {
+            self.messages.clone()
+        }
+
+        fn user_pubkey(&self) -> Option<String> {
+            Some("pk-user".to_string())
+        }
+    }
+
+    fn backend() -> FakeBackend {
+        FakeBackend {
+            messages: vec![IrcMessage {
+                pubkey: "pk-user".to_string(),
+                author_name: None,
+                content: "ship it".to_string(),
+            }],
+        }
+    }
+
+    fn registered(backend: &FakeBackend) -> IrcSession {
+        let mut session = IrcSession::new(None);
+        session.handle_line("NICK alice", backend);
+        let output = session.handle_line("USER alice 0 * :Alice", backend);
+        assert!(output.lines[0].starts_with(":tenex 001 alice "));
+        session
+    }
+
+    fn message(thread_id: &str, pubkey: &str, content: &str) -> Notification {
+        Notification {
+            id: 0,
+            event: EVENT_MESSAGE.to_string(),
+            project: Some("31933:owner:ops".to_string()),
+            thread_id: Some(thread_id.to_string()),
+            ancestors: vec![],
+            data: serde_json::json!({ "pubkey": pubkey, "content": content }),
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line(":alice PRIVMSG #ops :pm: hello there\r"),
+            Some((
+                "PRIVMSG".to_string(),
+                vec!["#ops".to_string(), "pm: hello there".to_string()]
+            ))
+        );
+        assert_eq!(
+            parse_line("join #ops,#docs"),
+            Some(("JOIN".to_string(), vec!["#ops,#docs".to_string()]))
+        );
+        assert_eq!(parse_line(""), None);
+        assert_eq!(irc_nick("Code Reviewer"), "Code-Reviewer");
+        assert_eq!(irc_nick("3po"), "_3po");
+    }
+
+    #[test]
+    fn test_irc_registration() {
+        let backend = backend();
+        let mut session = IrcSession::new(Some("secret".to_string()));
+        assert!(session.handle_line("JOIN #ops", &backend).lines[0].contains(" 451 "));
+        session.handle_line("PASS wrong", &backend);
+        session.handle_line("NICK alice", &backend);
+        let output = session.handle_line("USER alice 0 * :Alice", &backend);
+        assert!(output.close);
+        assert!(output.lines[0].contains(" 464 "));
+
+        let mut session = IrcSession::new(Some("secret".to_string()));
+        session.handle_line("NICK alice", &backend);
+        let output = session.handle_line("USER alice 0 * :Alice", &backend);
+        assert!(output.close);
+
+        let mut session = IrcSession::new(Some("secret".to_string()));
+        session.handle_line("PASS secret", &backend);
+        session.handle_line("NICK alice", &backend);
+        let output = session.handle_line("USER alice 0 * :Alice", &backend);
+        assert!(!output.close);
+        assert!(session.registered);
+        assert_eq!(
+            session.handle_line("PING :123", &backend).lines,
+            vec![":tenex PONG tenex :123"]
+        );
+    }
+
+    #[test]
+    fn test_irc_channels_and_messages() {
+        let backend = backend();
+        let mut session = registered(&backend);
+
+        let lines = session.handle_line("JOIN #ops,#missing", &backend).lines;
+        assert_eq!(lines[0], ":alice!alice@tenex JOIN #ops");
+        assert_eq!(lines[1], ":tenex 332 alice #ops :Operations");
+        assert_eq!(lines[2], ":tenex 353 alice = #ops :alice @pm coder");
+        assert!(lines[4].contains(" 403 alice #missing "));
+
+        // Unaddressed messages in the project channel start a thread with the PM
+        let output = session.handle_line("PRIVMSG #ops :deploy please", &backend);
+        assert_eq!(
+            output.publish,
+            Some(Publish::Thread {
+                channel: "#ops".to_string(),
+                project_a_tag: "31933:owner:ops".to_string(),
+                agent_pubkey: "pk-pm".to_string(),
+                content: "deploy please".to_string(),
+            })
+        );
+        let publish = output.publish.unwrap();
+        let lines = session.published(&publish, Ok("1234567890ff".to_string()), &backend);
+        assert_eq!(lines[0], ":tenex NOTICE #ops :Started thread #ops/12345678");
+        assert_eq!(lines[1], ":alice!alice@tenex JOIN #ops/12345678");
+
+        // Agent replies are relayed, the client's own messages are not
+        let reply = message("1234567890ff", "pk-coder", "on it\nstep 2");
+        assert_eq!(
+            session.on_message(&reply, &backend),
+            vec![
+                ":coder!coder@tenex PRIVMSG #ops/12345678 :on it",
+                ":coder!coder@tenex PRIVMSG #ops/12345678 :step 2",
+            ]
+        );
+        let own = message("1234567890ff", "pk-user", "thanks");
+        assert!(session.on_message(&own, &backend).is_empty());
+
+        // Follow-ups go to the agent that last replied unless addressed
+        let output = session.handle_line("PRIVMSG #ops/12345678 :and tests?", &backend);
+        assert!(matches!(
+            output.publish,
+            Some(Publish::Message { ref agent_pubkey, ref thread_id, .. })
+                if agent_pubkey == "pk-coder" && thread_id == "1234567890ff"
+        ));
+        let output = session.handle_line("PRIVMSG #ops/12345678 :PM, status?", &backend);
+        assert!(matches!(
+            output.publish,
+            Some(Publish::Message { ref agent_pubkey, ref content, .. })
+                if agent_pubkey == "pk-pm" && content == "status?"
+        ));
+
+        // Threads of a joined project are joined (with replay) on first reply
+        let lines = session.on_message(&message("abcdef0123456789", "pk-pm", "done"), &backend);
+        assert_eq!(lines[0], ":alice!alice@tenex JOIN #ops/abcdef01");
+        assert!(lines.contains(&":alice!alice@tenex PRIVMSG #ops/abcdef01 :ship it".to_string()));
+        assert_eq!(
+            lines.last().unwrap(),
+            ":pm!pm@tenex PRIVMSG #ops/abcdef01 :done"
+        );
+    }
+
+    #[test]
+    fn test_privmsg_lines_split_long_content() {
+        let content = "x".repeat(MAX_MESSAGE_BYTES + 10);
+        let lines = privmsg_lines("pm!pm@tenex", "#ops", &content);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(&"x".repeat(10)));
+    }
+}
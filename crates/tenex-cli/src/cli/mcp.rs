@@ -0,0 +1,443 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use super::client::send_command_raw;
+use super::config::CliConfig;
+use super::protocol::{CliCommand, Response};
+
+/// Protocol revisions we can speak, newest first
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+const DEFAULT_REPLY_TIMEOUT_SECS: u64 = 120;
+const MAX_REPLY_TIMEOUT_SECS: u64 = 600;
+const REPLY_POLL_INTERVAL_MS: u64 = 500;
+/// How long wait_for_reply waits for `after_message_id` to show up in the thread
+const UNKNOWN_MESSAGE_GRACE_SECS: u64 = 5;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Run an MCP server over stdio: newline-delimited JSON-RPC on stdin,
+/// responses on stdout. Tool calls go through the daemon socket.
+pub(super) fn serve(data_dir: &Path, config: Option<&CliConfig>) -> Result<()> {
+    let mut call = |command: &CliCommand| send_command_raw(command, data_dir, config);
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    eprintln!("TENEX MCP server ready on stdio");
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(&line, &mut call) {
+            writeln!(stdout, "{}", serde_json::to_string(&response)?)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Handle one JSON-RPC message. Returns None for notifications.
+fn handle_line(
+    line: &str,
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(rpc_error(Value::Null, PARSE_ERROR, &e.to_string())),
+    };
+    let Some(method) = message["method"].as_str() else {
+        return Some(rpc_error(
+            message.get("id").cloned().unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "Expected a JSON-RPC request object",
+        ));
+    };
+    // Requests without an id are notifications and get no response
+    let id = message.get("id").cloned()?;
+    let params = &message["params"];
+
+    let result = match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str().unwrap_or("");
+            let version = SUPPORTED_PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| **v == requested)
+                .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0]);
+            Ok(json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": "tenex-cli",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }))
+        }
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => match params["name"].as_str() {
+            Some(name) => Ok(call_tool(name, &params["arguments"], call)),
+            None => Err((INVALID_PARAMS, "Tool name is required".to_string())),
+        },
+        other => Err((METHOD_NOT_FOUND, format!("Method '{}' not found", other))),
+    };
+
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => rpc_error(id, code, &message),
+    })
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn tool(name: &str, description: &str, properties: Value, required: &[&str]) -> Value {
+    json!({
+        "name": name,
+        "description": description,
+        "inputSchema": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        },
+    })
+}
+
+fn tool_definitions() -> Vec<Value> {
+    let project = json!({ "type": "string", "description": "Project slug (d-tag)" });
+    let agent = json!({ "type": "string", "description": "Agent slug in the project" });
+    let thread_id =
+        json!({ "type": "string", "description": "Thread ID (event ID of the root message)" });
+    let content = json!({ "type": "string", "description": "Message text" });
+
+    vec![
+        tool(
+            "list_projects",
+            "List TENEX projects with their agents and whether they are booted",
+            json!({}),
+            &[],
+        ),
+        tool(
+            "list_agents",
+            "List the agents of a TENEX project",
+            json!({ "project": project }),
+            &["project"],
+        ),
+        tool(
+            "create_thread",
+            "Start a new thread with an agent. Returns the thread_id; use wait_for_reply to get the answer.",
+            json!({ "project": project, "agent": agent, "content": content }),
+            &["project", "agent", "content"],
+        ),
+        tool(
+            "send_message",
+            "Send a message to an agent in an existing thread. Returns the message_id; use wait_for_reply to get the answer.",
+            json!({ "project": project, "thread_id": thread_id, "agent": agent, "content": content }),
+            &["project", "thread_id", "agent", "content"],
+        ),
+        tool(
+            "wait_for_reply",
+            "Wait for an agent's final reply after the given message and return it",
+            json!({
+                "thread_id": thread_id,
+                "after_message_id": {
+                    "type": "string",
+                    "description": "Wait for a reply after this message (default: the thread's root message)",
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": format!(
+                        "How long to wait (default {}, max {})",
+                        DEFAULT_REPLY_TIMEOUT_SECS, MAX_REPLY_TIMEOUT_SECS
+                    ),
+                },
+            }),
+            &["thread_id"],
+        ),
+        tool(
+            "read_messages",
+            "Read the messages of a thread, oldest first",
+            json!({
+                "thread_id": thread_id,
+                "limit": { "type": "integer", "description": "Only return the last N messages" },
+            }),
+            &["thread_id"],
+        ),
+    ]
+}
+
+/// Run a tool and wrap its outcome as an MCP tool result
+fn call_tool(
+    name: &str,
+    arguments: &Value,
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+) -> Value {
+    let (text, is_error) = match run_tool(name, arguments, call) {
+        Ok(result) => (
+            serde_json::to_string_pretty(&result).unwrap_or_default(),
+            false,
+        ),
+        Err(message) => (message, true),
+    };
+    json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": is_error,
+    })
+}
+
+fn run_tool(
+    name: &str,
+    arguments: &Value,
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+) -> Result<Value, String> {
+    let string = |key: &str| {
+        arguments[key]
+            .as_str()
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .ok_or_else(|| format!("'{}' is required", key))
+    };
+
+    match name {
+        "list_projects" => daemon_call(call, &CliCommand::ListProjects),
+        "list_agents" => daemon_call(
+            call,
+            &CliCommand::ListAgents {
+                project_slug: string("project")?,
+                wait_for_project: true,
+            },
+        ),
+        "create_thread" => daemon_call(
+            call,
+            &CliCommand::CreateThread {
+                project_slug: string("project")?,
+                recipient_slug: string("agent")?,
+                content: string("content")?,
+                wait_secs: None,
+                until_idle: false,
+                wait_for_project: true,
+                skill_ids: vec![],
+            },
+        ),
+        "send_message" => daemon_call(
+            call,
+            &CliCommand::SendMessage {
+                project_slug: string("project")?,
+                thread_id: string("thread_id")?,
+                recipient_slug: string("agent")?,
+                content: string("content")?,
+                wait_secs: None,
+                until_idle: false,
+                wait_for_project: true,
+                skill_ids: vec![],
+            },
+        ),
+        "wait_for_reply" => {
+            let thread_id = string("thread_id")?;
+            let after = string("after_message_id").unwrap_or_else(|_| thread_id.clone());
+            let timeout_secs = arguments["timeout_secs"]
+                .as_u64()
+                .unwrap_or(DEFAULT_REPLY_TIMEOUT_SECS)
+                .min(MAX_REPLY_TIMEOUT_SECS);
+            wait_for_reply(&thread_id, &after, timeout_secs, call)
+        }
+        "read_messages" => {
+            let messages = thread_messages(&string("thread_id")?, call)?;
+            let skip = arguments["limit"]
+                .as_u64()
+                .map_or(0, |limit| messages.len().saturating_sub(limit as usize));
+            Ok(json!(messages[skip..]))
+        }
+        other => Err(format!("Unknown tool '{}'", other)),
+    }
+}
+
+/// Send a command to the daemon, turning daemon errors into tool errors
+fn daemon_call(
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+    command: &CliCommand,
+) -> Result<Value, String> {
+    let response = call(command).map_err(|e| e.to_string())?;
+    if let Some(error) = response.error {
+        return Err(format!("[{}] {}", error.code, error.message));
+    }
+    Ok(response.result.unwrap_or(Value::Null))
+}
+
+fn thread_messages(
+    thread_id: &str,
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+) -> Result<Vec<Value>, String> {
+    let result = daemon_call(
+        call,
+        &CliCommand::ListMessages {
+            thread_id: thread_id.to_string(),
+        },
+    )?;
+    Ok(result.as_array().cloned().unwrap_or_default())
+}
+
+/// Poll the thread until a message the daemon flags as `final_reply` follows
+/// `after_message_id`. Times out with a null reply rather than an
+/// error; an `after_message_id` that is not in the thread is an error.
+fn wait_for_reply(
+    thread_id: &str,
+    after_message_id: &str,
+    timeout_secs: u64,
+    call: &mut impl FnMut(&CliCommand) -> Result<Response>,
+) -> Result<Value, String> {
+    let start = Instant::now();
+    loop {
+        let messages = thread_messages(thread_id, call)?;
+        match messages
+            .iter()
+            .position(|m| m["id"].as_str() == Some(after_message_id))
+        {
+            Some(index) => {
+                if let Some(reply) = messages[index + 1..]
+                    .iter()
+                    .find(|m| m["final_reply"].as_bool() == Some(true))
+                {
+                    return Ok(json!({ "reply": reply, "timed_out": false }));
+                }
+            }
+            // A message sent moments ago may not have reached the store yet
+            None if start.elapsed() >= Duration::from_secs(UNKNOWN_MESSAGE_GRACE_SECS) => {
+                return Err(format!(
+                    "Message '{}' not found in thread '{}'",
+                    after_message_id, thread_id
+                ));
+            }
+            None => {}
+        }
+        if start.elapsed() >= Duration::from_secs(timeout_secs) {
+            return Ok(json!({ "reply": null, "timed_out": true }));
+        }
+        std::thread::sleep(Duration::from_millis(REPLY_POLL_INTERVAL_MS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in for the daemon that answers list_messages and records sends
+    fn fake_daemon(sent: &mut Vec<String>) -> impl FnMut(&CliCommand) -> Result<Response> + '_ {
+        move |command| {
+            let request = command.to_request(1).unwrap();
+            sent.push(request.method.clone());
+            Ok(match request.method.as_str() {
+                "list_messages" => Response::success(
+                    1,
+                    json!([
+                        { "id": "root", "content": "hi", "pubkey": "user", "final_reply": false },
+                        { "id": "m2", "content": "working on it", "pubkey": "agent", "final_reply": false },
+                        { "id": "m3", "content": "done", "pubkey": "agent", "final_reply": true },
+                    ]),
+                ),
+                "create_thread" => {
+                    Response::error(1, "PROJECT_NOT_FOUND", "Project 'nope' not found")
+                }
+                _ => Response::success(1, json!([])),
+            })
+        }
+    }
+
+    fn request(method: &str, params: Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params }).to_string()
+    }
+
+    #[test]
+    fn test_mcp_handshake() {
+        let mut sent = Vec::new();
+        let mut call = fake_daemon(&mut sent);
+
+        let response = handle_line(
+            &request("initialize", json!({ "protocolVersion": "2024-11-05" })),
+            &mut call,
+        )
+        .unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        assert!(response["result"]["capabilities"]["tools"].is_object());
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_line(&initialized.to_string(), &mut call).is_none());
+
+        let response = handle_line(&request("tools/list", json!({})), &mut call).unwrap();
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert!(tools.iter().any(|t| t["name"] == "wait_for_reply"));
+
+        let response = handle_line(&request("resources/list", json!({})), &mut call).unwrap();
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = handle_line("{not json", &mut call).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn test_mcp_tool_calls() {
+        let mut sent = Vec::new();
+        let mut call = fake_daemon(&mut sent);
+        let tool_call = |name: &str, arguments: Value| {
+            request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+        };
+
+        let response = handle_line(
+            &tool_call("wait_for_reply", json!({ "thread_id": "root" })),
+            &mut call,
+        )
+        .unwrap();
+        assert_eq!(response["result"]["isError"], false);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        let reply: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(reply["reply"]["id"], "m3");
+        assert_eq!(reply["timed_out"], false);
+
+        let response = handle_line(
+            &tool_call("read_messages", json!({ "thread_id": "root", "limit": 2 })),
+            &mut call,
+        )
+        .unwrap();
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        let messages: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(messages[0]["id"], "m2");
+
+        let response = handle_line(
+            &tool_call(
+                "create_thread",
+                json!({ "project": "nope", "agent": "pm", "content": "hi" }),
+            ),
+            &mut call,
+        )
+        .unwrap();
+        assert_eq!(response["result"]["isError"], true);
+        assert_eq!(
+            response["result"]["content"][0]["text"],
+            "[PROJECT_NOT_FOUND] Project 'nope' not found"
+        );
+
+        let response = handle_line(&tool_call("send_message", json!({})), &mut call).unwrap();
+        assert_eq!(response["result"]["isError"], true);
+
+        drop(call);
+        assert_eq!(
+            sent,
+            vec!["list_messages", "list_messages", "create_thread"]
+        );
+    }
+}
@@ -1,9 +1,23 @@
+pub mod api_tokens;
+mod asks;
 pub mod client;
 pub mod config;
 pub mod daemon;
+mod documents;
+mod follow;
 pub mod http;
+mod irc;
+mod mcp;
 pub mod protocol;
+mod reload;
+pub mod response_chains;
 mod roster;
+mod schedules;
+pub mod subscriptions;
+mod supervisor;
+pub mod usage;
+mod webhooks;
+mod ws;
 
 pub use client::{is_daemon_running, send_command, socket_path};
 pub use config::CliConfig;
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::usage::{UsageFormat, UsageGroup};
+
 /// Request from CLI client to daemon
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Request {
@@ -46,6 +48,145 @@ impl Response {
     }
 }
 
+/// Event types streamed to `subscribe` connections
+pub const EVENT_MESSAGE: &str = "message";
+pub const EVENT_STREAM_DELTA: &str = "stream_delta";
+pub const EVENT_PROJECT_STATUS: &str = "project_status";
+pub const EVENT_OPERATIONS_STATUS: &str = "operations_status";
+pub const EVENT_INBOX: &str = "inbox";
+pub const EVENT_BUNKER_REQUEST: &str = "bunker_request";
+
+pub const EVENT_TYPES: &[&str] = &[
+    EVENT_MESSAGE,
+    EVENT_STREAM_DELTA,
+    EVENT_PROJECT_STATUS,
+    EVENT_OPERATIONS_STATUS,
+    EVENT_INBOX,
+    EVENT_BUNKER_REQUEST,
+];
+
+/// Filter for the `subscribe` method. Empty lists match everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubscribeFilter {
+    /// Project d-tags or a-tag coordinates
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// Thread (conversation root) IDs
+    #[serde(default)]
+    pub threads: Vec<String>,
+    /// Also match conversations delegated (directly or transitively) from `threads`
+    #[serde(default)]
+    pub descendants: bool,
+    /// Event types, see `EVENT_TYPES`
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl SubscribeFilter {
+    /// Reject unknown event types
+    pub fn validate(&self) -> Result<(), String> {
+        match self
+            .events
+            .iter()
+            .find(|event| !EVENT_TYPES.contains(&event.as_str()))
+        {
+            Some(event) => Err(format!(
+                "Unknown event type '{}'. Must be one of: {}",
+                event,
+                EVENT_TYPES.join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a notification passes the filter. Notifications without a
+    /// project or thread never match a project or thread filter.
+    pub fn matches(&self, notification: &Notification) -> bool {
+        if !self.events.is_empty() && !self.events.contains(&notification.event) {
+            return false;
+        }
+
+        if !self.projects.is_empty() {
+            let Some(a_tag) = notification.project.as_deref() else {
+                return false;
+            };
+            let d_tag = a_tag.splitn(3, ':').nth(2);
+            if !self
+                .projects
+                .iter()
+                .any(|p| p == a_tag || Some(p.as_str()) == d_tag)
+            {
+                return false;
+            }
+        }
+
+        if !self.threads.is_empty() {
+            let Some(thread_id) = notification.thread_id.as_deref() else {
+                return false;
+            };
+            let in_tree = |t: &String| {
+                t == thread_id || (self.descendants && notification.ancestors.contains(t))
+            };
+            if !self.threads.iter().any(in_tree) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Notification streamed after a successful `subscribe` response, one per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    /// ID of the `subscribe` request
+    pub id: u64,
+    /// Event type, see `EVENT_TYPES`
+    pub event: String,
+    /// Project a-tag coordinate, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Thread (conversation root) ID, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    /// Conversations the thread was delegated from, nearest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ancestors: Vec<String>,
+    pub data: serde_json::Value,
+}
+
+/// Answer to one question of an ask event, matched to it by title
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AskAnswer {
+    /// Question title
+    pub question: String,
+    #[serde(flatten)]
+    pub value: AskAnswerValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AskAnswerValue {
+    /// One of the question's suggestions
+    SingleSelect { value: String },
+    /// Any of the question's options
+    MultiSelect { values: Vec<String> },
+    /// Free text instead of a suggestion
+    CustomText { value: String },
+}
+
+/// How `show-report` / `show-lesson` print the document (client-side only)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocumentFormat {
+    /// The daemon's JSON result
+    #[default]
+    Json,
+    /// Only the markdown content
+    Raw,
+    /// Markdown rendered as plain terminal text
+    Rendered,
+}
+
 /// CLI command parsed from arguments
 #[derive(Debug, Clone)]
 pub enum CliCommand {
@@ -74,6 +215,7 @@ pub enum CliCommand {
         recipient_slug: String,
         content: String,
         wait_secs: Option<u64>,
+        until_idle: bool,
         wait_for_project: bool,
         skill_ids: Vec<String>,
     },
@@ -83,6 +225,7 @@ pub enum CliCommand {
         recipient_slug: String,
         content: String,
         wait_secs: Option<u64>,
+        until_idle: bool,
         wait_for_project: bool,
         skill_ids: Vec<String>,
     },
@@ -94,10 +237,109 @@ pub enum CliCommand {
     Shutdown,
     /// List all agent definitions (kind:4199)
     ListAgentDefinitions,
+    /// Publish an agent definition (kind:4199), optionally forked or cloned
+    /// from `source_id`. Omitted fields are taken from the source.
+    CreateAgentDefinition {
+        name: Option<String>,
+        description: Option<String>,
+        role: Option<String>,
+        instructions: Option<String>,
+        version: Option<String>,
+        source_id: Option<String>,
+        is_fork: bool,
+    },
+    /// Delete an agent definition (kind:5)
+    DeleteAgentDefinition { id: String },
+    /// Ask a backend to install an agent definition (kind:24001)
+    InstallAgentDefinition {
+        id: String,
+        backend_pubkey: Option<String>,
+        project_slug: Option<String>,
+        slug: Option<String>,
+    },
     /// List all MCP tools (kind:4200)
     ListMCPTools,
     /// List all skills (kind:4202)
     ListSkills,
+    /// List nudges (kind:4201)
+    ListNudges,
+    /// Create a nudge (kind:4201). Tool permissions are either exclusive
+    /// (only_tools) or additive (allow_tools / deny_tools).
+    CreateNudge {
+        title: String,
+        description: String,
+        content: String,
+        hashtags: Vec<String>,
+        allow_tools: Vec<String>,
+        deny_tools: Vec<String>,
+        only_tools: Vec<String>,
+    },
+    /// Update a nudge by publishing a superseding kind:4201.
+    /// Omitted fields keep their current value.
+    UpdateNudge {
+        nudge_id: String,
+        title: Option<String>,
+        description: Option<String>,
+        content: Option<String>,
+        hashtags: Vec<String>,
+        allow_tools: Vec<String>,
+        deny_tools: Vec<String>,
+        only_tools: Vec<String>,
+    },
+    /// Delete a nudge (kind:5)
+    DeleteNudge { nudge_id: String },
+    /// List reports (kind:30023), newest version of each
+    ListReports {
+        project_slug: Option<String>,
+        author: Option<String>,
+        hashtags: Vec<String>,
+    },
+    /// Show a report by slug or `30023:<pubkey>:<slug>` coordinate
+    ShowReport {
+        report: String,
+        format: DocumentFormat,
+    },
+    /// List lessons (kind:4129), newest first
+    ListLessons {
+        project_slug: Option<String>,
+        author: Option<String>,
+        hashtags: Vec<String>,
+    },
+    /// Show a lesson by event ID
+    ShowLesson {
+        lesson_id: String,
+        format: DocumentFormat,
+    },
+    /// LLM cost, tokens and runtime from agent messages, grouped by
+    /// project, agent, model or day. `until` is exclusive.
+    Usage {
+        since: Option<u64>,
+        until: Option<u64>,
+        group_by: UsageGroup,
+        project_slug: Option<String>,
+        format: UsageFormat,
+    },
+    /// Recent webhook deliveries, newest first
+    WebhookLog { hook: Option<String>, limit: usize },
+    /// List unread inbox items and unanswered asks
+    ListInbox {
+        project_slug: Option<String>,
+        include_read: bool,
+    },
+    /// Show an ask event's questions
+    ShowAsk { ask_id: String },
+    /// Answer an ask event (kind:1 reply to it). Without answers, the
+    /// client prompts for them interactively.
+    AnswerAsk {
+        ask_id: String,
+        answers: Option<Vec<AskAnswer>>,
+    },
+    /// Start a thread about a report (kind:1 with the report's a-tag)
+    DiscussReport {
+        report: String,
+        recipient_slug: Option<String>,
+        content: String,
+    },
     /// Show detailed project information (kind:31933 roster and kind:0 agent config)
     ShowProject {
         project_slug: String,
@@ -111,6 +353,23 @@ pub enum CliCommand {
         agent_pubkeys: Vec<String>,
         mcp_tool_ids: Vec<String>,
     },
+    /// Apply one change to a project's ordered agent list and republish
+    /// kind:31933. `action` is add, remove, set_pm or move; `agent` is a
+    /// slug or hex pubkey; `position` is 0-based (0 = PM).
+    EditProjectRoster {
+        project_slug: String,
+        action: String,
+        agent: String,
+        position: Option<usize>,
+    },
+    /// Tombstone-delete a project (kind:31933 with a deleted tag)
+    DeleteProject { project_slug: String },
+    /// Delete an agent from a project, or globally without one (kind:24030)
+    DeleteAgent {
+        agent: String,
+        project_slug: Option<String>,
+        reason: Option<String>,
+    },
     /// Set agent settings (publishes kind:24020 config-change request)
     SetAgentSettings {
         project_slug: String,
@@ -149,13 +408,65 @@ pub enum CliCommand {
     BunkerListPending,
     /// Internal: respond to pending bunker signing request
     BunkerRespond { request_id: String, approved: bool },
+    /// Stop running agents (kind:24134): a thread, a thread and its delegations,
+    /// one agent, or every agent in a project
+    StopOperations {
+        project_slug: Option<String>,
+        thread_id: Option<String>,
+        agent_slug: Option<String>,
+        descendants: bool,
+        reason: Option<String>,
+    },
+    /// Create an HTTP API token scoped to projects (and optionally agents)
+    ApiTokenCreate {
+        name: String,
+        projects: Vec<String>,
+        agents: Vec<String>,
+    },
+    /// List HTTP API tokens
+    ApiTokenList,
+    /// Revoke an HTTP API token by id or name
+    ApiTokenRevoke { token: String },
+    /// Add a recurring prompt. Without `thread_id`, each run starts a new thread.
+    ScheduleAdd {
+        name: String,
+        cron: String,
+        project_slug: String,
+        agent_slug: Option<String>,
+        title: Option<String>,
+        thread_id: Option<String>,
+        content: String,
+        skill_ids: Vec<String>,
+        nudge_ids: Vec<String>,
+    },
+    /// List scheduled jobs with their next and last run
+    ScheduleList,
+    /// Pause or resume a scheduled job by id or name
+    SchedulePause { job: String, paused: bool },
+    /// Remove a scheduled job by id or name
+    ScheduleRemove { job: String },
+    /// Runs of a scheduled job, newest first
+    ScheduleHistory { job: String, limit: usize },
+    /// Stream daemon events as NDJSON until interrupted
+    Subscribe { filter: SubscribeFilter },
+    /// Live-tail a thread and its delegated sub-conversations (local mode)
+    Follow { thread_id: String },
+    /// Serve MCP over stdio, calling the daemon for each tool (local mode)
+    Mcp,
+    /// Internal: delegation tree of a thread with per-conversation state
+    GetConversationTree { thread_id: String },
 }
 
 impl CliCommand {
     /// Convert to a Request for sending to daemon
     pub fn to_request(&self, id: u64) -> Option<Request> {
         let (method, params) = match self {
-            CliCommand::Daemon | CliCommand::BunkerWatch => return None, // Not sent to daemon
+            CliCommand::Daemon
+            | CliCommand::BunkerWatch
+            | CliCommand::Follow { .. }
+            | CliCommand::Mcp => {
+                return None; // Not sent to daemon
+            }
             CliCommand::ListProjects => ("list_projects", serde_json::json!({})),
             CliCommand::ListThreads {
                 project_slug,
@@ -309,6 +620,271 @@ impl CliCommand {
                     "approved": approved
                 }),
             ),
+            CliCommand::CreateAgentDefinition {
+                name,
+                description,
+                role,
+                instructions,
+                version,
+                source_id,
+                is_fork,
+            } => (
+                "create_agent_definition",
+                serde_json::json!({
+                    "name": name,
+                    "description": description,
+                    "role": role,
+                    "instructions": instructions,
+                    "version": version,
+                    "source_id": source_id,
+                    "is_fork": is_fork
+                }),
+            ),
+            CliCommand::DeleteAgentDefinition { id } => {
+                ("delete_agent_definition", serde_json::json!({ "id": id }))
+            }
+            CliCommand::InstallAgentDefinition {
+                id,
+                backend_pubkey,
+                project_slug,
+                slug,
+            } => (
+                "install_agent_definition",
+                serde_json::json!({
+                    "id": id,
+                    "backend_pubkey": backend_pubkey,
+                    "project_slug": project_slug,
+                    "slug": slug
+                }),
+            ),
+            CliCommand::EditProjectRoster {
+                project_slug,
+                action,
+                agent,
+                position,
+            } => (
+                "edit_project_roster",
+                serde_json::json!({
+                    "project_slug": project_slug,
+                    "action": action,
+                    "agent": agent,
+                    "position": position
+                }),
+            ),
+            CliCommand::DeleteProject { project_slug } => (
+                "delete_project",
+                serde_json::json!({ "project_slug": project_slug }),
+            ),
+            CliCommand::DeleteAgent {
+                agent,
+                project_slug,
+                reason,
+            } => (
+                "delete_agent",
+                serde_json::json!({
+                    "agent": agent,
+                    "project_slug": project_slug,
+                    "reason": reason
+                }),
+            ),
+            CliCommand::ListNudges => ("list_nudges", serde_json::json!({})),
+            CliCommand::ListReports {
+                project_slug,
+                author,
+                hashtags,
+            } => (
+                "list_reports",
+                serde_json::json!({
+                    "project_slug": project_slug,
+                    "author": author,
+                    "hashtags": hashtags
+                }),
+            ),
+            CliCommand::ShowReport { report, .. } => {
+                ("show_report", serde_json::json!({ "report": report }))
+            }
+            CliCommand::ListLessons {
+                project_slug,
+                author,
+                hashtags,
+            } => (
+                "list_lessons",
+                serde_json::json!({
+                    "project_slug": project_slug,
+                    "author": author,
+                    "hashtags": hashtags
+                }),
+            ),
+            CliCommand::ShowLesson { lesson_id, .. } => {
+                ("show_lesson", serde_json::json!({ "lesson_id": lesson_id }))
+            }
+            CliCommand::Usage {
+                since,
+                until,
+                group_by,
+                project_slug,
+                ..
+            } => (
+                "usage",
+                serde_json::json!({
+                    "since": since,
+                    "until": until,
+                    "group_by": group_by,
+                    "project_slug": project_slug
+                }),
+            ),
+            CliCommand::WebhookLog { hook, limit } => (
+                "webhook_log",
+                serde_json::json!({ "hook": hook, "limit": limit }),
+            ),
+            CliCommand::ListInbox {
+                project_slug,
+                include_read,
+            } => (
+                "list_inbox",
+                serde_json::json!({
+                    "project_slug": project_slug,
+                    "include_read": include_read
+                }),
+            ),
+            CliCommand::ShowAsk { ask_id } => ("show_ask", serde_json::json!({ "ask_id": ask_id })),
+            CliCommand::AnswerAsk { ask_id, answers } => (
+                "answer_ask",
+                serde_json::json!({ "ask_id": ask_id, "answers": answers }),
+            ),
+            CliCommand::DiscussReport {
+                report,
+                recipient_slug,
+                content,
+            } => (
+                "discuss_report",
+                serde_json::json!({
+                    "report": report,
+                    "recipient_slug": recipient_slug,
+                    "content": content
+                }),
+            ),
+            CliCommand::CreateNudge {
+                title,
+                description,
+                content,
+                hashtags,
+                allow_tools,
+                deny_tools,
+                only_tools,
+            } => (
+                "create_nudge",
+                serde_json::json!({
+                    "title": title,
+                    "description": description,
+                    "content": content,
+                    "hashtags": hashtags,
+                    "allow_tools": allow_tools,
+                    "deny_tools": deny_tools,
+                    "only_tools": only_tools
+                }),
+            ),
+            CliCommand::UpdateNudge {
+                nudge_id,
+                title,
+                description,
+                content,
+                hashtags,
+                allow_tools,
+                deny_tools,
+                only_tools,
+            } => (
+                "update_nudge",
+                serde_json::json!({
+                    "nudge_id": nudge_id,
+                    "title": title,
+                    "description": description,
+                    "content": content,
+                    "hashtags": hashtags,
+                    "allow_tools": allow_tools,
+                    "deny_tools": deny_tools,
+                    "only_tools": only_tools
+                }),
+            ),
+            CliCommand::DeleteNudge { nudge_id } => {
+                ("delete_nudge", serde_json::json!({ "nudge_id": nudge_id }))
+            }
+            CliCommand::StopOperations {
+                project_slug,
+                thread_id,
+                agent_slug,
+                descendants,
+                reason,
+            } => (
+                "stop_operations",
+                serde_json::json!({
+                    "project_slug": project_slug,
+                    "thread_id": thread_id,
+                    "agent_slug": agent_slug,
+                    "descendants": descendants,
+                    "reason": reason
+                }),
+            ),
+            CliCommand::ApiTokenCreate {
+                name,
+                projects,
+                agents,
+            } => (
+                "api_token_create",
+                serde_json::json!({
+                    "name": name,
+                    "projects": projects,
+                    "agents": agents
+                }),
+            ),
+            CliCommand::ApiTokenList => ("api_token_list", serde_json::json!({})),
+            CliCommand::ScheduleAdd {
+                name,
+                cron,
+                project_slug,
+                agent_slug,
+                title,
+                thread_id,
+                content,
+                skill_ids,
+                nudge_ids,
+            } => (
+                "schedule_add",
+                serde_json::json!({
+                    "name": name,
+                    "cron": cron,
+                    "project_slug": project_slug,
+                    "agent_slug": agent_slug,
+                    "title": title,
+                    "thread_id": thread_id,
+                    "content": content,
+                    "skill_ids": skill_ids,
+                    "nudge_ids": nudge_ids
+                }),
+            ),
+            CliCommand::ScheduleList => ("schedule_list", serde_json::json!({})),
+            CliCommand::SchedulePause { job, paused } => (
+                "schedule_pause",
+                serde_json::json!({ "job": job, "paused": paused }),
+            ),
+            CliCommand::ScheduleRemove { job } => {
+                ("schedule_remove", serde_json::json!({ "job": job }))
+            }
+            CliCommand::ScheduleHistory { job, limit } => (
+                "schedule_history",
+                serde_json::json!({ "job": job, "limit": limit }),
+            ),
+            CliCommand::ApiTokenRevoke { token } => {
+                ("api_token_revoke", serde_json::json!({ "token": token }))
+            }
+            CliCommand::GetConversationTree { thread_id } => (
+                "get_conversation_tree",
+                serde_json::json!({ "thread_id": thread_id }),
+            ),
+            CliCommand::Subscribe { filter } => (
+                "subscribe",
+                serde_json::to_value(filter).unwrap_or_default(),
+            ),
         };
 
         Some(Request {
@@ -328,6 +904,15 @@ mod tests {
         assert!(CliCommand::BunkerWatch.to_request(1).is_none());
     }
 
+    #[test]
+    fn follow_is_local_only() {
+        let follow = CliCommand::Follow {
+            thread_id: "abc".to_string(),
+        };
+        assert!(follow.to_request(1).is_none());
+        assert!(CliCommand::Mcp.to_request(1).is_none());
+    }
+
     #[test]
     fn bunker_start_to_request_mapping() {
         let req = CliCommand::BunkerStart.to_request(42).expect("request");
@@ -439,4 +1024,240 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn update_nudge_to_request_mapping() {
+        let req = CliCommand::UpdateNudge {
+            nudge_id: "n1".to_string(),
+            title: None,
+            description: Some("terse".to_string()),
+            content: None,
+            hashtags: vec![],
+            allow_tools: vec![],
+            deny_tools: vec![],
+            only_tools: vec!["read_path".to_string()],
+        }
+        .to_request(15)
+        .expect("request");
+        assert_eq!(req.method, "update_nudge");
+        assert_eq!(
+            req.params,
+            serde_json::json!({
+                "nudge_id": "n1",
+                "title": null,
+                "description": "terse",
+                "content": null,
+                "hashtags": [],
+                "allow_tools": [],
+                "deny_tools": [],
+                "only_tools": ["read_path"]
+            })
+        );
+    }
+
+    #[test]
+    fn show_report_format_is_client_side() {
+        let req = CliCommand::ShowReport {
+            report: "weekly-status".to_string(),
+            format: DocumentFormat::Rendered,
+        }
+        .to_request(17)
+        .expect("request");
+        assert_eq!(req.method, "show_report");
+        assert_eq!(req.params, serde_json::json!({ "report": "weekly-status" }));
+    }
+
+    #[test]
+    fn ask_answers_are_tagged_by_type() {
+        let answers: Vec<AskAnswer> = serde_json::from_value(serde_json::json!([
+            { "question": "Database", "type": "single_select", "value": "Postgres" },
+            { "question": "Targets", "type": "multi_select", "values": ["linux", "macos"] },
+            { "question": "Notes", "type": "custom_text", "value": "ship it" }
+        ]))
+        .expect("answers");
+        assert_eq!(
+            answers[1],
+            AskAnswer {
+                question: "Targets".to_string(),
+                value: AskAnswerValue::MultiSelect {
+                    values: vec!["linux".to_string(), "macos".to_string()],
+                },
+            }
+        );
+
+        let req = CliCommand::AnswerAsk {
+            ask_id: "ask1".to_string(),
+            answers: Some(answers),
+        }
+        .to_request(18)
+        .expect("request");
+        assert_eq!(req.method, "answer_ask");
+        assert_eq!(req.params["answers"][0]["type"], "single_select");
+        assert_eq!(req.params["answers"][2]["value"], "ship it");
+    }
+
+    #[test]
+    fn stop_operations_to_request_mapping() {
+        let req = CliCommand::StopOperations {
+            project_slug: None,
+            thread_id: Some("t1".to_string()),
+            agent_slug: None,
+            descendants: true,
+            reason: None,
+        }
+        .to_request(16)
+        .expect("request");
+        assert_eq!(req.method, "stop_operations");
+        assert_eq!(
+            req.params,
+            serde_json::json!({
+                "project_slug": null,
+                "thread_id": "t1",
+                "agent_slug": null,
+                "descendants": true,
+                "reason": null
+            })
+        );
+    }
+
+    #[test]
+    fn api_token_create_to_request_mapping() {
+        let req = CliCommand::ApiTokenCreate {
+            name: "voice".to_string(),
+            projects: vec!["my-project".to_string()],
+            agents: vec![],
+        }
+        .to_request(17)
+        .expect("request");
+        assert_eq!(req.method, "api_token_create");
+        assert_eq!(
+            req.params,
+            serde_json::json!({
+                "name": "voice",
+                "projects": ["my-project"],
+                "agents": []
+            })
+        );
+    }
+
+    #[test]
+    fn api_token_revoke_to_request_mapping() {
+        let req = CliCommand::ApiTokenRevoke {
+            token: "tok_1".to_string(),
+        }
+        .to_request(18)
+        .expect("request");
+        assert_eq!(req.method, "api_token_revoke");
+        assert_eq!(req.params, serde_json::json!({ "token": "tok_1" }));
+    }
+
+    #[test]
+    fn schedule_resume_to_request_mapping() {
+        let req = CliCommand::SchedulePause {
+            job: "standup".to_string(),
+            paused: false,
+        }
+        .to_request(19)
+        .expect("request");
+        assert_eq!(req.method, "schedule_pause");
+        assert_eq!(
+            req.params,
+            serde_json::json!({ "job": "standup", "paused": false })
+        );
+    }
+
+    #[test]
+    fn subscribe_to_request_mapping() {
+        let req = CliCommand::Subscribe {
+            filter: SubscribeFilter {
+                projects: vec!["my-project".to_string()],
+                threads: vec![],
+                descendants: false,
+                events: vec![EVENT_MESSAGE.to_string()],
+            },
+        }
+        .to_request(19)
+        .expect("request");
+        assert_eq!(req.method, "subscribe");
+        assert_eq!(
+            req.params,
+            serde_json::json!({
+                "projects": ["my-project"],
+                "threads": [],
+                "descendants": false,
+                "events": ["message"]
+            })
+        );
+    }
+
+    fn notification(event: &str, project: Option<&str>, thread_id: Option<&str>) -> Notification {
+        Notification {
+            id: 1,
+            event: event.to_string(),
+            project: project.map(String::from),
+            thread_id: thread_id.map(String::from),
+            ancestors: vec![],
+            data: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn subscribe_filter_matching() {
+        let message = notification(EVENT_MESSAGE, Some("31933:abc:my-project"), Some("t1"));
+        let bunker = notification(EVENT_BUNKER_REQUEST, None, None);
+
+        assert!(SubscribeFilter::default().matches(&message));
+        assert!(SubscribeFilter::default().matches(&bunker));
+
+        let by_slug = SubscribeFilter {
+            projects: vec!["my-project".to_string()],
+            ..Default::default()
+        };
+        assert!(by_slug.matches(&message));
+        assert!(!by_slug.matches(&bunker));
+
+        let by_a_tag = SubscribeFilter {
+            projects: vec!["31933:abc:my-project".to_string()],
+            ..Default::default()
+        };
+        assert!(by_a_tag.matches(&message));
+
+        let by_thread = SubscribeFilter {
+            threads: vec!["t2".to_string()],
+            ..Default::default()
+        };
+        assert!(!by_thread.matches(&message));
+
+        let delegated = Notification {
+            ancestors: vec!["t1".to_string(), "root".to_string()],
+            ..notification(EVENT_MESSAGE, None, Some("child"))
+        };
+        let by_root = SubscribeFilter {
+            threads: vec!["root".to_string()],
+            ..Default::default()
+        };
+        assert!(!by_root.matches(&delegated));
+        let by_tree = SubscribeFilter {
+            descendants: true,
+            ..by_root
+        };
+        assert!(by_tree.matches(&delegated));
+
+        let by_event = SubscribeFilter {
+            events: vec![EVENT_BUNKER_REQUEST.to_string()],
+            ..Default::default()
+        };
+        assert!(!by_event.matches(&message));
+        assert!(by_event.matches(&bunker));
+    }
+
+    #[test]
+    fn subscribe_filter_rejects_unknown_events() {
+        let filter = SubscribeFilter {
+            events: vec!["messages".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.validate().is_err());
+        assert!(SubscribeFilter::default().validate().is_ok());
+    }
 }
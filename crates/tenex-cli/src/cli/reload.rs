@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use super::config::{
+    BunkerAutoApproveRule, CliConfig, ProjectAutoStart, RetentionConfig, DEFAULT_HTTP_BIND,
+    DEFAULT_IRC_BIND,
+};
+
+/// What a reloaded config changes for the running daemon
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct ConfigChanges {
+    pub relays: bool,
+    pub backends: bool,
+    /// Requested bunker state, when the config sets it to a new value
+    pub bunker_enabled: Option<bool>,
+    pub added_rules: Vec<BunkerAutoApproveRule>,
+    pub removed_rules: Vec<BunkerAutoApproveRule>,
+    /// Projects that are new, or that now ask to be booted
+    pub projects: Vec<ProjectAutoStart>,
+}
+
+pub(super) fn diff_config(old: &CliConfig, new: &CliConfig) -> ConfigChanges {
+    let rules = |config: &CliConfig| {
+        config
+            .bunker
+            .as_ref()
+            .map(|bunker| bunker.auto_approve.clone())
+            .unwrap_or_default()
+    };
+    let (old_rules, new_rules) = (rules(old), rules(new));
+    let bunker_enabled = |config: &CliConfig| config.bunker.as_ref().and_then(|b| b.enabled);
+
+    ConfigChanges {
+        relays: old.relay_urls() != new.relay_urls(),
+        backends: old.backends != new.backends,
+        bunker_enabled: bunker_enabled(new).filter(|enabled| bunker_enabled(old) != Some(*enabled)),
+        added_rules: new_rules
+            .iter()
+            .filter(|rule| !old_rules.contains(rule))
+            .cloned()
+            .collect(),
+        removed_rules: old_rules
+            .iter()
+            .filter(|rule| !new_rules.contains(rule))
+            .cloned()
+            .collect(),
+        projects: new
+            .projects
+            .iter()
+            .filter(
+                |project| match old.projects.iter().find(|p| p.slug == project.slug) {
+                    Some(previous) => project.boot && !previous.boot,
+                    None => true,
+                },
+            )
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Address the HTTP server should listen on, or None when it is disabled.
+/// `--http` / `--http-bind` take precedence over the config.
+pub(super) fn http_bind_address(
+    config: &CliConfig,
+    flag_enabled: bool,
+    flag_bind: Option<&str>,
+) -> Option<String> {
+    let http = config.http.clone().unwrap_or_default();
+    if !flag_enabled && !http.enabled {
+        return None;
+    }
+    Some(
+        flag_bind
+            .map(String::from)
+            .or(http.bind)
+            .unwrap_or_else(|| DEFAULT_HTTP_BIND.to_string()),
+    )
+}
+
+/// Address the IRC gateway should listen on, or None when it is disabled
+pub(super) fn irc_bind_address(config: &CliConfig) -> Option<String> {
+    let irc = config.irc.as_ref().filter(|irc| irc.enabled)?;
+    Some(
+        irc.bind
+            .clone()
+            .unwrap_or_else(|| DEFAULT_IRC_BIND.to_string()),
+    )
+}
+
+/// Rotate `path` to `path.1` (shifting older copies up) once it exceeds
+/// the configured size. Returns whether it was rotated.
+pub(super) fn rotate_log(path: &Path, retention: &RetentionConfig) -> std::io::Result<bool> {
+    let Some(max_bytes) = retention.max_log_bytes else {
+        return Ok(false);
+    };
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > max_bytes => {}
+        _ => return Ok(false),
+    }
+
+    let keep = retention.max_log_files.unwrap_or(1);
+    let rotated = |n: usize| path.with_extension(format!("log.{}", n));
+    if keep == 0 {
+        std::fs::remove_file(path)?;
+        return Ok(true);
+    }
+    std::fs::remove_file(rotated(keep)).ok();
+    for n in (1..keep).rev() {
+        std::fs::rename(rotated(n), rotated(n + 1)).ok();
+    }
+    std::fs::rename(path, rotated(1))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::config::{BunkerConfig, HttpConfig, IrcConfig};
+
+    fn project(slug: &str, boot: bool) -> ProjectAutoStart {
+        ProjectAutoStart {
+            slug: slug.to_string(),
+            boot,
+            keep_alive: false,
+        }
+    }
+
+    #[test]
+    fn test_diff_config() {
+        let rule = |pubkey: &str| BunkerAutoApproveRule {
+            requester_pubkey: pubkey.to_string(),
+            event_kind: None,
+        };
+        let old = CliConfig {
+            projects: vec![project("ops", false), project("docs", false)],
+            bunker: Some(BunkerConfig {
+                enabled: Some(true),
+                auto_approve: vec![rule("aa"), rule("bb")],
+            }),
+            ..Default::default()
+        };
+        let new = CliConfig {
+            relays: vec!["wss://relay.example.com".to_string()],
+            projects: vec![
+                project("ops", true),
+                project("docs", false),
+                project("web", false),
+            ],
+            bunker: Some(BunkerConfig {
+                enabled: Some(true),
+                auto_approve: vec![rule("bb"), rule("cc")],
+            }),
+            ..Default::default()
+        };
+
+        let changes = diff_config(&old, &new);
+        assert!(changes.relays);
+        assert!(!changes.backends);
+        assert_eq!(changes.bunker_enabled, None);
+        assert_eq!(changes.added_rules, vec![rule("cc")]);
+        assert_eq!(changes.removed_rules, vec![rule("aa")]);
+        assert_eq!(
+            changes.projects,
+            vec![project("ops", true), project("web", false)]
+        );
+
+        assert_eq!(diff_config(&new, &new), ConfigChanges::default());
+    }
+
+    #[test]
+    fn test_http_bind_address() {
+        let config = CliConfig {
+            http: Some(HttpConfig {
+                enabled: true,
+                bind: Some("0.0.0.0:9090".to_string()),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            http_bind_address(&config, false, None).as_deref(),
+            Some("0.0.0.0:9090")
+        );
+        assert_eq!(
+            http_bind_address(&config, true, Some("127.0.0.1:1")).as_deref(),
+            Some("127.0.0.1:1")
+        );
+        assert_eq!(http_bind_address(&CliConfig::default(), false, None), None);
+        assert_eq!(
+            http_bind_address(&CliConfig::default(), true, None).as_deref(),
+            Some(DEFAULT_HTTP_BIND)
+        );
+    }
+
+    #[test]
+    fn test_irc_bind_address() {
+        let irc = |enabled: bool, bind: Option<&str>| CliConfig {
+            irc: Some(IrcConfig {
+                enabled,
+                bind: bind.map(String::from),
+                password: None,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            irc_bind_address(&irc(true, None)).as_deref(),
+            Some(DEFAULT_IRC_BIND)
+        );
+        assert_eq!(
+            irc_bind_address(&irc(true, Some("0.0.0.0:6697"))).as_deref(),
+            Some("0.0.0.0:6697")
+        );
+        assert_eq!(irc_bind_address(&irc(false, Some("0.0.0.0:6697"))), None);
+        assert_eq!(irc_bind_address(&CliConfig::default()), None);
+    }
+
+    #[test]
+    fn test_rotate_log() {
+        let dir = std::env::temp_dir().join(format!(
+            "tenex-rotate-log-{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("tenex.log");
+        let retention = RetentionConfig {
+            max_log_bytes: Some(4),
+            max_log_files: Some(2),
+        };
+
+        std::fs::write(&log, "abc").unwrap();
+        assert!(!rotate_log(&log, &retention).unwrap());
+
+        std::fs::write(&log, "first").unwrap();
+        assert!(rotate_log(&log, &retention).unwrap());
+        std::fs::write(&log, "second").unwrap();
+        assert!(rotate_log(&log, &retention).unwrap());
+        assert!(!log.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("tenex.log.1")).unwrap(),
+            "second"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("tenex.log.2")).unwrap(),
+            "first"
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// File in the daemon data dir holding the Responses API chains
+const RESPONSE_CHAINS_FILE: &str = "response_chains.json";
+
+/// Oldest entries are dropped beyond this many responses
+const MAX_RESPONSE_CHAINS: usize = 10_000;
+
+/// Where a Responses API response lives in TENEX
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseChainEntry {
+    /// Thread root event ID
+    pub thread_id: String,
+    /// Project coordinate the thread belongs to
+    pub project_a_tag: String,
+    /// Latest event of this turn: the agent's reply when known, otherwise
+    /// the user message published for the request
+    pub last_event_id: String,
+    /// Unix timestamp (seconds) when the response was created
+    pub created_at: u64,
+}
+
+/// Maps `resp_*` IDs to TENEX threads, persisted so that chains survive
+/// daemon restarts
+#[derive(Debug)]
+pub struct ResponseChainStore {
+    path: PathBuf,
+    entries: HashMap<String, ResponseChainEntry>,
+    /// Bumped on every change; snapshots carry it so stale ones are not written
+    generation: u64,
+    /// Generation most recently written to disk
+    written: Arc<Mutex<u64>>,
+}
+
+/// Copy of the chains to write to disk after the store's lock is released
+#[must_use = "call persist() to write the snapshot"]
+pub struct ChainsSnapshot {
+    path: PathBuf,
+    entries: HashMap<String, ResponseChainEntry>,
+    generation: u64,
+    written: Arc<Mutex<u64>>,
+}
+
+impl ChainsSnapshot {
+    /// Write the snapshot on a blocking thread
+    pub async fn persist(self) -> Result<()> {
+        tokio::task::spawn_blocking(move || self.write())
+            .await
+            .context("Response chain writer panicked")?
+    }
+
+    /// Atomically replace the file (temp file + rename), unless a newer
+    /// snapshot was already written
+    fn write(&self) -> Result<()> {
+        let mut written = self.written.lock().unwrap();
+        if *written >= self.generation {
+            return Ok(());
+        }
+        let content =
+            serde_json::to_string(&self.entries).context("Failed to serialize response chains")?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        *written = self.generation;
+        Ok(())
+    }
+}
+
+impl ResponseChainStore {
+    /// Load chains from the data dir (empty if missing or unreadable)
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(RESPONSE_CHAINS_FILE);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to parse {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path,
+            entries,
+            generation: 0,
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    pub fn get(&self, response_id: &str) -> Option<&ResponseChainEntry> {
+        self.entries.get(response_id)
+    }
+
+    /// Record a response. Returns the snapshot to persist once the lock is released.
+    pub fn insert(&mut self, response_id: String, entry: ResponseChainEntry) -> ChainsSnapshot {
+        self.entries.insert(response_id, entry);
+        self.prune();
+        self.snapshot()
+    }
+
+    /// Update the latest event of a recorded response.
+    /// Returns the snapshot to persist when anything changed.
+    pub fn set_last_event(&mut self, response_id: &str, event_id: &str) -> Option<ChainsSnapshot> {
+        match self.entries.get_mut(response_id) {
+            Some(entry) if entry.last_event_id != event_id => {
+                entry.last_event_id = event_id.to_string();
+                Some(self.snapshot())
+            }
+            _ => None,
+        }
+    }
+
+    fn prune(&mut self) {
+        if self.entries.len() <= MAX_RESPONSE_CHAINS {
+            return;
+        }
+
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(id, entry)| (entry.created_at, id.clone()))
+            .collect();
+        by_age.sort();
+        let excess = self.entries.len() - MAX_RESPONSE_CHAINS;
+        for (_, id) in by_age.into_iter().take(excess) {
+            self.entries.remove(&id);
+        }
+    }
+
+    fn snapshot(&mut self) -> ChainsSnapshot {
+        self.generation += 1;
+        ChainsSnapshot {
+            path: self.path.clone(),
+            entries: self.entries.clone(),
+            generation: self.generation,
+            written: self.written.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(created_at: u64) -> ResponseChainEntry {
+        ResponseChainEntry {
+            thread_id: "thread".to_string(),
+            project_a_tag: "31933:pubkey:project".to_string(),
+            last_event_id: "event".to_string(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_chains_survive_reload() {
+        let dir = std::env::temp_dir().join(format!(
+            "tenex-response-chains-{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut store = ResponseChainStore::load(&dir);
+        assert!(store.get("resp_1").is_none());
+        let inserted = store.insert("resp_1".to_string(), entry(1));
+        let updated = store.set_last_event("resp_1", "reply").unwrap();
+        assert!(store.set_last_event("resp_1", "reply").is_none());
+
+        // A snapshot older than one already written is skipped
+        updated.write().unwrap();
+        inserted.write().unwrap();
+
+        let reloaded = ResponseChainStore::load(&dir);
+        let stored = reloaded.get("resp_1").unwrap();
+        assert_eq!(stored.thread_id, "thread");
+        assert_eq!(stored.last_event_id, "reply");
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_prune_drops_oldest() {
+        let mut store = ResponseChainStore {
+            path: PathBuf::new(),
+            entries: HashMap::new(),
+            generation: 0,
+            written: Arc::new(Mutex::new(0)),
+        };
+        for i in 0..=MAX_RESPONSE_CHAINS as u64 {
+            store.entries.insert(format!("resp_{}", i), entry(i));
+        }
+        store.prune();
+        assert_eq!(store.entries.len(), MAX_RESPONSE_CHAINS);
+        assert!(store.get("resp_0").is_none());
+        assert!(store.get("resp_1").is_some());
+    }
+}
@@ -17,3 +17,144 @@ pub(super) fn project_has_available_agent(store: &AppDataStore, project: &Projec
 pub(super) fn default_agent_pubkey(project: &Project) -> Option<String> {
     project.agent_pubkeys.first().cloned()
 }
+
+/// Find a roster agent by slug (agent name) within the project.
+pub(super) fn find_roster_agent(
+    store: &AppDataStore,
+    project: &Project,
+    slug: &str,
+) -> Option<ProjectAgent> {
+    project_roster_agents(store, project)
+        .into_iter()
+        .find(|agent| agent.name == slug)
+}
+
+/// A single change to a project's ordered agent list (kind:31933 p-tags).
+/// The first agent is the project manager.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum RosterEdit {
+    /// Insert at `position` (default: end of the list)
+    Add {
+        position: Option<usize>,
+    },
+    Remove,
+    SetPm,
+    Move {
+        position: usize,
+    },
+}
+
+impl RosterEdit {
+    pub(super) fn parse(action: &str, position: Option<usize>) -> Result<Self, String> {
+        match action {
+            "add" => Ok(RosterEdit::Add { position }),
+            "remove" => Ok(RosterEdit::Remove),
+            "set_pm" => Ok(RosterEdit::SetPm),
+            "move" => position
+                .map(|position| RosterEdit::Move { position })
+                .ok_or_else(|| "move requires position".to_string()),
+            other => Err(format!(
+                "Unknown action '{}' (expected add, remove, set_pm or move)",
+                other
+            )),
+        }
+    }
+}
+
+/// Apply one edit to the current agent order, keeping every other agent in place.
+/// Removing the PM while other agents remain is rejected so the PM never
+/// changes by accident; use `SetPm` first.
+pub(super) fn apply_roster_edit(
+    agents: &[String],
+    agent_pubkey: &str,
+    edit: &RosterEdit,
+) -> Result<Vec<String>, String> {
+    let mut updated = agents.to_vec();
+    let index = updated.iter().position(|pk| pk == agent_pubkey);
+
+    match (edit, index) {
+        (RosterEdit::Add { .. }, Some(_)) => {
+            return Err("Agent is already in the project".to_string());
+        }
+        (RosterEdit::Add { position }, None) => {
+            let position = position.unwrap_or(updated.len()).min(updated.len());
+            updated.insert(position, agent_pubkey.to_string());
+        }
+        (_, None) => return Err("Agent is not in the project".to_string()),
+        (RosterEdit::Remove, Some(0)) if updated.len() > 1 => {
+            return Err(
+                "Agent is the project manager; set another PM before removing it".to_string(),
+            );
+        }
+        (RosterEdit::Remove, Some(index)) => {
+            updated.remove(index);
+        }
+        (RosterEdit::SetPm, Some(index)) => {
+            let pubkey = updated.remove(index);
+            updated.insert(0, pubkey);
+        }
+        (RosterEdit::Move { position }, Some(index)) => {
+            let pubkey = updated.remove(index);
+            let position = (*position).min(updated.len());
+            updated.insert(position, pubkey);
+        }
+    }
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roster(pubkeys: &[&str]) -> Vec<String> {
+        pubkeys.iter().map(|pk| pk.to_string()).collect()
+    }
+
+    #[test]
+    fn add_keeps_existing_order() {
+        let agents = roster(&["pm", "coder"]);
+        let edit = RosterEdit::Add { position: None };
+        assert_eq!(
+            apply_roster_edit(&agents, "tester", &edit).unwrap(),
+            roster(&["pm", "coder", "tester"])
+        );
+        let edit = RosterEdit::Add { position: Some(1) };
+        assert_eq!(
+            apply_roster_edit(&agents, "tester", &edit).unwrap(),
+            roster(&["pm", "tester", "coder"])
+        );
+        assert!(apply_roster_edit(&agents, "coder", &edit).is_err());
+    }
+
+    #[test]
+    fn remove_never_demotes_pm_silently() {
+        let agents = roster(&["pm", "coder"]);
+        assert!(apply_roster_edit(&agents, "pm", &RosterEdit::Remove).is_err());
+        assert_eq!(
+            apply_roster_edit(&agents, "coder", &RosterEdit::Remove).unwrap(),
+            roster(&["pm"])
+        );
+        assert!(apply_roster_edit(&agents, "tester", &RosterEdit::Remove).is_err());
+        assert!(
+            apply_roster_edit(&roster(&["pm"]), "pm", &RosterEdit::Remove)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn set_pm_and_move() {
+        let agents = roster(&["pm", "coder", "tester"]);
+        assert_eq!(
+            apply_roster_edit(&agents, "tester", &RosterEdit::SetPm).unwrap(),
+            roster(&["tester", "pm", "coder"])
+        );
+        assert_eq!(
+            apply_roster_edit(&agents, "pm", &RosterEdit::Move { position: 9 }).unwrap(),
+            roster(&["coder", "tester", "pm"])
+        );
+        assert!(RosterEdit::parse("move", None).is_err());
+        assert!(RosterEdit::parse("promote", None).is_err());
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+
+const SCHEDULES_FILE: &str = "schedules.json";
+/// Runs kept per job
+const MAX_RUN_HISTORY: usize = 50;
+/// How far ahead `next_run` looks for a matching time
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week)
+/// with `*`, lists, ranges and steps, or @hourly/@daily/@weekly/@monthly.
+/// Day-of-week runs 0-6 from Sunday (7 is also Sunday).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Whether day-of-month / day-of-week are something other than `*`.
+    /// When both are restricted, either one matching is enough.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "Invalid cron expression '{}' (expected 5 fields: minute hour day month weekday)",
+                expr
+            ));
+        };
+
+        let weekdays = parse_field(weekday, 0, 7, "weekday")?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")? as u32,
+            days: parse_field(day, 1, 31, "day")? as u32,
+            months: parse_field(month, 1, 12, "month")? as u16,
+            // Fold 7 onto Sunday
+            weekdays: ((weekdays | (weekdays >> 7)) & 0x7f) as u8,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        bit(self.minutes, time.minute())
+            && bit(self.hours as u64, time.hour())
+            && self.matches_day(&time.date())
+    }
+
+    fn matches_day(&self, date: &NaiveDate) -> bool {
+        if !bit(self.months as u64, date.month()) {
+            return false;
+        }
+
+        let day = bit(self.days as u64, date.day());
+        let weekday = bit(self.weekdays as u64, date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// First matching minute strictly after `time`
+    pub fn next_after(&self, time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = time.with_second(0)?.with_nanosecond(0)? + chrono::Duration::minutes(1);
+        let mut date = start.date();
+        let last = date + chrono::Duration::days(MAX_LOOKAHEAD_DAYS);
+        while date <= last {
+            if !self.matches_day(&date) {
+                date = date.succ_opt()?;
+                continue;
+            }
+            for hour in 0..24 {
+                for minute in 0..60 {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate >= start && self.matches(&candidate) {
+                        return Some(candidate);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Parse one cron field into a bitmask of allowed values
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid cron {} field '{}'", name, field);
+    let number = |s: &str| -> Result<u32, String> {
+        let n: u32 = s.parse().map_err(|_| invalid())?;
+        if n < min || n > max {
+            return Err(format!(
+                "Cron {} {} is out of range ({}-{})",
+                name, n, min, max
+            ));
+        }
+        Ok(n)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (number(start)?, number(end)?),
+                // "5/15" means every 15 starting at 5
+                None if part.contains('/') => (number(range)?, max),
+                None => {
+                    let n = number(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// One firing of a job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    /// Unix timestamp (seconds)
+    pub at: u64,
+    /// Thread the prompt went to, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JobRun {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "at": self.at,
+            "thread_id": self.thread_id,
+            "error": self.error,
+        })
+    }
+}
+
+/// A recurring prompt sent by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledJob {
+    /// Short identifier used to pause and remove the job
+    pub id: String,
+    pub name: String,
+    /// Cron expression, evaluated in the daemon's local time
+    pub cron: String,
+    /// Project slug (d-tag)
+    pub project: String,
+    /// Agent slug; the project's PM when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// Title of the threads created (default: the job name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub content: String,
+    /// Post into this thread instead of starting a new one each run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skill_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nudge_ids: Vec<String>,
+    #[serde(default)]
+    pub paused: bool,
+    pub created_at: u64,
+    /// Most recent runs, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<JobRun>,
+}
+
+impl ScheduledJob {
+    /// JSON summary for listings; `next_run` is a Unix timestamp
+    pub fn to_json<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> serde_json::Value {
+        let next_run = if self.paused {
+            None
+        } else {
+            CronSchedule::parse(&self.cron)
+                .ok()
+                .and_then(|cron| cron.next_after(&now.naive_local()))
+                .and_then(|next| now.timezone().from_local_datetime(&next).earliest())
+                .map(|next| next.timestamp())
+        };
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "cron": self.cron,
+            "project": self.project,
+            "agent": self.agent,
+            "title": self.title,
+            "content": self.content,
+            "thread_id": self.thread_id,
+            "skill_ids": self.skill_ids,
+            "nudge_ids": self.nudge_ids,
+            "paused": self.paused,
+            "created_at": self.created_at,
+            "next_run": next_run,
+            "last_run": self.runs.last().map(JobRun::to_json),
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SchedulesFile {
+    #[serde(default)]
+    jobs: Vec<ScheduledJob>,
+}
+
+/// Scheduled jobs persisted in `schedules.json` in the data directory
+#[derive(Debug)]
+pub struct ScheduleRegistry {
+    path: PathBuf,
+    jobs: Vec<ScheduledJob>,
+    /// IDs of jobs whose run is being published (not persisted)
+    running: HashSet<String>,
+}
+
+impl ScheduleRegistry {
+    /// Load jobs from data_dir/schedules.json (empty if missing or unreadable)
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(SCHEDULES_FILE);
+        let jobs = if path.exists() {
+            match std::fs::read_to_string(&path)
+                .context("Failed to read schedules")
+                .and_then(|content| {
+                    serde_json::from_str::<SchedulesFile>(&content)
+                        .context("Failed to parse schedules")
+                }) {
+                Ok(file) => file.jobs,
+                Err(e) => {
+                    eprintln!("Warning: {:#}", e);
+                    Vec::new()
+                }
+            }
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            path,
+            jobs,
+            running: HashSet::new(),
+        }
+    }
+
+    pub fn jobs(&self) -> &[ScheduledJob] {
+        &self.jobs
+    }
+
+    pub fn find(&self, id_or_name: &str) -> Option<&ScheduledJob> {
+        self.jobs
+            .iter()
+            .find(|job| job.id == id_or_name || job.name == id_or_name)
+    }
+
+    /// Validate and persist a new job. `id` and `created_at` are assigned here.
+    pub fn add(&mut self, mut job: ScheduledJob) -> Result<ScheduledJob> {
+        job.name = job.name.trim().to_string();
+        if job.name.is_empty() {
+            anyhow::bail!("Job name cannot be empty");
+        }
+        if job.content.trim().is_empty() {
+            anyhow::bail!("Job content cannot be empty");
+        }
+        if self.jobs.iter().any(|existing| existing.name == job.name) {
+            anyhow::bail!("A job named '{}' already exists", job.name);
+        }
+        CronSchedule::parse(&job.cron).map_err(anyhow::Error::msg)?;
+
+        job.id = format!("job_{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+        job.created_at = now_secs();
+        job.runs.clear();
+
+        self.jobs.push(job.clone());
+        if let Err(e) = self.persist() {
+            self.jobs.pop();
+            return Err(e);
+        }
+        Ok(job)
+    }
+
+    /// Pause or resume a job. Returns the updated job, if found.
+    pub fn set_paused(&mut self, id_or_name: &str, paused: bool) -> Result<Option<ScheduledJob>> {
+        let Some(job) = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id_or_name || job.name == id_or_name)
+        else {
+            return Ok(None);
+        };
+        let previous = job.paused;
+        job.paused = paused;
+        let job = job.clone();
+
+        if let Err(e) = self.persist() {
+            if let Some(stored) = self.jobs.iter_mut().find(|j| j.id == job.id) {
+                stored.paused = previous;
+            }
+            return Err(e);
+        }
+        Ok(Some(job))
+    }
+
+    /// Remove a job by id or name. Returns the removed job, if any.
+    pub fn remove(&mut self, id_or_name: &str) -> Result<Option<ScheduledJob>> {
+        let Some(index) = self
+            .jobs
+            .iter()
+            .position(|job| job.id == id_or_name || job.name == id_or_name)
+        else {
+            return Ok(None);
+        };
+
+        let job = self.jobs.remove(index);
+        if let Err(e) = self.persist() {
+            self.jobs.insert(index, job);
+            return Err(e);
+        }
+        Ok(Some(job))
+    }
+
+    /// Jobs that should fire at `now`: not paused, matching the cron
+    /// expression, not running and not already run in this minute
+    pub fn due_jobs<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Vec<ScheduledJob> {
+        let minute = now.timestamp().max(0) as u64 / 60;
+        self.jobs
+            .iter()
+            .filter(|job| !job.paused && !self.running.contains(&job.id))
+            .filter(|job| job.runs.last().is_none_or(|run| run.at / 60 != minute))
+            .filter(|job| {
+                CronSchedule::parse(&job.cron)
+                    .map(|cron| cron.matches(&now.naive_local()))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Mark a job as running until its run is recorded, so it is not due again meanwhile
+    pub fn mark_running(&mut self, job_id: &str) {
+        self.running.insert(job_id.to_string());
+    }
+
+    /// Append a run to the job's history and persist it
+    pub fn record_run(&mut self, job_id: &str, run: JobRun) -> Result<()> {
+        self.running.remove(job_id);
+        let Some(job) = self.jobs.iter_mut().find(|job| job.id == job_id) else {
+            return Ok(());
+        };
+        job.runs.push(run);
+        if job.runs.len() > MAX_RUN_HISTORY {
+            job.runs.remove(0);
+        }
+        self.persist()
+    }
+
+    fn persist(&self) -> Result<()> {
+        let file = SchedulesFile {
+            jobs: self.jobs.clone(),
+        };
+        let content =
+            serde_json::to_string_pretty(&file).context("Failed to serialize schedules")?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn job(name: &str, cron: &str) -> ScheduledJob {
+        ScheduledJob {
+            id: String::new(),
+            name: name.to_string(),
+            cron: cron.to_string(),
+            project: "ops".to_string(),
+            agent: Some("planner".to_string()),
+            title: None,
+            content: "Standup".to_string(),
+            thread_id: None,
+            skill_ids: vec![],
+            nudge_ids: vec![],
+            paused: false,
+            created_at: 0,
+            runs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_cron_matches() {
+        // Weekdays at 09:30 (2024-01-01 was a Monday)
+        let cron = CronSchedule::parse("30 9 * * 1-5").unwrap();
+        assert!(cron.matches(&at(2024, 1, 1, 9, 30)));
+        assert!(!cron.matches(&at(2024, 1, 1, 9, 31)));
+        assert!(!cron.matches(&at(2024, 1, 6, 9, 30)));
+
+        let cron = CronSchedule::parse("*/15 8-10 * * *").unwrap();
+        assert!(cron.matches(&at(2024, 1, 1, 10, 45)));
+        assert!(!cron.matches(&at(2024, 1, 1, 11, 0)));
+
+        // Sunday as 7, and day-of-month OR day-of-week
+        let cron = CronSchedule::parse("0 0 15 * 7").unwrap();
+        assert!(cron.matches(&at(2024, 1, 7, 0, 0)));
+        assert!(cron.matches(&at(2024, 1, 15, 0, 0)));
+        assert!(!cron.matches(&at(2024, 1, 16, 0, 0)));
+
+        assert_eq!(
+            CronSchedule::parse("@weekly").unwrap(),
+            CronSchedule::parse("0 0 * * 0").unwrap()
+        );
+        for bad in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(CronSchedule::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let cron = CronSchedule::parse("0 9 * * 1").unwrap();
+        assert_eq!(
+            cron.next_after(&at(2024, 1, 1, 9, 0)),
+            Some(at(2024, 1, 8, 9, 0))
+        );
+        let cron = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            cron.next_after(&at(2024, 3, 1, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_registry_lifecycle() {
+        let dir =
+            std::env::temp_dir().join(format!("tenex-schedules-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut registry = ScheduleRegistry::load(&dir);
+
+        let standup = registry.add(job("standup", "30 9 * * *")).unwrap();
+        assert!(standup.id.starts_with("job_"));
+        assert!(registry.add(job("standup", "0 9 * * *")).is_err());
+        assert!(registry.add(job("bad", "every day")).is_err());
+
+        let now = Utc.from_utc_datetime(&at(2024, 1, 1, 9, 30));
+        assert_eq!(registry.due_jobs(&now).len(), 1);
+        registry.mark_running(&standup.id);
+        assert!(registry.due_jobs(&now).is_empty());
+        let run = JobRun {
+            at: now.timestamp() as u64 + 20,
+            thread_id: Some("t1".to_string()),
+            error: None,
+        };
+        registry.record_run(&standup.id, run.clone()).unwrap();
+        assert!(registry.due_jobs(&now).is_empty());
+
+        registry.set_paused("standup", true).unwrap().unwrap();
+        let tomorrow = Utc.from_utc_datetime(&at(2024, 1, 2, 9, 30));
+        assert!(registry.due_jobs(&tomorrow).is_empty());
+
+        // Jobs and history survive a reload from disk
+        let reloaded = ScheduleRegistry::load(&dir);
+        let stored = reloaded.find(&standup.id).unwrap();
+        assert!(stored.paused);
+        assert_eq!(stored.runs, vec![run]);
+
+        assert!(registry.remove("standup").unwrap().is_some());
+        assert!(registry.remove(&standup.id).unwrap().is_none());
+        assert!(ScheduleRegistry::load(&dir).jobs().is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,380 @@
+use std::io::Write;
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+
+use tokio::sync::{broadcast, oneshot};
+
+use crate::nostr::DataChange;
+use crate::store::AppDataStore;
+use tenex_core::events::CoreEvent;
+use tenex_core::models::{InboxEventType, InboxItem, Message, OperationsStatus, ProjectStatus};
+use tenex_core::nostr::bunker::BunkerSignRequest;
+
+use super::daemon::{message_to_json, resolve_author_name};
+use super::protocol::{
+    Notification, SubscribeFilter, EVENT_BUNKER_REQUEST, EVENT_INBOX, EVENT_MESSAGE,
+    EVENT_OPERATIONS_STATUS, EVENT_PROJECT_STATUS, EVENT_STREAM_DELTA,
+};
+
+/// Notifications buffered per subscriber before it is reported as lagging
+const SUBSCRIPTION_BUFFER: usize = 1024;
+
+/// Fans daemon events out to `subscribe` connections on the Unix socket.
+/// Each subscriber gets its own thread, so a slow or idle client never
+/// blocks the daemon's main loop.
+#[derive(Clone)]
+pub struct SubscriptionHub {
+    tx: broadcast::Sender<Notification>,
+}
+
+impl Default for SubscriptionHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubscriptionHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(SUBSCRIPTION_BUFFER);
+        Self { tx }
+    }
+
+    /// Skip building notifications when nobody is listening
+    pub fn has_subscribers(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
+    fn publish(
+        &self,
+        event: &str,
+        project: Option<String>,
+        thread_id: Option<String>,
+        data: serde_json::Value,
+    ) {
+        self.publish_in_tree(event, project, thread_id, vec![], data);
+    }
+
+    fn publish_in_tree(
+        &self,
+        event: &str,
+        project: Option<String>,
+        thread_id: Option<String>,
+        ancestors: Vec<String>,
+        data: serde_json::Value,
+    ) {
+        // No receivers is fine - the subscriber may have just disconnected
+        let _ = self.tx.send(Notification {
+            id: 0,
+            event: event.to_string(),
+            project,
+            thread_id,
+            ancestors,
+            data,
+        });
+    }
+
+    /// Receive every notification, e.g. for the IRC gateway
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.tx.subscribe()
+    }
+
+    /// Stream notifications matching `filter` to the connection until it closes.
+    /// Lines carry the `subscribe` request ID.
+    pub fn serve(&self, id: u64, filter: SubscribeFilter, writer: UnixStream) {
+        let rx = self.tx.subscribe();
+        std::thread::spawn(move || {
+            // Clients send nothing after `subscribe`, so a read only returns
+            // once they hang up. That ends the subscription even while no
+            // notifications arrive to fail a write.
+            let (closed_tx, closed_rx) = oneshot::channel();
+            match writer.try_clone() {
+                Ok(mut reader) => {
+                    std::thread::spawn(move || {
+                        let _ = std::io::copy(&mut reader, &mut std::io::sink());
+                        let _ = closed_tx.send(());
+                    });
+                }
+                Err(_) => return,
+            }
+
+            futures::executor::block_on(forward(id, &filter, rx, &writer, closed_rx));
+            // Unblocks the reader thread if the hub or a write ended the subscription
+            let _ = writer.shutdown(Shutdown::Both);
+        });
+    }
+
+    /// Publish notifications for a DataChange. Call after the daemon has
+    /// applied it to the store so that derived state (e.g. online) is current.
+    pub fn on_data_change(&self, store: &AppDataStore, change: &DataChange) {
+        match change {
+            DataChange::StreamTextDelta {
+                agent_pubkey,
+                conversation_id,
+                text_delta,
+                reasoning_delta,
+                is_finish,
+            } => self.publish_in_tree(
+                EVENT_STREAM_DELTA,
+                store.get_project_a_tag_for_thread(conversation_id),
+                Some(conversation_id.clone()),
+                store.runtime_hierarchy.get_ancestors(conversation_id),
+                serde_json::json!({
+                    "agent_pubkey": agent_pubkey,
+                    "author_name": resolve_author_name(store, agent_pubkey),
+                    "text_delta": text_delta,
+                    "reasoning_delta": reasoning_delta,
+                    "is_finish": is_finish,
+                }),
+            ),
+            DataChange::ProjectStatus { json } => {
+                let Ok(event) = serde_json::from_str::<serde_json::Value>(json) else {
+                    return;
+                };
+                match event["kind"].as_u64() {
+                    Some(24010) => self.on_project_status(store, &event),
+                    Some(24133) => {
+                        if let Some(status) = OperationsStatus::from_value(&event) {
+                            self.on_operations_status(store, &status);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            DataChange::BunkerSignRequest { request } => self.on_bunker_request(request),
+            _ => {}
+        }
+    }
+
+    /// Publish notifications for events processed from nostrdb
+    pub fn on_core_events(&self, store: &AppDataStore, events: &[CoreEvent]) {
+        for event in events {
+            if let CoreEvent::Message(message) = event {
+                self.on_message(store, message);
+            }
+        }
+    }
+
+    fn on_message(&self, store: &AppDataStore, message: &Message) {
+        let project = store.get_project_a_tag_for_thread(&message.thread_id);
+        let mut data = message_to_json(store, message);
+        data["thread_id"] = serde_json::json!(message.thread_id);
+        self.publish_in_tree(
+            EVENT_MESSAGE,
+            project.clone(),
+            Some(message.thread_id.clone()),
+            store.runtime_hierarchy.get_ancestors(&message.thread_id),
+            data,
+        );
+
+        // Messages that p-tag the user land in the inbox while they are processed
+        if let Some(item) = store
+            .inbox
+            .get_items()
+            .iter()
+            .find(|item| item.id == message.id && !item.is_read)
+        {
+            self.publish(
+                EVENT_INBOX,
+                project.or_else(|| Some(item.project_a_tag.clone()).filter(|a| !a.is_empty())),
+                item.thread_id.clone(),
+                inbox_item_to_json(item),
+            );
+        }
+    }
+
+    fn on_project_status(&self, store: &AppDataStore, event: &serde_json::Value) {
+        let Some(status) = ProjectStatus::from_value(event) else {
+            return;
+        };
+
+        let online = store.is_project_online(&status.project_coordinate);
+        self.publish(
+            EVENT_PROJECT_STATUS,
+            Some(status.project_coordinate.clone()),
+            None,
+            serde_json::json!({
+                "online": online,
+                "backend_pubkey": status.backend_pubkey,
+                "created_at": status.created_at,
+            }),
+        );
+    }
+
+    fn on_operations_status(&self, store: &AppDataStore, status: &OperationsStatus) {
+        // No receivers is fine - the subscriber may have just disconnected
+        let _ = self.tx.send(operations_status_notification(store, status));
+    }
+
+    fn on_bunker_request(&self, request: &BunkerSignRequest) {
+        self.publish(
+            EVENT_BUNKER_REQUEST,
+            None,
+            None,
+            serde_json::json!({
+                "request_id": request.request_id,
+                "requester_pubkey": request.requester_pubkey,
+                "event_kind": request.event_kind,
+                "event_content": request.event_content,
+            }),
+        );
+    }
+}
+
+/// Notification for a kind:24133 operations status, keyed by the
+/// conversation it reports on
+pub(super) fn operations_status_notification(
+    store: &AppDataStore,
+    status: &OperationsStatus,
+) -> Notification {
+    let conversation_id = status.thread_id.as_deref().unwrap_or(&status.event_id);
+    Notification {
+        id: 0,
+        event: EVENT_OPERATIONS_STATUS.to_string(),
+        project: Some(status.project_coordinate.clone()),
+        thread_id: Some(conversation_id.to_string()),
+        ancestors: store.runtime_hierarchy.get_ancestors(conversation_id),
+        data: serde_json::json!({
+            "event_id": status.event_id,
+            "agent_pubkeys": status.agent_pubkeys,
+            "active": !status.agent_pubkeys.is_empty(),
+            "llm_runtime_secs": status.llm_runtime_secs,
+            "created_at": status.created_at,
+        }),
+    }
+}
+
+/// Write matching notifications until the hub shuts down, a write fails or
+/// `closed` resolves
+async fn forward(
+    id: u64,
+    filter: &SubscribeFilter,
+    mut rx: broadcast::Receiver<Notification>,
+    mut writer: &UnixStream,
+    mut closed: oneshot::Receiver<()>,
+) {
+    loop {
+        let received = tokio::select! {
+            biased;
+            _ = &mut closed => return,
+            received = rx.recv() => received,
+        };
+        let mut notification = match received {
+            Ok(notification) => notification,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Notification {
+                id,
+                event: "lagged".to_string(),
+                project: None,
+                thread_id: None,
+                ancestors: vec![],
+                data: serde_json::json!({ "skipped": skipped }),
+            },
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if notification.event != "lagged" && !filter.matches(&notification) {
+            continue;
+        }
+        notification.id = id;
+
+        let Ok(line) = serde_json::to_string(&notification) else {
+            continue;
+        };
+        if writeln!(writer, "{}", line).is_err() || writer.flush().is_err() {
+            return;
+        }
+    }
+}
+
+pub(super) fn inbox_item_to_json(item: &InboxItem) -> serde_json::Value {
+    serde_json::json!({
+        "id": item.id,
+        "type": match item.event_type {
+            InboxEventType::Ask => "ask",
+            InboxEventType::Mention => "mention",
+        },
+        "title": item.title,
+        "content": item.content,
+        "author_pubkey": item.author_pubkey,
+        "created_at": item.created_at,
+        "thread_id": item.thread_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::{Duration, Instant};
+
+    fn subscriber(
+        hub: &SubscriptionHub,
+        id: u64,
+        filter: SubscribeFilter,
+    ) -> BufReader<UnixStream> {
+        let (client, server) = UnixStream::pair().unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        hub.serve(id, filter, server);
+        BufReader::new(client)
+    }
+
+    fn next_notification(reader: &mut BufReader<UnixStream>) -> Notification {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    fn publish_message(hub: &SubscriptionHub, thread_id: &str) {
+        hub.publish(
+            EVENT_MESSAGE,
+            None,
+            Some(thread_id.to_string()),
+            serde_json::json!({ "content": thread_id }),
+        );
+    }
+
+    #[test]
+    fn test_subscription_fan_out_and_filtering() {
+        let hub = SubscriptionHub::new();
+        let mut all = subscriber(&hub, 1, SubscribeFilter::default());
+        let mut one_thread = subscriber(
+            &hub,
+            2,
+            SubscribeFilter {
+                threads: vec!["t2".to_string()],
+                ..Default::default()
+            },
+        );
+
+        publish_message(&hub, "t1");
+        publish_message(&hub, "t2");
+
+        let first = next_notification(&mut all);
+        assert_eq!((first.id, first.thread_id.as_deref()), (1, Some("t1")));
+        assert_eq!(next_notification(&mut all).thread_id.as_deref(), Some("t2"));
+        // t1 was filtered out, so t2 is the first line this subscriber sees
+        let filtered = next_notification(&mut one_thread);
+        assert_eq!(
+            (filtered.id, filtered.thread_id.as_deref()),
+            (2, Some("t2"))
+        );
+    }
+
+    #[test]
+    fn test_subscription_ends_when_client_disconnects() {
+        let hub = SubscriptionHub::new();
+        let client = subscriber(&hub, 1, SubscribeFilter::default());
+        assert!(hub.has_subscribers());
+
+        // No notification is published, so only the hang-up can end it
+        drop(client);
+        let start = Instant::now();
+        while hub.has_subscribers() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "subscriber thread still running after disconnect"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
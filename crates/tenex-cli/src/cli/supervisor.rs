@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::Serialize;
+
+/// Wait after the first boot of a stale project; doubles with each attempt
+const INITIAL_BACKOFF_SECS: u64 = 30;
+const MAX_BACKOFF_SECS: u64 = 600;
+/// Boot attempts kept per project for `status`
+const MAX_BOOT_HISTORY: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(super) struct BootAttempt {
+    pub at: u64,
+    /// How long the supervisor waits before the next attempt
+    pub backoff_secs: u64,
+}
+
+#[derive(Debug, Default)]
+struct WatchedProject {
+    /// None until the first check
+    online: Option<bool>,
+    online_since: Option<u64>,
+    offline_since: Option<u64>,
+    /// Time spent online in completed spans
+    uptime_secs: u64,
+    /// Boots sent since the project was last online
+    failed_boots: u32,
+    next_boot_at: u64,
+    attempts: VecDeque<BootAttempt>,
+}
+
+/// Keep-alive supervisor: tracks the online state of configured projects
+/// and decides when a stale one should be booted again.
+#[derive(Debug, Default)]
+pub(super) struct KeepAlive {
+    projects: BTreeMap<String, WatchedProject>,
+}
+
+impl KeepAlive {
+    /// Watch exactly `slugs`, keeping the history of projects already watched
+    pub fn set_projects(&mut self, slugs: impl IntoIterator<Item = String>) {
+        let mut projects = BTreeMap::new();
+        for slug in slugs {
+            let watched = self.projects.remove(&slug).unwrap_or_default();
+            projects.insert(slug, watched);
+        }
+        self.projects = projects;
+    }
+
+    pub fn slugs(&self) -> Vec<String> {
+        self.projects.keys().cloned().collect()
+    }
+
+    /// Record whether `slug` is online at `now` and log transitions.
+    /// Returns true when the project is offline and a boot is due.
+    pub fn observe(&mut self, slug: &str, online: bool, now: u64) -> bool {
+        let Some(project) = self.projects.get_mut(slug) else {
+            return false;
+        };
+
+        if project.online != Some(online) {
+            if online {
+                eprintln!("Keep-alive: project '{}' is online", slug);
+                project.online_since = Some(now);
+                project.offline_since = None;
+                project.failed_boots = 0;
+                project.next_boot_at = 0;
+            } else {
+                eprintln!("Keep-alive: project '{}' is offline", slug);
+                if let Some(since) = project.online_since.take() {
+                    project.uptime_secs += now.saturating_sub(since);
+                }
+                project.offline_since = Some(now);
+            }
+            project.online = Some(online);
+        }
+
+        !online && now >= project.next_boot_at
+    }
+
+    /// Record a boot sent at `now` and schedule the next one with backoff
+    pub fn record_boot(&mut self, slug: &str, now: u64) -> u32 {
+        let Some(project) = self.projects.get_mut(slug) else {
+            return 0;
+        };
+
+        let backoff_secs = INITIAL_BACKOFF_SECS
+            .saturating_mul(1 << project.failed_boots.min(16))
+            .min(MAX_BACKOFF_SECS);
+        project.failed_boots += 1;
+        project.next_boot_at = now + backoff_secs;
+        project.attempts.push_back(BootAttempt {
+            at: now,
+            backoff_secs,
+        });
+        if project.attempts.len() > MAX_BOOT_HISTORY {
+            project.attempts.pop_front();
+        }
+        project.failed_boots
+    }
+
+    /// Per-project state for the `status` command
+    pub fn to_json(&self, now: u64) -> serde_json::Value {
+        let projects: Vec<serde_json::Value> = self
+            .projects
+            .iter()
+            .map(|(slug, project)| {
+                let current_span = project
+                    .online_since
+                    .map(|since| now.saturating_sub(since))
+                    .unwrap_or(0);
+                serde_json::json!({
+                    "slug": slug,
+                    "online": project.online,
+                    "online_since": project.online_since,
+                    "offline_since": project.offline_since,
+                    "uptime_secs": project.uptime_secs + current_span,
+                    "next_boot_at": (project.online == Some(false)).then_some(project.next_boot_at),
+                    "boot_attempts": project.attempts,
+                })
+            })
+            .collect();
+        serde_json::json!(projects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supervisor() -> KeepAlive {
+        let mut keep_alive = KeepAlive::default();
+        keep_alive.set_projects(["ops".to_string()]);
+        keep_alive
+    }
+
+    #[test]
+    fn test_keep_alive_backoff() {
+        let mut keep_alive = supervisor();
+
+        assert!(keep_alive.observe("ops", false, 1000));
+        assert_eq!(keep_alive.record_boot("ops", 1000), 1);
+        assert!(!keep_alive.observe("ops", false, 1010));
+        assert!(keep_alive.observe("ops", false, 1030));
+        keep_alive.record_boot("ops", 1030);
+        assert!(!keep_alive.observe("ops", false, 1060));
+        assert!(keep_alive.observe("ops", false, 1090));
+
+        // Coming back online resets the backoff
+        assert!(!keep_alive.observe("ops", true, 1100));
+        assert!(keep_alive.observe("ops", false, 1200));
+        keep_alive.record_boot("ops", 1200);
+        let status = keep_alive.to_json(1200);
+        assert_eq!(status[0]["next_boot_at"], 1230);
+        assert_eq!(status[0]["boot_attempts"].as_array().unwrap().len(), 3);
+
+        assert!(!keep_alive.observe("other", false, 1200));
+    }
+
+    #[test]
+    fn test_keep_alive_uptime() {
+        let mut keep_alive = supervisor();
+        keep_alive.observe("ops", true, 100);
+        keep_alive.observe("ops", false, 160);
+        keep_alive.observe("ops", true, 200);
+
+        let status = keep_alive.to_json(250);
+        assert_eq!(status[0]["uptime_secs"], 110);
+        assert_eq!(status[0]["online_since"], 200);
+        assert!(status[0]["next_boot_at"].is_null());
+
+        keep_alive.set_projects(["ops".to_string(), "docs".to_string()]);
+        let status = keep_alive.to_json(250);
+        assert_eq!(status[1]["slug"], "ops");
+        assert_eq!(status[1]["uptime_secs"], 110);
+        assert!(status[0]["online"].is_null());
+    }
+}
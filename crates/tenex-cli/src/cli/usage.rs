@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tenex_core::models::Message;
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// How `usage` rows are grouped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageGroup {
+    #[default]
+    Project,
+    Agent,
+    Model,
+    Day,
+}
+
+impl UsageGroup {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "project" => Ok(Self::Project),
+            "agent" => Ok(Self::Agent),
+            "model" => Ok(Self::Model),
+            "day" => Ok(Self::Day),
+            other => Err(format!(
+                "Unknown group '{}' (expected project, agent, model or day)",
+                other
+            )),
+        }
+    }
+}
+
+/// How the client prints `usage` results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsageFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+impl UsageFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "Unknown format '{}' (expected table, csv or json)",
+                other
+            )),
+        }
+    }
+}
+
+/// LLM usage of one agent message, from its llm-* tags
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct UsageRecord {
+    pub project_a_tag: String,
+    pub agent_pubkey: String,
+    pub model: Option<String>,
+    pub created_at: u64,
+    pub cost_usd: f64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub runtime_ms: u64,
+}
+
+impl UsageRecord {
+    /// None for messages without LLM metadata (e.g. user messages)
+    pub fn from_message(project_a_tag: &str, message: &Message) -> Option<Self> {
+        let metadata = &message.llm_metadata;
+        if metadata.is_empty() {
+            return None;
+        }
+        let number = |key: &str| metadata.get(key).and_then(|v| v.parse::<u64>().ok());
+
+        Some(Self {
+            project_a_tag: project_a_tag.to_string(),
+            agent_pubkey: message.pubkey.clone(),
+            model: metadata.get("model").cloned(),
+            created_at: message.created_at,
+            cost_usd: metadata
+                .get("cost-usd")
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(0.0),
+            prompt_tokens: number("prompt-tokens").unwrap_or(0),
+            completion_tokens: number("completion-tokens").unwrap_or(0),
+            runtime_ms: number("runtime").unwrap_or(0),
+        })
+    }
+}
+
+/// Usage totals for one group
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageRow {
+    /// Project a-tag, agent pubkey, model or YYYY-MM-DD (UTC)
+    pub key: String,
+    /// Display name for the key
+    pub name: String,
+    pub cost_usd: f64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub runtime_ms: u64,
+    pub messages: u64,
+}
+
+impl UsageRow {
+    fn add(&mut self, record: &UsageRecord) {
+        self.cost_usd += record.cost_usd;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        self.runtime_ms += record.runtime_ms;
+        self.messages += 1;
+    }
+}
+
+/// Group records into rows plus a total. Days are in chronological order,
+/// other groups by cost descending.
+pub(super) fn aggregate_usage(
+    records: impl IntoIterator<Item = UsageRecord>,
+    group: UsageGroup,
+    name_of: impl Fn(&str) -> Option<String>,
+) -> (Vec<UsageRow>, UsageRow) {
+    let mut total = UsageRow {
+        key: "total".to_string(),
+        name: "Total".to_string(),
+        ..Default::default()
+    };
+    let mut rows: HashMap<String, UsageRow> = HashMap::new();
+
+    for record in records {
+        let key = match group {
+            UsageGroup::Project => record.project_a_tag.clone(),
+            UsageGroup::Agent => record.agent_pubkey.clone(),
+            UsageGroup::Model => record
+                .model
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            UsageGroup::Day => format_day(record.created_at),
+        };
+        total.add(&record);
+        rows.entry(key.clone())
+            .or_insert_with(|| UsageRow {
+                name: match group {
+                    UsageGroup::Project | UsageGroup::Agent => {
+                        name_of(&key).unwrap_or_else(|| key.clone())
+                    }
+                    UsageGroup::Model | UsageGroup::Day => key.clone(),
+                },
+                key,
+                ..Default::default()
+            })
+            .add(&record);
+    }
+
+    let mut rows: Vec<UsageRow> = rows.into_values().collect();
+    match group {
+        UsageGroup::Day => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+        _ => rows.sort_by(|a, b| {
+            b.cost_usd
+                .partial_cmp(&a.cost_usd)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+    }
+    (rows, total)
+}
+
+/// UTC calendar day of a timestamp
+fn format_day(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp((timestamp / SECONDS_PER_DAY * SECONDS_PER_DAY) as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Parse a `--since`/`--until` date (YYYY-MM-DD, UTC) or month (YYYY-MM)
+/// into the timestamp range it covers
+pub fn parse_date_range(value: &str) -> Result<(u64, u64), String> {
+    let parse = |s: &str| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+    let (start, end) = if let Some(day) = parse(value) {
+        (day, day.succ_opt())
+    } else if let Some(month) = parse(&format!("{}-01", value)) {
+        (month, month.checked_add_months(chrono::Months::new(1)))
+    } else {
+        return Err(format!(
+            "Invalid date '{}' (expected YYYY-MM-DD or YYYY-MM)",
+            value
+        ));
+    };
+
+    let timestamp = |date: chrono::NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc().timestamp().max(0) as u64)
+            .unwrap_or(0)
+    };
+    Ok((timestamp(start), end.map(timestamp).unwrap_or(u64::MAX)))
+}
+
+fn row_fields(row: &UsageRow) -> [String; 7] {
+    [
+        row.key.clone(),
+        row.name.clone(),
+        format!("{:.4}", row.cost_usd),
+        row.prompt_tokens.to_string(),
+        row.completion_tokens.to_string(),
+        format!("{:.1}", row.runtime_ms as f64 / 1000.0),
+        row.messages.to_string(),
+    ]
+}
+
+const USAGE_COLUMNS: [&str; 7] = [
+    "key",
+    "name",
+    "cost_usd",
+    "prompt_tokens",
+    "completion_tokens",
+    "runtime_secs",
+    "messages",
+];
+
+/// CSV with a header row and a trailing total row
+pub fn format_usage_csv(rows: &[UsageRow], total: &UsageRow) -> String {
+    let escape = |field: &str| {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+
+    let mut out = USAGE_COLUMNS.join(",");
+    out.push('\n');
+    for row in rows.iter().chain(std::iter::once(total)) {
+        let fields: Vec<String> = row_fields(row).iter().map(|f| escape(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Aligned text table; the key column is left out since names are shown
+pub fn format_usage_table(rows: &[UsageRow], total: &UsageRow) -> String {
+    let lines: Vec<Vec<String>> = std::iter::once(
+        USAGE_COLUMNS[1..]
+            .iter()
+            .map(|c| c.to_uppercase())
+            .collect(),
+    )
+    .chain(
+        rows.iter()
+            .chain(std::iter::once(total))
+            .map(|row| row_fields(row)[1..].to_vec()),
+    )
+    .collect();
+
+    let mut widths = [0usize; 6];
+    for line in &lines {
+        for (width, field) in widths.iter_mut().zip(line) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for line in &lines {
+        let mut cells: Vec<String> = Vec::new();
+        for (i, field) in line.iter().enumerate() {
+            // Name is left-aligned, numbers right-aligned
+            if i == 0 {
+                cells.push(format!("{:<width$}", field, width = widths[i]));
+            } else {
+                cells.push(format!("{:>width$}", field, width = widths[i]));
+            }
+        }
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(project: &str, model: Option<&str>, created_at: u64, cost: f64) -> UsageRecord {
+        UsageRecord {
+            project_a_tag: project.to_string(),
+            agent_pubkey: "agent".to_string(),
+            model: model.map(String::from),
+            created_at,
+            cost_usd: cost,
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            runtime_ms: 1500,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_usage_groups() {
+        let records = vec![
+            record("31933:pk:a", Some("opus"), 86400, 0.5),
+            record("31933:pk:b", Some("sonnet"), 86400 * 2 + 5, 1.0),
+            record("31933:pk:a", None, 86400 * 2 + 10, 0.25),
+        ];
+        let names = |key: &str| (key == "31933:pk:a").then(|| "Alpha".to_string());
+
+        let (rows, total) = aggregate_usage(records.clone(), UsageGroup::Project, names);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].key, "31933:pk:b");
+        assert_eq!(rows[0].name, "31933:pk:b");
+        assert_eq!(rows[1].name, "Alpha");
+        assert_eq!(rows[1].messages, 2);
+        assert_eq!(rows[1].prompt_tokens, 200);
+        assert_eq!(total.cost_usd, 1.75);
+        assert_eq!(total.runtime_ms, 4500);
+
+        let (rows, _) = aggregate_usage(records.clone(), UsageGroup::Model, |_| None);
+        assert!(rows.iter().any(|r| r.key == "unknown"));
+
+        let (rows, _) = aggregate_usage(records, UsageGroup::Day, |_| None);
+        let days: Vec<&str> = rows.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(days, vec!["1970-01-02", "1970-01-03"]);
+    }
+
+    #[test]
+    fn test_parse_date_range() {
+        assert_eq!(parse_date_range("1970-01-02"), Ok((86400, 86400 * 2)));
+        assert_eq!(parse_date_range("1970-02"), Ok((86400 * 31, 86400 * 59)));
+        assert!(parse_date_range("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_format_usage_csv() {
+        let row = UsageRow {
+            key: "31933:pk:a".to_string(),
+            name: "Alpha, Inc".to_string(),
+            cost_usd: 0.5,
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            runtime_ms: 1500,
+            messages: 1,
+        };
+        let total = UsageRow {
+            key: "total".to_string(),
+            name: "Total".to_string(),
+            ..row.clone()
+        };
+        assert_eq!(
+            format_usage_csv(&[row], &total),
+            "key,name,cost_usd,prompt_tokens,completion_tokens,runtime_secs,messages\n\
+             31933:pk:a,\"Alpha, Inc\",0.5000,100,10,1.5,1\n\
+             total,Total,0.5000,100,10,1.5,1\n"
+        );
+    }
+}
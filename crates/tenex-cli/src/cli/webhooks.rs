@@ -0,0 +1,557 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::nostr::DataChange;
+use crate::store::AppDataStore;
+use tenex_core::events::{CoreEvent, PendingBackendApproval};
+use tenex_core::models::{Message, OperationsStatus};
+use tenex_core::nostr::bunker::BunkerSignRequest;
+
+use super::asks::ask_to_json;
+use super::config::{WebhookConfig, WebhookEvent, MAX_WEBHOOK_ATTEMPTS};
+use super::daemon::{report_to_json, resolve_author_name};
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry; doubles with each attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// Longest wait between two attempts
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Deliveries kept for `webhook-log`
+const MAX_DELIVERY_LOG: usize = 200;
+
+/// Body POSTed to a webhook
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct WebhookPayload {
+    /// Same for every hook the event is sent to
+    pub id: String,
+    pub event: WebhookEvent,
+    /// Project a-tag, when the event belongs to a project
+    pub project: Option<String>,
+    pub created_at: u64,
+    pub data: serde_json::Value,
+}
+
+/// Outcome of delivering one payload to one hook
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct DeliveryRecord {
+    pub delivery_id: String,
+    pub hook: String,
+    pub event: WebhookEvent,
+    pub project: Option<String>,
+    pub delivered: bool,
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub finished_at: u64,
+}
+
+/// Recent deliveries, oldest first
+pub(super) type DeliveryLog = Arc<Mutex<VecDeque<DeliveryRecord>>>;
+
+/// Turns daemon events into webhook payloads and delivers them in the
+/// background, retrying failed deliveries with backoff.
+pub(super) struct WebhookDispatcher {
+    hooks: Vec<WebhookConfig>,
+    client: reqwest::Client,
+    log: DeliveryLog,
+    /// Events created before the daemon started are history, not news
+    since: u64,
+    /// Active operation event IDs per conversation
+    active_operations: HashMap<String, HashSet<String>>,
+    /// (backend, project) approvals already sent
+    announced_backends: HashSet<(String, String)>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(hooks: Vec<WebhookConfig>) -> Self {
+        Self {
+            hooks,
+            client: reqwest::Client::new(),
+            log: DeliveryLog::default(),
+            since: now_secs(),
+            active_operations: HashMap::new(),
+            announced_backends: HashSet::new(),
+        }
+    }
+
+    pub fn set_hooks(&mut self, hooks: Vec<WebhookConfig>) {
+        self.hooks = hooks;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.hooks.is_empty()
+    }
+
+    pub fn log(&self) -> &DeliveryLog {
+        &self.log
+    }
+
+    /// Send webhooks for a DataChange. Call after the daemon has applied it
+    /// to the store.
+    pub fn on_data_change(&mut self, store: &AppDataStore, change: &DataChange) {
+        match change {
+            DataChange::ProjectStatus { json } => {
+                let Ok(event) = serde_json::from_str::<serde_json::Value>(json) else {
+                    return;
+                };
+                match event["kind"].as_u64() {
+                    Some(24010) => {
+                        let pending = store.trust.pending_backend_approvals.clone();
+                        for approval in &pending {
+                            self.on_backend_approval(approval);
+                        }
+                    }
+                    Some(24133) => {
+                        if let Some(status) = OperationsStatus::from_value(&event) {
+                            self.on_operations_status(&status);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            DataChange::BunkerSignRequest { request } => self.on_bunker_request(request),
+            _ => {}
+        }
+    }
+
+    /// Send webhooks for events processed from nostrdb
+    pub fn on_core_events(&mut self, store: &AppDataStore, events: &[CoreEvent]) {
+        for event in events {
+            match event {
+                CoreEvent::Message(message) => self.on_message(store, message),
+                CoreEvent::ReportUpsert(report) if report.created_at >= self.since => {
+                    self.dispatch(
+                        WebhookEvent::Report,
+                        Some(report.project_a_tag.clone()),
+                        report_to_json(store, report, false),
+                    );
+                }
+                CoreEvent::PendingBackendApproval(approval) => self.on_backend_approval(approval),
+                _ => {}
+            }
+        }
+    }
+
+    fn on_message(&self, store: &AppDataStore, message: &Message) {
+        let Some(ask) = &message.ask_event else {
+            return;
+        };
+        if message.created_at < self.since
+            || store.user_pubkey.as_deref() == Some(message.pubkey.as_str())
+        {
+            return;
+        }
+
+        let mut data = ask_to_json(ask);
+        data["ask_id"] = serde_json::json!(message.id);
+        data["thread_id"] = serde_json::json!(message.thread_id);
+        data["author"] = serde_json::json!(message.pubkey);
+        data["author_name"] = serde_json::json!(resolve_author_name(store, &message.pubkey));
+        self.dispatch(
+            WebhookEvent::Ask,
+            store.get_project_a_tag_for_thread(&message.thread_id),
+            data,
+        );
+    }
+
+    fn on_operations_status(&mut self, status: &OperationsStatus) {
+        let conversation_id = status.thread_id.as_deref().unwrap_or(&status.event_id);
+        if !self.track_operations(conversation_id, &status.event_id, &status.agent_pubkeys) {
+            return;
+        }
+        self.dispatch(
+            WebhookEvent::ConversationFinished,
+            Some(status.project_coordinate.clone()),
+            serde_json::json!({
+                "conversation_id": conversation_id,
+                "event_id": status.event_id,
+                "llm_runtime_secs": status.llm_runtime_secs,
+            }),
+        );
+    }
+
+    /// Record an operations status. Returns true when the conversation had
+    /// active operations and now has none.
+    fn track_operations(
+        &mut self,
+        conversation_id: &str,
+        event_id: &str,
+        agent_pubkeys: &[String],
+    ) -> bool {
+        if !agent_pubkeys.is_empty() {
+            self.active_operations
+                .entry(conversation_id.to_string())
+                .or_default()
+                .insert(event_id.to_string());
+            return false;
+        }
+
+        let Some(active) = self.active_operations.get_mut(conversation_id) else {
+            return false;
+        };
+        active.remove(event_id);
+        if !active.is_empty() {
+            return false;
+        }
+        self.active_operations.remove(conversation_id);
+        true
+    }
+
+    fn on_backend_approval(&mut self, approval: &PendingBackendApproval) {
+        let key = (
+            approval.backend_pubkey.clone(),
+            approval.project_a_tag.clone(),
+        );
+        if !self.announced_backends.insert(key) {
+            return;
+        }
+        self.dispatch(
+            WebhookEvent::BackendApproval,
+            Some(approval.project_a_tag.clone()),
+            serde_json::json!({
+                "backend_pubkey": approval.backend_pubkey,
+                "project_a_tag": approval.project_a_tag,
+                "first_seen": approval.first_seen,
+            }),
+        );
+    }
+
+    fn on_bunker_request(&self, request: &BunkerSignRequest) {
+        self.dispatch(
+            WebhookEvent::BunkerRequest,
+            None,
+            serde_json::json!({
+                "request_id": request.request_id,
+                "requester_pubkey": request.requester_pubkey,
+                "event_kind": request.event_kind,
+                "event_content": request.event_content,
+            }),
+        );
+    }
+
+    fn dispatch(&self, event: WebhookEvent, project: Option<String>, data: serde_json::Value) {
+        let hooks: Vec<&WebhookConfig> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook_matches(hook, event, project.as_deref()))
+            .collect();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let payload = Arc::new(WebhookPayload {
+            id: uuid::Uuid::new_v4().to_string(),
+            event,
+            project,
+            created_at: now_secs(),
+            data,
+        });
+        for hook in hooks {
+            let client = self.client.clone();
+            let hook = hook.clone();
+            let payload = payload.clone();
+            let log = self.log.clone();
+            tokio::spawn(async move {
+                let record = deliver(&client, &hook, &payload, RETRY_BASE_DELAY).await;
+                if !record.delivered {
+                    eprintln!(
+                        "Webhook '{}' failed after {} attempts: {}",
+                        record.hook,
+                        record.attempts,
+                        record.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+                let mut log = log.lock().unwrap();
+                log.push_back(record);
+                if log.len() > MAX_DELIVERY_LOG {
+                    log.pop_front();
+                }
+            });
+        }
+    }
+}
+
+/// Whether `hook` wants `event`. Project filters match on the slug of the
+/// event's project; events without a project only match unfiltered hooks.
+fn hook_matches(hook: &WebhookConfig, event: WebhookEvent, project: Option<&str>) -> bool {
+    if !hook.events.is_empty() && !hook.events.contains(&event) {
+        return false;
+    }
+    if hook.projects.is_empty() {
+        return true;
+    }
+    project
+        .and_then(|a_tag| a_tag.splitn(3, ':').nth(2))
+        .is_some_and(|slug| hook.projects.iter().any(|p| p == slug))
+}
+
+/// Wait before retry number `retry` (0-based): the base delay doubled per
+/// retry, capped at [`MAX_RETRY_DELAY`]
+fn retry_delay(base: Duration, retry: u32) -> Duration {
+    2u32.checked_pow(retry)
+        .map_or(MAX_RETRY_DELAY, |factor| base.saturating_mul(factor))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Hex HMAC-SHA256 of the body, sent as `X-Tenex-Signature: sha256=<hex>`
+pub(super) fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// POST `payload` to `hook`, retrying network errors, 429s and 5xx
+/// responses with exponential backoff
+async fn deliver(
+    client: &reqwest::Client,
+    hook: &WebhookConfig,
+    payload: &WebhookPayload,
+    retry_base_delay: Duration,
+) -> DeliveryRecord {
+    let body = serde_json::to_string(payload).unwrap_or_default();
+    let max_attempts = hook
+        .max_attempts
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+        .clamp(1, MAX_WEBHOOK_ATTEMPTS);
+    let mut record = DeliveryRecord {
+        delivery_id: payload.id.clone(),
+        hook: hook.name.clone(),
+        event: payload.event,
+        project: payload.project.clone(),
+        delivered: false,
+        attempts: 0,
+        status_code: None,
+        error: None,
+        finished_at: 0,
+    };
+
+    while record.attempts < max_attempts {
+        if record.attempts > 0 {
+            tokio::time::sleep(retry_delay(retry_base_delay, record.attempts - 1)).await;
+        }
+        record.attempts += 1;
+
+        let mut request = client
+            .post(&hook.url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Tenex-Event", payload.event.as_str())
+            .header("X-Tenex-Delivery", &payload.id);
+        if let Some(secret) = &hook.secret {
+            request = request.header(
+                "X-Tenex-Signature",
+                format!("sha256={}", sign_payload(secret, body.as_bytes())),
+            );
+        }
+
+        let retry = match request.body(body.clone()).send().await {
+            Ok(response) => {
+                let status = response.status();
+                record.status_code = Some(status.as_u16());
+                if status.is_success() {
+                    record.delivered = true;
+                    record.error = None;
+                    break;
+                }
+                record.error = Some(format!("HTTP {}", status));
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                record.status_code = None;
+                record.error = Some(e.to_string());
+                true
+            }
+        };
+        if !retry {
+            break;
+        }
+    }
+
+    record.finished_at = now_secs();
+    record
+}
+
+/// Deliveries newest first, optionally for one hook
+pub(super) fn delivery_log_to_json(
+    log: &DeliveryLog,
+    hook: Option<&str>,
+    limit: usize,
+) -> serde_json::Value {
+    let log = log.lock().unwrap();
+    let deliveries: Vec<&DeliveryRecord> = log
+        .iter()
+        .rev()
+        .filter(|record| hook.is_none_or(|name| record.hook == name))
+        .take(limit)
+        .collect();
+    serde_json::json!(deliveries)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+
+    fn hook(url: &str) -> WebhookConfig {
+        WebhookConfig {
+            name: "ci".to_string(),
+            url: url.to_string(),
+            secret: Some("s3cret".to_string()),
+            events: vec![],
+            projects: vec![],
+            max_attempts: Some(3),
+        }
+    }
+
+    fn payload() -> WebhookPayload {
+        WebhookPayload {
+            id: "d1".to_string(),
+            event: WebhookEvent::Ask,
+            project: Some("31933:abc:ops".to_string()),
+            created_at: 1700000000,
+            data: serde_json::json!({ "title": "Setup" }),
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let base = Duration::from_secs(2);
+        assert_eq!(retry_delay(base, 0), base);
+        assert_eq!(retry_delay(base, 3), Duration::from_secs(16));
+        assert_eq!(retry_delay(base, 9), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(base, 40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_hook_matches() {
+        let mut hook = hook("http://127.0.0.1:1");
+        assert!(hook_matches(&hook, WebhookEvent::BunkerRequest, None));
+
+        hook.events = vec![WebhookEvent::Ask];
+        hook.projects = vec!["ops".to_string()];
+        assert!(hook_matches(
+            &hook,
+            WebhookEvent::Ask,
+            Some("31933:abc:ops")
+        ));
+        assert!(!hook_matches(
+            &hook,
+            WebhookEvent::Ask,
+            Some("31933:abc:docs")
+        ));
+        assert!(!hook_matches(
+            &hook,
+            WebhookEvent::Report,
+            Some("31933:abc:ops")
+        ));
+        assert!(!hook_matches(&hook, WebhookEvent::Ask, None));
+    }
+
+    #[test]
+    fn test_track_operations() {
+        let mut dispatcher = WebhookDispatcher::new(vec![]);
+        let agents = vec!["agent".to_string()];
+
+        assert!(!dispatcher.track_operations("conv", "e1", &[]));
+        assert!(!dispatcher.track_operations("conv", "e1", &agents));
+        assert!(!dispatcher.track_operations("conv", "e2", &agents));
+        assert!(!dispatcher.track_operations("conv", "e1", &[]));
+        assert!(dispatcher.track_operations("conv", "e2", &[]));
+        assert!(!dispatcher.track_operations("conv", "e2", &[]));
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries_and_signs() {
+        // Local stand-in that fails the first request
+        let received: Arc<Mutex<Vec<(HeaderMap, String)>>> = Arc::default();
+        let received_by_server = received.clone();
+        let app = axum::Router::new().route(
+            "/hook",
+            post(move |headers: HeaderMap, body: String| {
+                let received = received_by_server.clone();
+                async move {
+                    let mut received = received.lock().unwrap();
+                    received.push((headers, body));
+                    if received.len() == 1 {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let record = deliver(
+            &reqwest::Client::new(),
+            &hook(&url),
+            &payload(),
+            Duration::from_millis(10),
+        )
+        .await;
+        assert!(record.delivered);
+        assert_eq!(record.attempts, 2);
+        assert_eq!(record.status_code, Some(200));
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[1];
+        assert_eq!(headers["x-tenex-event"], "ask");
+        assert_eq!(headers["x-tenex-delivery"], "d1");
+        assert_eq!(
+            headers["x-tenex-signature"].to_str().unwrap(),
+            format!("sha256={}", sign_payload("s3cret", body.as_bytes()))
+        );
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["project"], "31933:abc:ops");
+        assert_eq!(body["data"]["title"], "Setup");
+    }
+
+    #[tokio::test]
+    async fn test_deliver_gives_up() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let record = deliver(
+            &reqwest::Client::new(),
+            &hook(&url),
+            &payload(),
+            Duration::from_millis(1),
+        )
+        .await;
+        assert!(!record.delivered);
+        assert_eq!(record.attempts, 3);
+        assert!(record.error.is_some());
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
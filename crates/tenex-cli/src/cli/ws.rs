@@ -0,0 +1,585 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::api_tokens::ApiToken;
+use super::asks::{ask_to_json, format_ask_answers};
+use super::daemon::{message_to_json, resolve_author_name};
+use super::http::{
+    authorize_agent, authorize_request, await_event_id, create_thread, openai_error_response,
+    publish_reply, resolve_project, ApiError, HTTPServerState, OpenAIError,
+};
+use super::protocol::AskAnswer;
+use super::roster::{default_agent_pubkey, find_roster_agent};
+use crate::nostr::{DataChange, NostrCommand};
+use crate::store::AppDataStore;
+use tenex_core::models::{Message, OperationsStatus, Project};
+
+/// How often the store is checked for new messages in the session's thread
+const WS_POLL_INTERVAL_MS: u64 = 250;
+
+/// Query of `GET /:project_dtag/ws`
+#[derive(Debug, Deserialize)]
+pub(super) struct WsQuery {
+    /// Continue an existing thread instead of starting one with the first message
+    #[serde(default)]
+    thread_id: Option<String>,
+    /// API token, for clients that cannot set headers on a WebSocket handshake
+    #[serde(default)]
+    token: Option<String>,
+}
+
+/// Frames sent by the client, as JSON text
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// A user turn. The first one starts the session's thread unless the
+    /// session was opened on an existing thread.
+    Message {
+        content: String,
+        /// Agent slug (default: the project manager)
+        #[serde(default)]
+        agent: Option<String>,
+    },
+    AnswerAsk {
+        ask_id: String,
+        answers: Vec<AskAnswer>,
+    },
+    Ping,
+}
+
+/// Frames sent by the server, as JSON text. `thread_id` is the conversation
+/// the event belongs to: the session's thread or one delegated from it.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Ready {
+        project: String,
+        thread_id: Option<String>,
+    },
+    /// A user turn or ask answer was published
+    Sent {
+        thread_id: String,
+        event_id: String,
+    },
+    Message {
+        thread_id: String,
+        message: serde_json::Value,
+    },
+    StreamDelta {
+        thread_id: String,
+        agent_pubkey: String,
+        author_name: Option<String>,
+        text_delta: Option<String>,
+        reasoning_delta: Option<String>,
+        is_finish: bool,
+    },
+    OperationsStatus {
+        thread_id: String,
+        agent_pubkeys: Vec<String>,
+        active: bool,
+    },
+    Ask {
+        thread_id: String,
+        ask_id: String,
+        author_pubkey: String,
+        ask: serde_json::Value,
+    },
+    Pong,
+    Error {
+        code: String,
+        message: String,
+    },
+}
+
+impl ServerMessage {
+    fn error(code: &str, message: impl Into<String>) -> Self {
+        Self::Error {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn from_api_error((_, error): ApiError) -> Self {
+        let body = error.0.error;
+        Self::Error {
+            code: body.code.unwrap_or(body.error_type),
+            message: body.message,
+        }
+    }
+}
+
+/// State of one WebSocket session: its thread and the messages already sent
+struct LiveSession {
+    thread_id: Option<String>,
+    /// Message IDs already sent or predating the session
+    seen: HashSet<String>,
+    /// Latest event in the thread; the next user turn replies to it
+    last_event_id: Option<String>,
+}
+
+impl LiveSession {
+    fn new(thread_id: Option<String>) -> Self {
+        Self {
+            last_event_id: thread_id.clone(),
+            thread_id,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Whether a conversation is the session's thread or delegated from it
+    fn in_scope(&self, conversation_id: &str, ancestors: &[String]) -> bool {
+        self.thread_id.as_deref().is_some_and(|thread_id| {
+            conversation_id == thread_id || ancestors.iter().any(|a| a == thread_id)
+        })
+    }
+
+    fn conversations(&self, store: &AppDataStore) -> Vec<String> {
+        let Some(thread_id) = &self.thread_id else {
+            return vec![];
+        };
+        let mut conversations = vec![thread_id.clone()];
+        conversations.extend(store.runtime_hierarchy.get_descendants(thread_id));
+        conversations
+    }
+
+    /// Record existing messages so they are not sent as new
+    fn mark_seen(&mut self, store: &AppDataStore) {
+        for conversation_id in self.conversations(store) {
+            let messages = store.get_messages(&conversation_id);
+            if Some(&conversation_id) == self.thread_id.as_ref() {
+                if let Some(last) = messages.last() {
+                    self.last_event_id = Some(last.id.clone());
+                }
+            }
+            self.seen.extend(messages.iter().map(|m| m.id.clone()));
+        }
+    }
+
+    /// New messages in the thread tree, plus an `ask` for each ask event.
+    /// The user's own messages are skipped since the client sent them.
+    fn poll_messages(&mut self, store: &AppDataStore) -> Vec<ServerMessage> {
+        let user_pubkey = store.user_pubkey.as_deref();
+        let mut out = Vec::new();
+        for conversation_id in self.conversations(store) {
+            for message in store.get_messages(&conversation_id) {
+                if !self.seen.insert(message.id.clone()) {
+                    continue;
+                }
+                if Some(&conversation_id) == self.thread_id.as_ref() {
+                    self.last_event_id = Some(message.id.clone());
+                }
+                if Some(message.pubkey.as_str()) == user_pubkey {
+                    continue;
+                }
+                out.extend(message_events(store, &conversation_id, message));
+            }
+        }
+        out
+    }
+
+    fn on_data_change(&self, store: &AppDataStore, change: &DataChange) -> Vec<ServerMessage> {
+        match change {
+            DataChange::StreamTextDelta {
+                agent_pubkey,
+                conversation_id,
+                text_delta,
+                reasoning_delta,
+                is_finish,
+            } if self.in_scope(
+                conversation_id,
+                &store.runtime_hierarchy.get_ancestors(conversation_id),
+            ) =>
+            {
+                vec![ServerMessage::StreamDelta {
+                    thread_id: conversation_id.clone(),
+                    agent_pubkey: agent_pubkey.clone(),
+                    author_name: resolve_author_name(store, agent_pubkey),
+                    text_delta: text_delta.clone(),
+                    reasoning_delta: reasoning_delta.clone(),
+                    is_finish: *is_finish,
+                }]
+            }
+            DataChange::ProjectStatus { json } => {
+                let status = serde_json::from_str::<serde_json::Value>(json)
+                    .ok()
+                    .filter(|event| event["kind"].as_u64() == Some(24133))
+                    .and_then(|event| OperationsStatus::from_value(&event));
+                let Some(status) = status else {
+                    return vec![];
+                };
+                let conversation_id = status.thread_id.as_deref().unwrap_or(&status.event_id);
+                if !self.in_scope(
+                    conversation_id,
+                    &store.runtime_hierarchy.get_ancestors(conversation_id),
+                ) {
+                    return vec![];
+                }
+                vec![ServerMessage::OperationsStatus {
+                    thread_id: conversation_id.to_string(),
+                    active: !status.agent_pubkeys.is_empty(),
+                    agent_pubkeys: status.agent_pubkeys,
+                }]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+fn message_events(
+    store: &AppDataStore,
+    conversation_id: &str,
+    message: &Message,
+) -> Vec<ServerMessage> {
+    let mut out = vec![ServerMessage::Message {
+        thread_id: conversation_id.to_string(),
+        message: message_to_json(store, message),
+    }];
+    if let Some(ask) = &message.ask_event {
+        out.push(ServerMessage::Ask {
+            thread_id: conversation_id.to_string(),
+            ask_id: message.id.clone(),
+            author_pubkey: message.pubkey.clone(),
+            ask: ask_to_json(ask),
+        });
+    }
+    out
+}
+
+/// Handler for GET /:project_dtag/ws
+pub(super) async fn ws_handler(
+    Path(project_dtag): Path<String>,
+    Query(query): Query<WsQuery>,
+    State(state): State<HTTPServerState>,
+    mut headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    if let Some(value) = query
+        .token
+        .as_ref()
+        .and_then(|token| HeaderValue::from_str(&format!("Bearer {}", token)).ok())
+    {
+        headers.insert(header::AUTHORIZATION, value);
+    }
+    let token = authorize_request(&state, &headers, &project_dtag)?;
+
+    let project = resolve_project(&state, &project_dtag).await.map_err(|e| {
+        openai_error_response(
+            StatusCode::NOT_FOUND,
+            OpenAIError::not_found(format!("Project not found: {}", e)),
+        )
+    })?;
+    if let Some(thread_id) = &query.thread_id {
+        let thread_project = state
+            .data_store
+            .lock()
+            .unwrap()
+            .get_project_a_tag_for_thread(thread_id);
+        if thread_project.as_deref() != Some(project.a_tag().as_str()) {
+            return Err(openai_error_response(
+                StatusCode::NOT_FOUND,
+                OpenAIError::not_found(format!(
+                    "Thread '{}' not found in project '{}'",
+                    thread_id, project_dtag
+                )),
+            ));
+        }
+    }
+
+    Ok(ws.on_upgrade(move |socket| run_session(socket, state, project, token, query.thread_id)))
+}
+
+async fn run_session(
+    mut socket: WebSocket,
+    state: HTTPServerState,
+    project: Project,
+    token: Option<ApiToken>,
+    thread_id: Option<String>,
+) {
+    let mut data_rx = state.data_tx.subscribe();
+    let mut session = LiveSession::new(thread_id);
+    session.mark_seen(&state.data_store.lock().unwrap());
+    let mut poll = tokio::time::interval(tokio::time::Duration::from_millis(WS_POLL_INTERVAL_MS));
+
+    let ready = ServerMessage::Ready {
+        project: project.id.clone(),
+        thread_id: session.thread_id.clone(),
+    };
+    if send(&mut socket, &ready).await.is_err() {
+        return;
+    }
+
+    loop {
+        let outgoing = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(WsMessage::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(message) => {
+                            let token = token.as_ref();
+                            handle_client_message(&state, &project, token, &mut session, message)
+                                .await
+                        }
+                        Err(e) => {
+                            vec![ServerMessage::error("invalid_request_error", e.to_string())]
+                        }
+                    }
+                }
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames are not part of the protocol
+                Some(Ok(_)) => continue,
+            },
+            change = data_rx.recv() => match change {
+                Ok(change) => {
+                    let store = state.data_store.lock().unwrap();
+                    session.on_data_change(&store, &change)
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = poll.tick() => {
+                let store = state.data_store.lock().unwrap();
+                session.poll_messages(&store)
+            }
+        };
+
+        for message in &outgoing {
+            if send(&mut socket, message).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(WsMessage::Text(text)).await
+}
+
+async fn handle_client_message(
+    state: &HTTPServerState,
+    project: &Project,
+    token: Option<&ApiToken>,
+    session: &mut LiveSession,
+    message: ClientMessage,
+) -> Vec<ServerMessage> {
+    let result = match message {
+        ClientMessage::Ping => return vec![ServerMessage::Pong],
+        ClientMessage::Message { content, agent } => {
+            send_turn(state, project, token, session, &content, agent.as_deref()).await
+        }
+        ClientMessage::AnswerAsk { ask_id, answers } => {
+            answer_ask(state, project, token, session, &ask_id, &answers).await
+        }
+    };
+    vec![result.unwrap_or_else(|error| error)]
+}
+
+/// Publish a user turn: a new thread on the first turn, a reply after that
+async fn send_turn(
+    state: &HTTPServerState,
+    project: &Project,
+    token: Option<&ApiToken>,
+    session: &mut LiveSession,
+    content: &str,
+    agent: Option<&str>,
+) -> Result<ServerMessage, ServerMessage> {
+    if content.trim().is_empty() {
+        return Err(ServerMessage::error(
+            "invalid_request_error",
+            "content cannot be empty",
+        ));
+    }
+    let agent_pubkey = match agent {
+        Some(slug) => {
+            let store = state.data_store.lock().unwrap();
+            find_roster_agent(&store, project, slug)
+                .map(|agent| agent.pubkey)
+                .ok_or_else(|| {
+                    ServerMessage::error("not_found_error", format!("Agent '{}' not found", slug))
+                })?
+        }
+        None => default_agent_pubkey(project)
+            .ok_or_else(|| ServerMessage::error("server_error", "Project has no roster agents"))?,
+    };
+    authorize_agent(state, project, token, &agent_pubkey).map_err(ServerMessage::from_api_error)?;
+
+    let project_a_tag = project.a_tag();
+    let (thread_id, event_id) = match (&session.thread_id, &session.last_event_id) {
+        (Some(thread_id), Some(last_event_id)) => {
+            let event_id = publish_reply(
+                state,
+                thread_id,
+                last_event_id,
+                &project_a_tag,
+                &agent_pubkey,
+                content,
+            )
+            .await
+            .map_err(ServerMessage::from_api_error)?;
+            (thread_id.clone(), event_id)
+        }
+        _ => {
+            let thread_id = create_thread(state, &project_a_tag, &agent_pubkey, content, None)
+                .await
+                .map_err(ServerMessage::from_api_error)?;
+            session.thread_id = Some(thread_id.clone());
+            (thread_id.clone(), thread_id)
+        }
+    };
+    session.seen.insert(event_id.clone());
+    session.last_event_id = Some(event_id.clone());
+    Ok(ServerMessage::Sent {
+        thread_id,
+        event_id,
+    })
+}
+
+/// Answer an ask raised in the session's thread or a conversation delegated
+/// from it. The token must be allowed to talk to the ask's author.
+async fn answer_ask(
+    state: &HTTPServerState,
+    project: &Project,
+    token: Option<&ApiToken>,
+    session: &LiveSession,
+    ask_id: &str,
+    answers: &[AskAnswer],
+) -> Result<ServerMessage, ServerMessage> {
+    let not_found =
+        || ServerMessage::error("not_found_error", format!("Ask '{}' not found", ask_id));
+    let (thread_id, author_pubkey, content) = {
+        let store = state.data_store.lock().unwrap();
+        let (ask, author_pubkey) = store.get_ask_event_by_id(ask_id).ok_or_else(not_found)?;
+        let thread_id = store
+            .get_thread_info_for_event(ask_id)
+            .map(|(thread_id, _)| thread_id)
+            .filter(|thread_id| {
+                session.in_scope(thread_id, &store.runtime_hierarchy.get_ancestors(thread_id))
+            })
+            .ok_or_else(not_found)?;
+        if store.is_ask_answered_by_user(ask_id) {
+            return Err(ServerMessage::error(
+                "already_answered",
+                format!("Ask '{}' has already been answered", ask_id),
+            ));
+        }
+        let content = format_ask_answers(&ask, answers)
+            .map_err(|e| ServerMessage::error("invalid_request_error", e))?;
+        (thread_id, author_pubkey, content)
+    };
+    authorize_agent(state, project, token, &author_pubkey)
+        .map_err(ServerMessage::from_api_error)?;
+
+    // Published like the daemon's answer_ask: a reply to the ask that p-tags its author
+    let (response_tx, response_rx) = std::sync::mpsc::sync_channel::<String>(1);
+    state
+        .core_handle
+        .send(NostrCommand::PublishMessage {
+            thread_id: thread_id.clone(),
+            project_a_tag: project.a_tag(),
+            content,
+            agent_pubkey: None,
+            reply_to: Some(ask_id.to_string()),
+            nudge_ids: Vec::new(),
+            skill_ids: Vec::new(),
+            ask_author_pubkey: Some(author_pubkey),
+            response_tx: Some(response_tx),
+        })
+        .map_err(|e| ServerMessage::error("server_error", format!("Failed to send: {}", e)))?;
+    let event_id = await_event_id(response_rx, "ask answer")
+        .await
+        .map_err(ServerMessage::from_api_error)?;
+    state.data_store.lock().unwrap().inbox.mark_read(ask_id);
+
+    Ok(ServerMessage::Sent {
+        thread_id,
+        event_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::protocol::AskAnswerValue;
+
+    #[test]
+    fn test_parse_client_messages() {
+        let message: ClientMessage =
+            serde_json::from_str(r#"{"type":"message","content":"hi","agent":"coder"}"#).unwrap();
+        assert_eq!(
+            message,
+            ClientMessage::Message {
+                content: "hi".to_string(),
+                agent: Some("coder".to_string()),
+            }
+        );
+
+        let message: ClientMessage = serde_json::from_str(
+            r#"{"type":"answer_ask","ask_id":"abc","answers":[{"question":"Env","type":"single_select","value":"prod"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            message,
+            ClientMessage::AnswerAsk {
+                ask_id: "abc".to_string(),
+                answers: vec![AskAnswer {
+                    question: "Env".to_string(),
+                    value: AskAnswerValue::SingleSelect {
+                        value: "prod".to_string(),
+                    },
+                }],
+            }
+        );
+
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"type":"ping"}"#).unwrap(),
+            ClientMessage::Ping
+        );
+        assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"message"}"#).is_err());
+    }
+
+    #[test]
+    fn test_server_message_json() {
+        let delta = ServerMessage::StreamDelta {
+            thread_id: "t1".to_string(),
+            agent_pubkey: "pk".to_string(),
+            author_name: Some("coder".to_string()),
+            text_delta: Some("Hel".to_string()),
+            reasoning_delta: None,
+            is_finish: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&delta).unwrap(),
+            serde_json::json!({
+                "type": "stream_delta",
+                "thread_id": "t1",
+                "agent_pubkey": "pk",
+                "author_name": "coder",
+                "text_delta": "Hel",
+                "reasoning_delta": null,
+                "is_finish": false,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ServerMessage::Pong).unwrap(),
+            serde_json::json!({ "type": "pong" })
+        );
+    }
+
+    #[test]
+    fn test_session_scope() {
+        let session = LiveSession::new(Some("root".to_string()));
+        assert!(session.in_scope("root", &[]));
+        assert!(session.in_scope("child", &["parent".to_string(), "root".to_string()]));
+        assert!(!session.in_scope("other", &["parent".to_string()]));
+        assert!(!LiveSession::new(None).in_scope("root", &[]));
+    }
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
+use tenex_cli::cli::protocol::{AskAnswer, DocumentFormat, SubscribeFilter};
+use tenex_cli::cli::usage::{parse_date_range, UsageFormat, UsageGroup};
 use tenex_cli::cli::{
     is_daemon_running, run_daemon, send_command, socket_path, CliCommand, CliConfig,
 };
@@ -27,9 +29,9 @@ struct Cli {
     #[arg(long)]
     http: bool,
 
-    /// HTTP server bind address (requires --http)
-    #[arg(long, default_value = "127.0.0.1:8080")]
-    http_bind: String,
+    /// HTTP server bind address (default: http.bind in config.json, or 127.0.0.1:8080)
+    #[arg(long)]
+    http_bind: Option<String>,
 
     #[command(subcommand)]
     command: Option<Commands>,
@@ -78,6 +80,11 @@ enum Commands {
         /// Wait for agent reply (max seconds to wait)
         #[arg(long, short)]
         wait: Option<u64>,
+        /// Wait until every agent in the thread and its delegations has stopped,
+        /// then print the final answer and the delegation tree (--wait sets the max seconds;
+        /// exits 1 on timeout)
+        #[arg(long)]
+        until_idle: bool,
         /// Wait for project roster (31933 event) before proceeding
         #[arg(long, short = 'W')]
         wait_for_project: bool,
@@ -99,6 +106,11 @@ enum Commands {
         /// Wait for agent reply (max seconds to wait)
         #[arg(long, short)]
         wait: Option<u64>,
+        /// Wait until every agent in the thread and its delegations has stopped,
+        /// then print the final answer and the delegation tree (--wait sets the max seconds;
+        /// exits 1 on timeout)
+        #[arg(long)]
+        until_idle: bool,
         /// Wait for project roster (31933 event) before proceeding
         #[arg(long, short = 'W')]
         wait_for_project: bool,
@@ -209,6 +221,481 @@ enum Commands {
         #[command(subcommand)]
         command: BunkerCommands,
     },
+
+    /// Live-tail a thread: print its messages, then stream new messages and
+    /// deltas (including delegated sub-conversations) until no agents are active
+    Follow {
+        /// Thread ID (event ID)
+        thread_id: String,
+    },
+
+    /// Run an MCP server on stdio so MCP clients can list projects and
+    /// agents, start threads and exchange messages with TENEX agents
+    Mcp,
+
+    /// Stream daemon events (messages, stream deltas, project and operations
+    /// status, inbox items, bunker requests) as NDJSON until interrupted
+    Subscribe {
+        /// Only events for this project slug (can be specified multiple times)
+        #[arg(long, short = 'p')]
+        project: Vec<String>,
+        /// Only events for this thread ID (can be specified multiple times)
+        #[arg(long, short = 't')]
+        thread: Vec<String>,
+        /// Only this event type (can be specified multiple times): message,
+        /// stream_delta, project_status, operations_status, inbox, bunker_request
+        #[arg(long, short = 'e')]
+        event: Vec<String>,
+    },
+
+    /// Edit a project's agent list one change at a time, or delete the project.
+    /// Roster edits keep the current kind:31933 agent order; the first agent is the PM.
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommands,
+    },
+
+    /// Manage installed agents
+    Agent {
+        #[command(subcommand)]
+        command: AgentCommands,
+    },
+
+    /// Publish, fork, delete and install agent definitions (kind:4199)
+    #[command(alias = "agent-def")]
+    AgentDefinition {
+        #[command(subcommand)]
+        command: AgentDefinitionCommands,
+    },
+
+    /// Manage nudges (kind:4201)
+    Nudge {
+        #[command(subcommand)]
+        command: NudgeCommands,
+    },
+
+    /// LLM cost, prompt/completion tokens and runtime from agent messages.
+    /// Dates are UTC; --since and --until are inclusive.
+    Usage {
+        /// First day (YYYY-MM-DD) or month (YYYY-MM) to include
+        #[arg(long)]
+        since: Option<String>,
+        /// Last day (YYYY-MM-DD) or month (YYYY-MM) to include
+        #[arg(long)]
+        until: Option<String>,
+        /// A single month (YYYY-MM), e.g. for a monthly export
+        #[arg(long, conflicts_with_all = ["since", "until"])]
+        month: Option<String>,
+        /// Group by project, agent, model or day
+        #[arg(long, short = 'g', default_value = "project")]
+        group_by: String,
+        /// Only usage in this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Output format: table, csv or json
+        #[arg(long, short = 'f', default_value = "table")]
+        format: String,
+    },
+
+    /// Show recent webhook deliveries (webhooks are set up in config.json)
+    WebhookLog {
+        /// Only deliveries to this webhook name
+        #[arg(long)]
+        hook: Option<String>,
+        /// Number of deliveries to show
+        #[arg(long, short = 'n', default_value = "20")]
+        limit: usize,
+    },
+
+    /// List unread inbox items (asks and mentions) and unanswered asks
+    Inbox {
+        /// Only items for this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Include items already marked as read
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Answer an agent's ask event. Prompts for each question unless
+    /// --json is given.
+    ///
+    /// The JSON is a list with one answer per question, matched by title:
+    /// {"question": "<title>", "type": "single_select", "value": "<suggestion>"},
+    /// {"question": "<title>", "type": "multi_select", "values": ["<option>", ...]} or
+    /// {"question": "<title>", "type": "custom_text", "value": "<text>"}
+    Answer {
+        /// Ask event ID
+        ask_id: String,
+        /// Read answers from a JSON file ("-" for stdin)
+        #[arg(long)]
+        json: Option<String>,
+    },
+
+    /// List reports (kind:30023 events), latest version of each
+    ListReports {
+        /// Only reports in this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Only reports by this author (pubkey or agent name)
+        #[arg(long, short = 'a')]
+        author: Option<String>,
+        /// Only reports with any of these hashtags (can be specified multiple times)
+        #[arg(long, short = 't')]
+        hashtag: Vec<String>,
+    },
+
+    /// Show a report as JSON, raw markdown (--raw) or rendered text (--render)
+    ShowReport {
+        /// Report slug (d-tag) or 30023:<pubkey>:<slug> coordinate
+        report: String,
+        /// Print only the markdown content
+        #[arg(long, conflicts_with = "render")]
+        raw: bool,
+        /// Print the report rendered for the terminal
+        #[arg(long)]
+        render: bool,
+    },
+
+    /// List lessons (kind:4129 events), newest first
+    ListLessons {
+        /// Only lessons in this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Only lessons by this author (pubkey or agent name)
+        #[arg(long, short = 'a')]
+        author: Option<String>,
+        /// Only lessons with any of these hashtags or categories (can be specified multiple times)
+        #[arg(long, short = 't')]
+        hashtag: Vec<String>,
+    },
+
+    /// Show a lesson as JSON, raw markdown (--raw) or rendered text (--render)
+    ShowLesson {
+        /// Lesson event ID
+        lesson_id: String,
+        /// Print only the markdown content
+        #[arg(long, conflicts_with = "render")]
+        raw: bool,
+        /// Print the lesson and its sections rendered for the terminal
+        #[arg(long)]
+        render: bool,
+    },
+
+    /// Start a thread about a report in the report's project.
+    /// Goes to the report's author if it is on the project, otherwise the PM.
+    DiscussReport {
+        /// Report slug (d-tag) or 30023:<pubkey>:<slug> coordinate
+        report: String,
+        /// Message content
+        content: String,
+        /// Agent slug to address instead of the default
+        #[arg(long, short = 'a')]
+        agent: Option<String>,
+    },
+
+    /// Stop running agents (publishes kind:24134): a thread, a thread and its
+    /// delegations, one agent, or every agent in a project.
+    /// Prints the conversations and agents that were signaled.
+    Stop {
+        /// Project slug (d-tag); required unless --thread is given
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Thread ID (event ID) to stop
+        #[arg(long, short = 't')]
+        thread: Option<String>,
+        /// Also stop the delegated sub-conversations of --thread
+        #[arg(long, short = 'd', requires = "thread")]
+        descendants: bool,
+        /// Only stop this agent slug
+        #[arg(long, short = 'a')]
+        agent: Option<String>,
+        /// Reason recorded in the stop event
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Manage bearer tokens for the HTTP server.
+    /// Once any token exists, every HTTP request must present one.
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+
+    /// Manage recurring prompts sent by the daemon
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectCommands {
+    /// Add an agent to the project (appended unless --position is given)
+    AddAgent {
+        /// Project slug (d-tag)
+        project_slug: String,
+        /// Agent slug or hex pubkey
+        agent: String,
+        /// 0-based position in the agent list (0 makes it the PM)
+        #[arg(long)]
+        position: Option<usize>,
+    },
+    /// Remove an agent from the project (the PM cannot be removed while
+    /// other agents remain; use set-pm first)
+    RemoveAgent {
+        /// Project slug (d-tag)
+        project_slug: String,
+        /// Agent slug or hex pubkey
+        agent: String,
+    },
+    /// Make an agent the project manager (moves it to the front of the list)
+    SetPm {
+        /// Project slug (d-tag)
+        project_slug: String,
+        /// Agent slug or hex pubkey
+        agent: String,
+    },
+    /// Move an agent to another position in the agent list
+    MoveAgent {
+        /// Project slug (d-tag)
+        project_slug: String,
+        /// Agent slug or hex pubkey
+        agent: String,
+        /// 0-based position (0 makes it the PM)
+        position: usize,
+    },
+    /// Delete the project (republishes kind:31933 with a deleted tag)
+    Delete {
+        /// Project slug (d-tag)
+        project_slug: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentCommands {
+    /// Delete an agent (kind:24030): from one project with --project,
+    /// otherwise from every project on its backend
+    Delete {
+        /// Agent slug or hex pubkey
+        agent: String,
+        /// Only remove the agent from this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Reason recorded in the event
+        #[arg(long)]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentDefinitionCommands {
+    /// Publish a new agent definition. Instructions are read from --file, or from stdin.
+    Create {
+        /// Agent name
+        #[arg(long)]
+        name: String,
+        /// Short description
+        #[arg(long)]
+        description: String,
+        /// Agent role
+        #[arg(long, default_value = "assistant")]
+        role: String,
+        /// Version
+        #[arg(long, default_value = "1")]
+        version: String,
+        /// File containing the instructions ("-" for stdin)
+        #[arg(long, short = 'f')]
+        file: Option<String>,
+    },
+    /// Fork an agent definition: publish a new version that references it.
+    /// Omitted options are taken from the source; the version is bumped.
+    Fork {
+        /// Event ID of the definition to fork
+        source_id: String,
+        /// Agent name
+        #[arg(long)]
+        name: Option<String>,
+        /// Short description
+        #[arg(long)]
+        description: Option<String>,
+        /// Agent role
+        #[arg(long)]
+        role: Option<String>,
+        /// Version (default: source version + 1)
+        #[arg(long)]
+        version: Option<String>,
+        /// File containing the new instructions ("-" for stdin)
+        #[arg(long, short = 'f')]
+        file: Option<String>,
+    },
+    /// Delete an agent definition (publishes kind:5; only the author can delete)
+    Delete {
+        /// Agent definition event ID
+        id: String,
+    },
+    /// Ask a backend to install an agent definition (kind:24001)
+    Install {
+        /// Agent definition event ID
+        id: String,
+        /// Backend pubkey (hex) to install on
+        #[arg(long, conflicts_with = "project")]
+        backend: Option<String>,
+        /// Install on the backend running this project slug
+        #[arg(long, short = 'p')]
+        project: Option<String>,
+        /// Slug for the installed agent (default: derived by the backend)
+        #[arg(long)]
+        slug: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum NudgeCommands {
+    /// List nudges
+    List,
+    /// Create a nudge. The body is read from --file, or from stdin.
+    ///
+    /// Tool permissions use one of two modes: exclusive (--only-tool, the
+    /// agent gets exactly these tools) or additive (--allow-tool/--deny-tool).
+    Create {
+        /// Nudge title
+        #[arg(long)]
+        title: String,
+        /// Short description
+        #[arg(long, default_value = "")]
+        description: String,
+        /// File containing the nudge body ("-" for stdin)
+        #[arg(long, short = 'f')]
+        file: Option<String>,
+        /// Hashtag (can be specified multiple times)
+        #[arg(long = "hashtag", short = 't')]
+        hashtag: Vec<String>,
+        /// Tool to add to the agent's tools (can be specified multiple times)
+        #[arg(long = "allow-tool")]
+        allow_tool: Vec<String>,
+        /// Tool to remove from the agent's tools (can be specified multiple times)
+        #[arg(long = "deny-tool")]
+        deny_tool: Vec<String>,
+        /// Exclusive tool list (can be specified multiple times)
+        #[arg(long = "only-tool", conflicts_with_all = ["allow_tool", "deny_tool"])]
+        only_tool: Vec<String>,
+    },
+    /// Update a nudge (publishes a superseding kind:4201).
+    /// Omitted options keep their current value; any tool option replaces
+    /// all tool permissions.
+    Update {
+        /// Nudge event ID
+        nudge_id: String,
+        /// New title
+        #[arg(long)]
+        title: Option<String>,
+        /// New description
+        #[arg(long)]
+        description: Option<String>,
+        /// File containing the new nudge body ("-" for stdin)
+        #[arg(long, short = 'f')]
+        file: Option<String>,
+        /// Replace hashtags (can be specified multiple times)
+        #[arg(long = "hashtag", short = 't')]
+        hashtag: Vec<String>,
+        /// Tool to add to the agent's tools (can be specified multiple times)
+        #[arg(long = "allow-tool")]
+        allow_tool: Vec<String>,
+        /// Tool to remove from the agent's tools (can be specified multiple times)
+        #[arg(long = "deny-tool")]
+        deny_tool: Vec<String>,
+        /// Exclusive tool list (can be specified multiple times)
+        #[arg(long = "only-tool", conflicts_with_all = ["allow_tool", "deny_tool"])]
+        only_tool: Vec<String>,
+    },
+    /// Delete a nudge (publishes kind:5; only the author can delete)
+    Delete {
+        /// Nudge event ID
+        nudge_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    /// Create a token (the secret is printed once and not stored)
+    Create {
+        /// Label for the token
+        name: String,
+        /// Project slug (d-tag) the token may access (can be specified multiple times)
+        #[arg(long, short = 'p', required = true)]
+        project: Vec<String>,
+        /// Agent slug the token may address (can be specified multiple times).
+        /// Omit to allow every agent in the allowed projects.
+        #[arg(long, short = 'a')]
+        agent: Vec<String>,
+    },
+    /// List tokens (secrets are never shown)
+    List,
+    /// Revoke a token by id or name
+    Revoke {
+        /// Token id (tok_...) or name
+        token: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleCommands {
+    /// Add a job that sends a prompt at the times given by a cron expression
+    /// (minute hour day month weekday, in the daemon's local time)
+    Add {
+        /// Unique job name
+        name: String,
+        /// Cron expression, e.g. "30 9 * * 1-5", or @hourly/@daily/@weekly/@monthly
+        #[arg(long)]
+        cron: String,
+        /// Project slug (d-tag)
+        #[arg(long, short = 'p')]
+        project: String,
+        /// Agent slug to address (default: the project manager)
+        #[arg(long, short = 'a')]
+        agent: Option<String>,
+        /// Title for the threads created (default: the job name)
+        #[arg(long)]
+        title: Option<String>,
+        /// Post into this thread instead of starting a new thread each run
+        #[arg(long)]
+        thread: Option<String>,
+        /// Skill event IDs to attach (can be specified multiple times)
+        #[arg(long, short = 'S')]
+        skill: Vec<String>,
+        /// Nudge event IDs to attach (can be specified multiple times)
+        #[arg(long, short = 'N')]
+        nudge: Vec<String>,
+        /// Prompt content (all remaining arguments are joined)
+        #[arg(trailing_var_arg = true, num_args = 1..)]
+        message: Vec<String>,
+    },
+    /// List jobs with their next and last run
+    List,
+    /// Stop a job from running until it is resumed
+    Pause {
+        /// Job id (job_...) or name
+        job: String,
+    },
+    /// Resume a paused job
+    Resume {
+        /// Job id (job_...) or name
+        job: String,
+    },
+    /// Remove a job
+    Remove {
+        /// Job id (job_...) or name
+        job: String,
+    },
+    /// Show a job's runs and the threads they created, newest first
+    History {
+        /// Job id (job_...) or name
+        job: String,
+        /// Number of runs to show
+        #[arg(long, short = 'n', default_value = "20")]
+        limit: usize,
+    },
 }
 
 #[derive(Subcommand)]
@@ -270,7 +757,18 @@ fn main() {
         .unwrap_or_else(CoreConfig::default_data_dir);
 
     // Load config from data_dir/config.json if exists
-    let config = load_config(&data_dir);
+    let config = match load_config(&data_dir) {
+        Ok(config) => config,
+        // The daemon must not start without its configured relays, tokens and webhooks
+        Err(e) if cli.daemon => {
+            eprintln!("Daemon error: {:#}", e);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Warning: Failed to load config: {}", e);
+            None
+        }
+    };
 
     // Run daemon mode
     if cli.daemon {
@@ -299,6 +797,7 @@ fn main() {
             thread_id,
             recipient_slug,
             wait,
+            until_idle,
             wait_for_project,
             skill,
             message,
@@ -308,6 +807,7 @@ fn main() {
             recipient_slug,
             content: message.join(" "),
             wait_secs: wait,
+            until_idle,
             wait_for_project,
             skill_ids: validate_skill_ids(skill),
         },
@@ -315,6 +815,7 @@ fn main() {
             project_slug,
             recipient_slug,
             wait,
+            until_idle,
             wait_for_project,
             skill,
             message,
@@ -323,6 +824,7 @@ fn main() {
             recipient_slug,
             content: message.join(" "),
             wait_secs: wait,
+            until_idle,
             wait_for_project,
             skill_ids: validate_skill_ids(skill),
         },
@@ -490,6 +992,307 @@ fn main() {
             },
             BunkerCommands::Audit { limit } => CliCommand::BunkerAudit { limit },
         },
+        Some(Commands::Follow { thread_id }) => CliCommand::Follow { thread_id },
+        Some(Commands::Mcp) => CliCommand::Mcp,
+        Some(Commands::Subscribe {
+            project,
+            thread,
+            event,
+        }) => CliCommand::Subscribe {
+            filter: SubscribeFilter {
+                projects: project,
+                threads: thread,
+                descendants: false,
+                events: event,
+            },
+        },
+        Some(Commands::Project { command }) => match command {
+            ProjectCommands::AddAgent {
+                project_slug,
+                agent,
+                position,
+            } => CliCommand::EditProjectRoster {
+                project_slug,
+                action: "add".to_string(),
+                agent,
+                position,
+            },
+            ProjectCommands::RemoveAgent {
+                project_slug,
+                agent,
+            } => CliCommand::EditProjectRoster {
+                project_slug,
+                action: "remove".to_string(),
+                agent,
+                position: None,
+            },
+            ProjectCommands::SetPm {
+                project_slug,
+                agent,
+            } => CliCommand::EditProjectRoster {
+                project_slug,
+                action: "set_pm".to_string(),
+                agent,
+                position: None,
+            },
+            ProjectCommands::MoveAgent {
+                project_slug,
+                agent,
+                position,
+            } => CliCommand::EditProjectRoster {
+                project_slug,
+                action: "move".to_string(),
+                agent,
+                position: Some(position),
+            },
+            ProjectCommands::Delete { project_slug } => CliCommand::DeleteProject { project_slug },
+        },
+        Some(Commands::Agent { command }) => match command {
+            AgentCommands::Delete {
+                agent,
+                project,
+                reason,
+            } => CliCommand::DeleteAgent {
+                agent,
+                project_slug: project,
+                reason,
+            },
+        },
+        Some(Commands::AgentDefinition { command }) => match command {
+            AgentDefinitionCommands::Create {
+                name,
+                description,
+                role,
+                version,
+                file,
+            } => CliCommand::CreateAgentDefinition {
+                name: Some(name),
+                description: Some(description),
+                role: Some(role),
+                instructions: Some(read_file_or_stdin(file.as_deref().unwrap_or("-"))),
+                version: Some(version),
+                source_id: None,
+                is_fork: false,
+            },
+            AgentDefinitionCommands::Fork {
+                source_id,
+                name,
+                description,
+                role,
+                version,
+                file,
+            } => CliCommand::CreateAgentDefinition {
+                name,
+                description,
+                role,
+                instructions: file.as_deref().map(read_file_or_stdin),
+                version,
+                source_id: Some(source_id),
+                is_fork: true,
+            },
+            AgentDefinitionCommands::Delete { id } => CliCommand::DeleteAgentDefinition { id },
+            AgentDefinitionCommands::Install {
+                id,
+                backend,
+                project,
+                slug,
+            } => CliCommand::InstallAgentDefinition {
+                id,
+                backend_pubkey: backend,
+                project_slug: project,
+                slug,
+            },
+        },
+        Some(Commands::Nudge { command }) => match command {
+            NudgeCommands::List => CliCommand::ListNudges,
+            NudgeCommands::Create {
+                title,
+                description,
+                file,
+                hashtag,
+                allow_tool,
+                deny_tool,
+                only_tool,
+            } => CliCommand::CreateNudge {
+                title,
+                description,
+                content: read_file_or_stdin(file.as_deref().unwrap_or("-")),
+                hashtags: hashtag,
+                allow_tools: allow_tool,
+                deny_tools: deny_tool,
+                only_tools: only_tool,
+            },
+            NudgeCommands::Update {
+                nudge_id,
+                title,
+                description,
+                file,
+                hashtag,
+                allow_tool,
+                deny_tool,
+                only_tool,
+            } => CliCommand::UpdateNudge {
+                nudge_id,
+                title,
+                description,
+                content: file.as_deref().map(read_file_or_stdin),
+                hashtags: hashtag,
+                allow_tools: allow_tool,
+                deny_tools: deny_tool,
+                only_tools: only_tool,
+            },
+            NudgeCommands::Delete { nudge_id } => CliCommand::DeleteNudge { nudge_id },
+        },
+        Some(Commands::Usage {
+            since,
+            until,
+            month,
+            group_by,
+            project,
+            format,
+        }) => {
+            let range = |value: Option<String>| {
+                value.map(|v| {
+                    parse_date_range(&v).unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    })
+                })
+            };
+            let (since, until) = match range(month) {
+                Some((start, end)) => (Some(start), Some(end)),
+                None => (
+                    range(since).map(|(start, _)| start),
+                    range(until).map(|(_, end)| end),
+                ),
+            };
+            let parsed = UsageGroup::parse(&group_by)
+                .and_then(|group| UsageFormat::parse(&format).map(|format| (group, format)));
+            let (group_by, format) = parsed.unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            CliCommand::Usage {
+                since,
+                until,
+                group_by,
+                project_slug: project,
+                format,
+            }
+        }
+        Some(Commands::WebhookLog { hook, limit }) => CliCommand::WebhookLog { hook, limit },
+        Some(Commands::Inbox { project, all }) => CliCommand::ListInbox {
+            project_slug: project,
+            include_read: all,
+        },
+        Some(Commands::Answer { ask_id, json }) => CliCommand::AnswerAsk {
+            ask_id,
+            answers: json.as_deref().map(|path| {
+                serde_json::from_str::<Vec<AskAnswer>>(&read_file_or_stdin(path)).unwrap_or_else(
+                    |e| {
+                        eprintln!("Error: Invalid answers JSON: {}", e);
+                        std::process::exit(1);
+                    },
+                )
+            }),
+        },
+        Some(Commands::ListReports {
+            project,
+            author,
+            hashtag,
+        }) => CliCommand::ListReports {
+            project_slug: project,
+            author,
+            hashtags: hashtag,
+        },
+        Some(Commands::ShowReport {
+            report,
+            raw,
+            render,
+        }) => CliCommand::ShowReport {
+            report,
+            format: document_format(raw, render),
+        },
+        Some(Commands::ListLessons {
+            project,
+            author,
+            hashtag,
+        }) => CliCommand::ListLessons {
+            project_slug: project,
+            author,
+            hashtags: hashtag,
+        },
+        Some(Commands::ShowLesson {
+            lesson_id,
+            raw,
+            render,
+        }) => CliCommand::ShowLesson {
+            lesson_id,
+            format: document_format(raw, render),
+        },
+        Some(Commands::DiscussReport {
+            report,
+            content,
+            agent,
+        }) => CliCommand::DiscussReport {
+            report,
+            recipient_slug: agent,
+            content,
+        },
+        Some(Commands::Stop {
+            project,
+            thread,
+            descendants,
+            agent,
+            reason,
+        }) => CliCommand::StopOperations {
+            project_slug: project,
+            thread_id: thread,
+            agent_slug: agent,
+            descendants,
+            reason,
+        },
+        Some(Commands::Token { command }) => match command {
+            TokenCommands::Create {
+                name,
+                project,
+                agent,
+            } => CliCommand::ApiTokenCreate {
+                name,
+                projects: project,
+                agents: agent,
+            },
+            TokenCommands::List => CliCommand::ApiTokenList,
+            TokenCommands::Revoke { token } => CliCommand::ApiTokenRevoke { token },
+        },
+        Some(Commands::Schedule { command }) => match command {
+            ScheduleCommands::Add {
+                name,
+                cron,
+                project,
+                agent,
+                title,
+                thread,
+                skill,
+                nudge,
+                message,
+            } => CliCommand::ScheduleAdd {
+                name,
+                cron,
+                project_slug: project,
+                agent_slug: agent,
+                title,
+                thread_id: thread,
+                content: message.join(" "),
+                skill_ids: skill,
+                nudge_ids: nudge,
+            },
+            ScheduleCommands::List => CliCommand::ScheduleList,
+            ScheduleCommands::Pause { job } => CliCommand::SchedulePause { job, paused: true },
+            ScheduleCommands::Resume { job } => CliCommand::SchedulePause { job, paused: false },
+            ScheduleCommands::Remove { job } => CliCommand::ScheduleRemove { job },
+            ScheduleCommands::History { job, limit } => CliCommand::ScheduleHistory { job, limit },
+        },
         None => {
             // No command - show help
             eprintln!("No command specified. Use --help for usage.");
@@ -505,17 +1308,45 @@ fn main() {
 }
 
 /// Load configuration from data_dir/config.json if it exists
-fn load_config(data_dir: &std::path::Path) -> Option<CliConfig> {
+fn load_config(data_dir: &std::path::Path) -> anyhow::Result<Option<CliConfig>> {
     let config_path = data_dir.join("config.json");
-    if config_path.exists() {
-        match CliConfig::load(&config_path) {
-            Ok(config) => return Some(config),
-            Err(e) => {
-                eprintln!("Warning: Failed to load config: {}", e);
-            }
+    if !config_path.exists() {
+        return Ok(None);
+    }
+    CliConfig::load(&config_path).map(Some)
+}
+
+/// Read a nudge body or agent instructions from a file, or from stdin when
+/// the path is "-".
+/// Exits with an error if it cannot be read.
+fn read_file_or_stdin(path: &str) -> String {
+    use std::io::Read;
+
+    let result = if path == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| content)
+    } else {
+        std::fs::read_to_string(path)
+    };
+
+    match result {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error: Failed to read {}: {}", path, e);
+            std::process::exit(1);
         }
     }
-    None
+}
+
+/// Output format from the `--raw` / `--render` flags (JSON by default)
+fn document_format(raw: bool, render: bool) -> DocumentFormat {
+    match (raw, render) {
+        (true, _) => DocumentFormat::Raw,
+        (_, true) => DocumentFormat::Rendered,
+        _ => DocumentFormat::Json,
+    }
 }
 
 /// Validate and normalize skill IDs.
@@ -12,6 +12,12 @@ pub struct Lesson {
     pub metacognition: Option<String>,
     pub reflection: Option<String>,
     pub category: Option<String>,
+    /// Project the lesson was learned in (a-tag)
+    #[serde(default)]
+    pub project_a_tag: Option<String>,
+    /// Hashtags (t-tags)
+    #[serde(default)]
+    pub hashtags: Vec<String>,
     pub created_at: u64,
 }
 
@@ -33,6 +39,8 @@ impl Lesson {
         let mut metacognition: Option<String> = None;
         let mut reflection: Option<String> = None;
         let mut category: Option<String> = None;
+        let mut project_a_tag: Option<String> = None;
+        let mut hashtags: Vec<String> = Vec::new();
 
         // Parse tags
         for tag in note.tags() {
@@ -46,6 +54,10 @@ impl Lesson {
                             "metacognition" => metacognition = Some(value.to_string()),
                             "reflection" => reflection = Some(value.to_string()),
                             "category" => category = Some(value.to_string()),
+                            "a" if value.starts_with("31933:") => {
+                                project_a_tag = Some(value.to_string())
+                            }
+                            "t" => hashtags.push(value.to_string()),
                             _ => {}
                         }
                     }
@@ -63,6 +75,8 @@ impl Lesson {
             metacognition,
             reflection,
             category,
+            project_a_tag,
+            hashtags,
             created_at,
         })
     }
@@ -126,6 +140,8 @@ mod tests {
             metacognition: None,
             reflection: None,
             category: None,
+            project_a_tag: None,
+            hashtags: vec![],
             created_at: 0,
         };
 
@@ -144,6 +160,8 @@ mod tests {
             metacognition: Some("Meta content".to_string()),
             reflection: None,
             category: None,
+            project_a_tag: None,
+            hashtags: vec![],
             created_at: 0,
         };
 
@@ -1,18 +1,34 @@
-//! Blossom blob upload for images
+//! Blossom blob upload for images and other attachments
 //!
 //! Blossom is a protocol for storing blobs on Nostr.
 //! See: https://github.com/hzrd149/blossom
 
 use nostr_sdk::{EventBuilder, Keys, Kind, Tag, TagKind, Timestamp};
-use reqwest::Client;
+use reqwest::{redirect, Client, Url};
 use sha2::{Digest, Sha256};
 use std::io::Write;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::constants::BLOSSOM_SERVER;
 
+/// Largest blob fetched from a remote URL for re-upload (50 MiB)
+const MAX_REMOTE_BLOB_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Redirects followed when fetching a remote URL; each hop is re-validated
+const MAX_REMOTE_REDIRECTS: usize = 5;
+
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REMOTE_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const REMOTE_TOTAL_TIMEOUT: Duration = Duration::from_secs(120);
+
 /// Upload an image to Blossom and return the URL
 pub async fn upload_image(data: &[u8], keys: &Keys, mime_type: &str) -> anyhow::Result<String> {
+    upload_blob(data, keys, mime_type).await
+}
+
+/// Upload a blob of any type to Blossom and return the URL
+pub async fn upload_blob(data: &[u8], keys: &Keys, mime_type: &str) -> anyhow::Result<String> {
     // Calculate SHA-256 hash of the blob
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -57,6 +73,160 @@ pub async fn upload_image(data: &[u8], keys: &Keys, mime_type: &str) -> anyhow::
     Ok(blob_descriptor.url)
 }
 
+/// Download a remote file and re-upload it to Blossom, returning the Blossom URL.
+/// Only public http(s) hosts are fetched: loopback, private and link-local
+/// addresses are refused so clients cannot reach the daemon's network.
+pub async fn upload_from_url(url: &str, keys: &Keys) -> anyhow::Result<String> {
+    let mut url = Url::parse(url)?;
+    let mut redirects = 0;
+    let mut response = loop {
+        let response = fetch_public_url(&url).await?;
+        if !response.status().is_redirection() {
+            break response;
+        }
+        redirects += 1;
+        if redirects > MAX_REMOTE_REDIRECTS {
+            anyhow::bail!("Too many redirects fetching {}", url);
+        }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| anyhow::anyhow!("Redirect from {} without a location", url))?;
+        url = url.join(location)?;
+    };
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to fetch {}: {}", url, response.status());
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_REMOTE_BLOB_BYTES)
+    {
+        anyhow::bail!("{} exceeds {} bytes", url, MAX_REMOTE_BLOB_BYTES);
+    }
+
+    let mime_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    // The declared length may be missing or wrong, so count while streaming
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (data.len() + chunk.len()) as u64 > MAX_REMOTE_BLOB_BYTES {
+            anyhow::bail!("{} exceeds {} bytes", url, MAX_REMOTE_BLOB_BYTES);
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    upload_blob(&data, keys, &mime_type).await
+}
+
+/// Send a GET for one hop of a remote fetch, without following redirects.
+/// The host is resolved up front and the connection pinned to the checked
+/// addresses, so a second DNS answer cannot point it elsewhere.
+async fn fetch_public_url(url: &Url) -> anyhow::Result<reqwest::Response> {
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Unsupported URL scheme '{}'", url.scheme());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL {} has no host", url))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow::anyhow!("URL {} has no port", url))?;
+
+    // IPv6 literals keep their brackets in host_str
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((bare_host, port)).await?.collect();
+    if addrs.is_empty() {
+        anyhow::bail!("Could not resolve {}", host);
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        anyhow::bail!(
+            "Refusing to fetch {}: {} is not a public address",
+            url,
+            addr.ip()
+        );
+    }
+
+    let client = Client::builder()
+        .redirect(redirect::Policy::none())
+        .connect_timeout(REMOTE_CONNECT_TIMEOUT)
+        .read_timeout(REMOTE_READ_TIMEOUT)
+        .timeout(REMOTE_TOTAL_TIMEOUT)
+        .resolve_to_addrs(bare_host, &addrs)
+        .build()?;
+    Ok(client.get(url.clone()).send().await?)
+}
+
+/// Whether an address is publicly routable (not loopback, private, link-local,
+/// shared, multicast, documentation or otherwise reserved)
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking 198.18.0.0/15
+                || (a == 198 && (b == 18 || b == 19))
+                // Reserved 240.0.0.0/4
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local fe80::/10 and deprecated site-local fec0::/10
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0
+                // Documentation 2001:db8::/32
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// Decode a base64 `data:` URL into its MIME type and bytes
+pub fn decode_data_url(url: &str) -> anyhow::Result<(String, Vec<u8>)> {
+    let rest = url
+        .strip_prefix("data:")
+        .ok_or_else(|| anyhow::anyhow!("not a data URL"))?;
+    let (meta, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("malformed data URL"))?;
+    let meta = meta
+        .strip_suffix(";base64")
+        .ok_or_else(|| anyhow::anyhow!("only base64 data URLs are supported"))?;
+
+    let mime_type = meta.split(';').next().unwrap_or_default().trim();
+    let mime_type = if mime_type.is_empty() {
+        "application/octet-stream"
+    } else {
+        mime_type
+    };
+
+    Ok((mime_type.to_string(), base64_decode(payload)?))
+}
+
 #[derive(serde::Deserialize)]
 struct BlobDescriptor {
     url: String,
@@ -72,6 +242,40 @@ fn base64_encode(input: &str) -> String {
     String::from_utf8(buf).unwrap_or_default()
 }
 
+/// Decode standard or URL-safe base64, ignoring whitespace and padding
+pub fn base64_decode(input: &str) -> anyhow::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits: u8 = 0;
+
+    for byte in input.bytes() {
+        if byte == b'=' {
+            break;
+        }
+        if byte.is_ascii_whitespace() {
+            continue;
+        }
+        let value = match byte {
+            b'-' => 62,
+            b'_' => 63,
+            _ => BASE64_CHARS
+                .iter()
+                .position(|&c| c == byte)
+                .ok_or_else(|| anyhow::anyhow!("invalid base64 character '{}'", byte as char))?
+                as u32,
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(output)
+}
+
 // Simple base64 encoder
 struct Base64Encoder<'a> {
     output: &'a mut Vec<u8>,
@@ -128,3 +332,69 @@ impl<'a> Drop for Base64Encoder<'a> {
         self.flush().ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_round_trip() {
+        for input in ["", "a", "ab", "abc", "hello blossom"] {
+            let encoded = base64_encode(input);
+            assert_eq!(base64_decode(&encoded).unwrap(), input.as_bytes());
+        }
+        assert!(base64_decode("not*base64").is_err());
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        let (mime, data) = decode_data_url("data:image/png;base64,aGVsbG8=").unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(data, b"hello");
+
+        let (mime, _) = decode_data_url("data:;base64,aGVsbG8=").unwrap();
+        assert_eq!(mime, "application/octet-stream");
+
+        assert!(decode_data_url("data:text/plain,hello").is_err());
+        assert!(decode_data_url("https://example.com/a.png").is_err());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.5",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_refuses_local_and_non_http_urls() {
+        for url in [
+            "http://127.0.0.1:9/blob",
+            "http://[::1]/blob",
+            "http://localhost/blob",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                fetch_public_url(&Url::parse(url).unwrap()).await.is_err(),
+                "{}",
+                url
+            );
+        }
+    }
+}
@@ -7,7 +7,8 @@ pub use auth::{
     credentials_need_password, get_current_pubkey, has_stored_credentials, load_stored_keys,
     load_unencrypted_keys,
 };
-pub use blossom::upload_image;
+pub use blossom::{base64_decode, decode_data_url, upload_blob, upload_from_url, upload_image};
 pub use worker::{
-    elapsed_ms, log_to_file, set_log_path, DataChange, EventIdSender, NostrCommand, NostrWorker,
+    elapsed_ms, log_to_file, set_log_level, set_log_path, DataChange, EventIdSender, LogLevel,
+    NostrCommand, NostrWorker,
 };
@@ -1,6 +1,7 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
@@ -169,12 +170,44 @@ impl NostrDatabase for EphemeralFilteringNdbDatabase {
 static START_TIME: OnceLock<Instant> = OnceLock::new();
 static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
 static LOG_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Which `tlog!` lines are written: ERROR lines always, DEBUG lines only at
+/// `Debug`, every other tag from `Info` up.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error = 0,
+    #[default]
+    Info = 1,
+    Debug = 2,
+}
 
 /// Set the log file path. Must be called before any logging occurs.
 pub fn set_log_path(path: PathBuf) {
     let _ = LOG_PATH.set(path);
 }
 
+/// Set the log level. Can be changed at any time.
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+fn log_level_enabled(level: LogLevel) -> bool {
+    LOG_LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
 fn get_log_path() -> PathBuf {
     LOG_PATH
         .get()
@@ -187,6 +220,17 @@ pub fn elapsed_ms() -> u64 {
 }
 
 pub fn log_to_file(tag: &str, msg: &str) {
+    let level = match tag {
+        "ERROR" => LogLevel::Error,
+        "DEBUG" => LogLevel::Debug,
+        _ => LogLevel::Info,
+    };
+    if log_level_enabled(level) {
+        write_log_line(tag, msg);
+    }
+}
+
+fn write_log_line(tag: &str, msg: &str) {
     let lock = LOG_LOCK.get_or_init(|| Mutex::new(()));
     if let Ok(_guard) = lock.lock() {
         if let Ok(mut file) = std::fs::OpenOptions::new()
@@ -207,11 +251,12 @@ macro_rules! tlog {
 }
 
 fn debug_log(msg: &str) {
-    if std::env::var("TENEX_DEBUG")
-        .map(|v| v == "1")
-        .unwrap_or(false)
+    if log_level_enabled(LogLevel::Debug)
+        || std::env::var("TENEX_DEBUG")
+            .map(|v| v == "1")
+            .unwrap_or(false)
     {
-        tlog!("DEBUG", "{}", msg);
+        write_log_line("DEBUG", msg);
     }
 }
 
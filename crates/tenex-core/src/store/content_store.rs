@@ -399,6 +399,8 @@ mod tests {
             metacognition: None,
             reflection: None,
             category: None,
+            project_a_tag: None,
+            hashtags: vec![],
             created_at,
         }
     }